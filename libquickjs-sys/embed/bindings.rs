@@ -246,7 +246,7 @@ fn bindgen_test_layout___fsid_t() {
         concat!("Alignment of ", stringify!(__fsid_t))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<__fsid_t>())).__val as *const _ as usize },
+        ::std::mem::offset_of!(__fsid_t, __val),
         0usize,
         concat!(
             "Offset of field: ",
@@ -310,7 +310,7 @@ fn bindgen_test_layout___mbstate_t__bindgen_ty_1() {
         concat!("Alignment of ", stringify!(__mbstate_t__bindgen_ty_1))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<__mbstate_t__bindgen_ty_1>())).__wch as *const _ as usize },
+        ::std::mem::offset_of!(__mbstate_t__bindgen_ty_1, __wch),
         0usize,
         concat!(
             "Offset of field: ",
@@ -320,9 +320,7 @@ fn bindgen_test_layout___mbstate_t__bindgen_ty_1() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<__mbstate_t__bindgen_ty_1>())).__wchb as *const _ as usize
-        },
+        ::std::mem::offset_of!(__mbstate_t__bindgen_ty_1, __wchb),
         0usize,
         concat!(
             "Offset of field: ",
@@ -345,7 +343,7 @@ fn bindgen_test_layout___mbstate_t() {
         concat!("Alignment of ", stringify!(__mbstate_t))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<__mbstate_t>())).__count as *const _ as usize },
+        ::std::mem::offset_of!(__mbstate_t, __count),
         0usize,
         concat!(
             "Offset of field: ",
@@ -355,7 +353,7 @@ fn bindgen_test_layout___mbstate_t() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<__mbstate_t>())).__value as *const _ as usize },
+        ::std::mem::offset_of!(__mbstate_t, __value),
         4usize,
         concat!(
             "Offset of field: ",
@@ -384,7 +382,7 @@ fn bindgen_test_layout__G_fpos_t() {
         concat!("Alignment of ", stringify!(_G_fpos_t))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_G_fpos_t>())).__pos as *const _ as usize },
+        ::std::mem::offset_of!(_G_fpos_t, __pos),
         0usize,
         concat!(
             "Offset of field: ",
@@ -394,7 +392,7 @@ fn bindgen_test_layout__G_fpos_t() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_G_fpos_t>())).__state as *const _ as usize },
+        ::std::mem::offset_of!(_G_fpos_t, __state),
         8usize,
         concat!(
             "Offset of field: ",
@@ -424,7 +422,7 @@ fn bindgen_test_layout__G_fpos64_t() {
         concat!("Alignment of ", stringify!(_G_fpos64_t))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_G_fpos64_t>())).__pos as *const _ as usize },
+        ::std::mem::offset_of!(_G_fpos64_t, __pos),
         0usize,
         concat!(
             "Offset of field: ",
@@ -434,7 +432,7 @@ fn bindgen_test_layout__G_fpos64_t() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_G_fpos64_t>())).__state as *const _ as usize },
+        ::std::mem::offset_of!(_G_fpos64_t, __state),
         8usize,
         concat!(
             "Offset of field: ",
@@ -509,7 +507,7 @@ fn bindgen_test_layout__IO_FILE() {
         concat!("Alignment of ", stringify!(_IO_FILE))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._flags as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _flags),
         0usize,
         concat!(
             "Offset of field: ",
@@ -519,7 +517,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._IO_read_ptr as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _IO_read_ptr),
         8usize,
         concat!(
             "Offset of field: ",
@@ -529,7 +527,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._IO_read_end as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _IO_read_end),
         16usize,
         concat!(
             "Offset of field: ",
@@ -539,7 +537,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._IO_read_base as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _IO_read_base),
         24usize,
         concat!(
             "Offset of field: ",
@@ -549,7 +547,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._IO_write_base as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _IO_write_base),
         32usize,
         concat!(
             "Offset of field: ",
@@ -559,7 +557,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._IO_write_ptr as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _IO_write_ptr),
         40usize,
         concat!(
             "Offset of field: ",
@@ -569,7 +567,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._IO_write_end as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _IO_write_end),
         48usize,
         concat!(
             "Offset of field: ",
@@ -579,7 +577,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._IO_buf_base as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _IO_buf_base),
         56usize,
         concat!(
             "Offset of field: ",
@@ -589,7 +587,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._IO_buf_end as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _IO_buf_end),
         64usize,
         concat!(
             "Offset of field: ",
@@ -599,7 +597,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._IO_save_base as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _IO_save_base),
         72usize,
         concat!(
             "Offset of field: ",
@@ -609,7 +607,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._IO_backup_base as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _IO_backup_base),
         80usize,
         concat!(
             "Offset of field: ",
@@ -619,7 +617,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._IO_save_end as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _IO_save_end),
         88usize,
         concat!(
             "Offset of field: ",
@@ -629,7 +627,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._markers as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _markers),
         96usize,
         concat!(
             "Offset of field: ",
@@ -639,7 +637,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._chain as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _chain),
         104usize,
         concat!(
             "Offset of field: ",
@@ -649,7 +647,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._fileno as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _fileno),
         112usize,
         concat!(
             "Offset of field: ",
@@ -659,7 +657,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._flags2 as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _flags2),
         116usize,
         concat!(
             "Offset of field: ",
@@ -669,7 +667,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._old_offset as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _old_offset),
         120usize,
         concat!(
             "Offset of field: ",
@@ -679,7 +677,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._cur_column as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _cur_column),
         128usize,
         concat!(
             "Offset of field: ",
@@ -689,7 +687,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._vtable_offset as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _vtable_offset),
         130usize,
         concat!(
             "Offset of field: ",
@@ -699,7 +697,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._shortbuf as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _shortbuf),
         131usize,
         concat!(
             "Offset of field: ",
@@ -709,7 +707,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._lock as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _lock),
         136usize,
         concat!(
             "Offset of field: ",
@@ -719,7 +717,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._offset as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _offset),
         144usize,
         concat!(
             "Offset of field: ",
@@ -729,7 +727,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._codecvt as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _codecvt),
         152usize,
         concat!(
             "Offset of field: ",
@@ -739,7 +737,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._wide_data as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _wide_data),
         160usize,
         concat!(
             "Offset of field: ",
@@ -749,7 +747,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._freeres_list as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _freeres_list),
         168usize,
         concat!(
             "Offset of field: ",
@@ -759,7 +757,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._freeres_buf as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _freeres_buf),
         176usize,
         concat!(
             "Offset of field: ",
@@ -769,7 +767,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>())).__pad5 as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, __pad5),
         184usize,
         concat!(
             "Offset of field: ",
@@ -779,7 +777,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._mode as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _mode),
         192usize,
         concat!(
             "Offset of field: ",
@@ -789,7 +787,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._unused2 as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _unused2),
         196usize,
         concat!(
             "Offset of field: ",
@@ -1323,7 +1321,7 @@ fn bindgen_test_layout_JSRefCountHeader() {
         concat!("Alignment of ", stringify!(JSRefCountHeader))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSRefCountHeader>())).ref_count as *const _ as usize },
+        ::std::mem::offset_of!(JSRefCountHeader, ref_count),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1354,7 +1352,7 @@ fn bindgen_test_layout_JSValueUnion() {
         concat!("Alignment of ", stringify!(JSValueUnion))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSValueUnion>())).int32 as *const _ as usize },
+        ::std::mem::offset_of!(JSValueUnion, int32),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1364,7 +1362,7 @@ fn bindgen_test_layout_JSValueUnion() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSValueUnion>())).float64 as *const _ as usize },
+        ::std::mem::offset_of!(JSValueUnion, float64),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1374,7 +1372,7 @@ fn bindgen_test_layout_JSValueUnion() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSValueUnion>())).ptr as *const _ as usize },
+        ::std::mem::offset_of!(JSValueUnion, ptr),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1403,7 +1401,7 @@ fn bindgen_test_layout_JSValue() {
         concat!("Alignment of ", stringify!(JSValue))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSValue>())).u as *const _ as usize },
+        ::std::mem::offset_of!(JSValue, u),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1413,7 +1411,7 @@ fn bindgen_test_layout_JSValue() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSValue>())).tag as *const _ as usize },
+        ::std::mem::offset_of!(JSValue, tag),
         8usize,
         concat!(
             "Offset of field: ",
@@ -1471,7 +1469,7 @@ fn bindgen_test_layout_JSMallocState() {
         concat!("Alignment of ", stringify!(JSMallocState))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSMallocState>())).malloc_count as *const _ as usize },
+        ::std::mem::offset_of!(JSMallocState, malloc_count),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1481,7 +1479,7 @@ fn bindgen_test_layout_JSMallocState() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSMallocState>())).malloc_size as *const _ as usize },
+        ::std::mem::offset_of!(JSMallocState, malloc_size),
         8usize,
         concat!(
             "Offset of field: ",
@@ -1491,7 +1489,7 @@ fn bindgen_test_layout_JSMallocState() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSMallocState>())).malloc_limit as *const _ as usize },
+        ::std::mem::offset_of!(JSMallocState, malloc_limit),
         16usize,
         concat!(
             "Offset of field: ",
@@ -1501,7 +1499,7 @@ fn bindgen_test_layout_JSMallocState() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSMallocState>())).opaque as *const _ as usize },
+        ::std::mem::offset_of!(JSMallocState, opaque),
         24usize,
         concat!(
             "Offset of field: ",
@@ -1543,7 +1541,7 @@ fn bindgen_test_layout_JSMallocFunctions() {
         concat!("Alignment of ", stringify!(JSMallocFunctions))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSMallocFunctions>())).js_malloc as *const _ as usize },
+        ::std::mem::offset_of!(JSMallocFunctions, js_malloc),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1553,7 +1551,7 @@ fn bindgen_test_layout_JSMallocFunctions() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSMallocFunctions>())).js_free as *const _ as usize },
+        ::std::mem::offset_of!(JSMallocFunctions, js_free),
         8usize,
         concat!(
             "Offset of field: ",
@@ -1563,7 +1561,7 @@ fn bindgen_test_layout_JSMallocFunctions() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSMallocFunctions>())).js_realloc as *const _ as usize },
+        ::std::mem::offset_of!(JSMallocFunctions, js_realloc),
         16usize,
         concat!(
             "Offset of field: ",
@@ -1573,9 +1571,7 @@ fn bindgen_test_layout_JSMallocFunctions() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<JSMallocFunctions>())).js_malloc_usable_size as *const _ as usize
-        },
+        ::std::mem::offset_of!(JSMallocFunctions, js_malloc_usable_size),
         24usize,
         concat!(
             "Offset of field: ",
@@ -1823,7 +1819,7 @@ fn bindgen_test_layout_JSMemoryUsage() {
         concat!("Alignment of ", stringify!(JSMemoryUsage))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSMemoryUsage>())).malloc_size as *const _ as usize },
+        ::std::mem::offset_of!(JSMemoryUsage, malloc_size),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1833,7 +1829,7 @@ fn bindgen_test_layout_JSMemoryUsage() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSMemoryUsage>())).malloc_limit as *const _ as usize },
+        ::std::mem::offset_of!(JSMemoryUsage, malloc_limit),
         8usize,
         concat!(
             "Offset of field: ",
@@ -1843,7 +1839,7 @@ fn bindgen_test_layout_JSMemoryUsage() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSMemoryUsage>())).memory_used_size as *const _ as usize },
+        ::std::mem::offset_of!(JSMemoryUsage, memory_used_size),
         16usize,
         concat!(
             "Offset of field: ",
@@ -1853,7 +1849,7 @@ fn bindgen_test_layout_JSMemoryUsage() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSMemoryUsage>())).malloc_count as *const _ as usize },
+        ::std::mem::offset_of!(JSMemoryUsage, malloc_count),
         24usize,
         concat!(
             "Offset of field: ",
@@ -1863,7 +1859,7 @@ fn bindgen_test_layout_JSMemoryUsage() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSMemoryUsage>())).memory_used_count as *const _ as usize },
+        ::std::mem::offset_of!(JSMemoryUsage, memory_used_count),
         32usize,
         concat!(
             "Offset of field: ",
@@ -1873,7 +1869,7 @@ fn bindgen_test_layout_JSMemoryUsage() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSMemoryUsage>())).atom_count as *const _ as usize },
+        ::std::mem::offset_of!(JSMemoryUsage, atom_count),
         40usize,
         concat!(
             "Offset of field: ",
@@ -1883,7 +1879,7 @@ fn bindgen_test_layout_JSMemoryUsage() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSMemoryUsage>())).atom_size as *const _ as usize },
+        ::std::mem::offset_of!(JSMemoryUsage, atom_size),
         48usize,
         concat!(
             "Offset of field: ",
@@ -1893,7 +1889,7 @@ fn bindgen_test_layout_JSMemoryUsage() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSMemoryUsage>())).str_count as *const _ as usize },
+        ::std::mem::offset_of!(JSMemoryUsage, str_count),
         56usize,
         concat!(
             "Offset of field: ",
@@ -1903,7 +1899,7 @@ fn bindgen_test_layout_JSMemoryUsage() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSMemoryUsage>())).str_size as *const _ as usize },
+        ::std::mem::offset_of!(JSMemoryUsage, str_size),
         64usize,
         concat!(
             "Offset of field: ",
@@ -1913,7 +1909,7 @@ fn bindgen_test_layout_JSMemoryUsage() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSMemoryUsage>())).obj_count as *const _ as usize },
+        ::std::mem::offset_of!(JSMemoryUsage, obj_count),
         72usize,
         concat!(
             "Offset of field: ",
@@ -1923,7 +1919,7 @@ fn bindgen_test_layout_JSMemoryUsage() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSMemoryUsage>())).obj_size as *const _ as usize },
+        ::std::mem::offset_of!(JSMemoryUsage, obj_size),
         80usize,
         concat!(
             "Offset of field: ",
@@ -1933,7 +1929,7 @@ fn bindgen_test_layout_JSMemoryUsage() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSMemoryUsage>())).prop_count as *const _ as usize },
+        ::std::mem::offset_of!(JSMemoryUsage, prop_count),
         88usize,
         concat!(
             "Offset of field: ",
@@ -1943,7 +1939,7 @@ fn bindgen_test_layout_JSMemoryUsage() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSMemoryUsage>())).prop_size as *const _ as usize },
+        ::std::mem::offset_of!(JSMemoryUsage, prop_size),
         96usize,
         concat!(
             "Offset of field: ",
@@ -1953,7 +1949,7 @@ fn bindgen_test_layout_JSMemoryUsage() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSMemoryUsage>())).shape_count as *const _ as usize },
+        ::std::mem::offset_of!(JSMemoryUsage, shape_count),
         104usize,
         concat!(
             "Offset of field: ",
@@ -1963,7 +1959,7 @@ fn bindgen_test_layout_JSMemoryUsage() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSMemoryUsage>())).shape_size as *const _ as usize },
+        ::std::mem::offset_of!(JSMemoryUsage, shape_size),
         112usize,
         concat!(
             "Offset of field: ",
@@ -1973,7 +1969,7 @@ fn bindgen_test_layout_JSMemoryUsage() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSMemoryUsage>())).js_func_count as *const _ as usize },
+        ::std::mem::offset_of!(JSMemoryUsage, js_func_count),
         120usize,
         concat!(
             "Offset of field: ",
@@ -1983,7 +1979,7 @@ fn bindgen_test_layout_JSMemoryUsage() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSMemoryUsage>())).js_func_size as *const _ as usize },
+        ::std::mem::offset_of!(JSMemoryUsage, js_func_size),
         128usize,
         concat!(
             "Offset of field: ",
@@ -1993,7 +1989,7 @@ fn bindgen_test_layout_JSMemoryUsage() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSMemoryUsage>())).js_func_code_size as *const _ as usize },
+        ::std::mem::offset_of!(JSMemoryUsage, js_func_code_size),
         136usize,
         concat!(
             "Offset of field: ",
@@ -2003,9 +1999,7 @@ fn bindgen_test_layout_JSMemoryUsage() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<JSMemoryUsage>())).js_func_pc2line_count as *const _ as usize
-        },
+        ::std::mem::offset_of!(JSMemoryUsage, js_func_pc2line_count),
         144usize,
         concat!(
             "Offset of field: ",
@@ -2015,9 +2009,7 @@ fn bindgen_test_layout_JSMemoryUsage() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<JSMemoryUsage>())).js_func_pc2line_size as *const _ as usize
-        },
+        ::std::mem::offset_of!(JSMemoryUsage, js_func_pc2line_size),
         152usize,
         concat!(
             "Offset of field: ",
@@ -2027,7 +2019,7 @@ fn bindgen_test_layout_JSMemoryUsage() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSMemoryUsage>())).c_func_count as *const _ as usize },
+        ::std::mem::offset_of!(JSMemoryUsage, c_func_count),
         160usize,
         concat!(
             "Offset of field: ",
@@ -2037,7 +2029,7 @@ fn bindgen_test_layout_JSMemoryUsage() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSMemoryUsage>())).array_count as *const _ as usize },
+        ::std::mem::offset_of!(JSMemoryUsage, array_count),
         168usize,
         concat!(
             "Offset of field: ",
@@ -2047,7 +2039,7 @@ fn bindgen_test_layout_JSMemoryUsage() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSMemoryUsage>())).fast_array_count as *const _ as usize },
+        ::std::mem::offset_of!(JSMemoryUsage, fast_array_count),
         176usize,
         concat!(
             "Offset of field: ",
@@ -2057,9 +2049,7 @@ fn bindgen_test_layout_JSMemoryUsage() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<JSMemoryUsage>())).fast_array_elements as *const _ as usize
-        },
+        ::std::mem::offset_of!(JSMemoryUsage, fast_array_elements),
         184usize,
         concat!(
             "Offset of field: ",
@@ -2069,9 +2059,7 @@ fn bindgen_test_layout_JSMemoryUsage() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<JSMemoryUsage>())).binary_object_count as *const _ as usize
-        },
+        ::std::mem::offset_of!(JSMemoryUsage, binary_object_count),
         192usize,
         concat!(
             "Offset of field: ",
@@ -2081,9 +2069,7 @@ fn bindgen_test_layout_JSMemoryUsage() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<JSMemoryUsage>())).binary_object_size as *const _ as usize
-        },
+        ::std::mem::offset_of!(JSMemoryUsage, binary_object_size),
         200usize,
         concat!(
             "Offset of field: ",
@@ -2152,7 +2138,7 @@ fn bindgen_test_layout_JSPropertyEnum() {
         concat!("Alignment of ", stringify!(JSPropertyEnum))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSPropertyEnum>())).is_enumerable as *const _ as usize },
+        ::std::mem::offset_of!(JSPropertyEnum, is_enumerable),
         0usize,
         concat!(
             "Offset of field: ",
@@ -2162,7 +2148,7 @@ fn bindgen_test_layout_JSPropertyEnum() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSPropertyEnum>())).atom as *const _ as usize },
+        ::std::mem::offset_of!(JSPropertyEnum, atom),
         4usize,
         concat!(
             "Offset of field: ",
@@ -2193,7 +2179,7 @@ fn bindgen_test_layout_JSPropertyDescriptor() {
         concat!("Alignment of ", stringify!(JSPropertyDescriptor))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSPropertyDescriptor>())).flags as *const _ as usize },
+        ::std::mem::offset_of!(JSPropertyDescriptor, flags),
         0usize,
         concat!(
             "Offset of field: ",
@@ -2203,7 +2189,7 @@ fn bindgen_test_layout_JSPropertyDescriptor() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSPropertyDescriptor>())).value as *const _ as usize },
+        ::std::mem::offset_of!(JSPropertyDescriptor, value),
         8usize,
         concat!(
             "Offset of field: ",
@@ -2213,7 +2199,7 @@ fn bindgen_test_layout_JSPropertyDescriptor() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSPropertyDescriptor>())).getter as *const _ as usize },
+        ::std::mem::offset_of!(JSPropertyDescriptor, getter),
         24usize,
         concat!(
             "Offset of field: ",
@@ -2223,7 +2209,7 @@ fn bindgen_test_layout_JSPropertyDescriptor() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSPropertyDescriptor>())).setter as *const _ as usize },
+        ::std::mem::offset_of!(JSPropertyDescriptor, setter),
         40usize,
         concat!(
             "Offset of field: ",
@@ -2309,9 +2295,7 @@ fn bindgen_test_layout_JSClassExoticMethods() {
         concat!("Alignment of ", stringify!(JSClassExoticMethods))
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<JSClassExoticMethods>())).get_own_property as *const _ as usize
-        },
+        ::std::mem::offset_of!(JSClassExoticMethods, get_own_property),
         0usize,
         concat!(
             "Offset of field: ",
@@ -2321,10 +2305,7 @@ fn bindgen_test_layout_JSClassExoticMethods() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<JSClassExoticMethods>())).get_own_property_names as *const _
-                as usize
-        },
+        ::std::mem::offset_of!(JSClassExoticMethods, get_own_property_names),
         8usize,
         concat!(
             "Offset of field: ",
@@ -2334,9 +2315,7 @@ fn bindgen_test_layout_JSClassExoticMethods() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<JSClassExoticMethods>())).delete_property as *const _ as usize
-        },
+        ::std::mem::offset_of!(JSClassExoticMethods, delete_property),
         16usize,
         concat!(
             "Offset of field: ",
@@ -2346,10 +2325,7 @@ fn bindgen_test_layout_JSClassExoticMethods() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<JSClassExoticMethods>())).define_own_property as *const _
-                as usize
-        },
+        ::std::mem::offset_of!(JSClassExoticMethods, define_own_property),
         24usize,
         concat!(
             "Offset of field: ",
@@ -2359,9 +2335,7 @@ fn bindgen_test_layout_JSClassExoticMethods() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<JSClassExoticMethods>())).has_property as *const _ as usize
-        },
+        ::std::mem::offset_of!(JSClassExoticMethods, has_property),
         32usize,
         concat!(
             "Offset of field: ",
@@ -2371,9 +2345,7 @@ fn bindgen_test_layout_JSClassExoticMethods() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<JSClassExoticMethods>())).get_property as *const _ as usize
-        },
+        ::std::mem::offset_of!(JSClassExoticMethods, get_property),
         40usize,
         concat!(
             "Offset of field: ",
@@ -2383,9 +2355,7 @@ fn bindgen_test_layout_JSClassExoticMethods() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<JSClassExoticMethods>())).set_property as *const _ as usize
-        },
+        ::std::mem::offset_of!(JSClassExoticMethods, set_property),
         48usize,
         concat!(
             "Offset of field: ",
@@ -2432,7 +2402,7 @@ fn bindgen_test_layout_JSClassDef() {
         concat!("Alignment of ", stringify!(JSClassDef))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSClassDef>())).class_name as *const _ as usize },
+        ::std::mem::offset_of!(JSClassDef, class_name),
         0usize,
         concat!(
             "Offset of field: ",
@@ -2442,7 +2412,7 @@ fn bindgen_test_layout_JSClassDef() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSClassDef>())).finalizer as *const _ as usize },
+        ::std::mem::offset_of!(JSClassDef, finalizer),
         8usize,
         concat!(
             "Offset of field: ",
@@ -2452,7 +2422,7 @@ fn bindgen_test_layout_JSClassDef() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSClassDef>())).gc_mark as *const _ as usize },
+        ::std::mem::offset_of!(JSClassDef, gc_mark),
         16usize,
         concat!(
             "Offset of field: ",
@@ -2462,7 +2432,7 @@ fn bindgen_test_layout_JSClassDef() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSClassDef>())).call as *const _ as usize },
+        ::std::mem::offset_of!(JSClassDef, call),
         24usize,
         concat!(
             "Offset of field: ",
@@ -2472,7 +2442,7 @@ fn bindgen_test_layout_JSClassDef() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSClassDef>())).exotic as *const _ as usize },
+        ::std::mem::offset_of!(JSClassDef, exotic),
         32usize,
         concat!(
             "Offset of field: ",
@@ -2976,9 +2946,7 @@ fn bindgen_test_layout_JSSharedArrayBufferFunctions() {
         concat!("Alignment of ", stringify!(JSSharedArrayBufferFunctions))
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<JSSharedArrayBufferFunctions>())).sab_alloc as *const _ as usize
-        },
+        ::std::mem::offset_of!(JSSharedArrayBufferFunctions, sab_alloc),
         0usize,
         concat!(
             "Offset of field: ",
@@ -2988,9 +2956,7 @@ fn bindgen_test_layout_JSSharedArrayBufferFunctions() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<JSSharedArrayBufferFunctions>())).sab_free as *const _ as usize
-        },
+        ::std::mem::offset_of!(JSSharedArrayBufferFunctions, sab_free),
         8usize,
         concat!(
             "Offset of field: ",
@@ -3000,9 +2966,7 @@ fn bindgen_test_layout_JSSharedArrayBufferFunctions() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<JSSharedArrayBufferFunctions>())).sab_dup as *const _ as usize
-        },
+        ::std::mem::offset_of!(JSSharedArrayBufferFunctions, sab_dup),
         16usize,
         concat!(
             "Offset of field: ",
@@ -3012,9 +2976,7 @@ fn bindgen_test_layout_JSSharedArrayBufferFunctions() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<JSSharedArrayBufferFunctions>())).sab_opaque as *const _ as usize
-        },
+        ::std::mem::offset_of!(JSSharedArrayBufferFunctions, sab_opaque),
         24usize,
         concat!(
             "Offset of field: ",
@@ -3257,7 +3219,7 @@ fn bindgen_test_layout_JSCFunctionType() {
         concat!("Alignment of ", stringify!(JSCFunctionType))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSCFunctionType>())).generic as *const _ as usize },
+        ::std::mem::offset_of!(JSCFunctionType, generic),
         0usize,
         concat!(
             "Offset of field: ",
@@ -3267,7 +3229,7 @@ fn bindgen_test_layout_JSCFunctionType() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSCFunctionType>())).generic_magic as *const _ as usize },
+        ::std::mem::offset_of!(JSCFunctionType, generic_magic),
         0usize,
         concat!(
             "Offset of field: ",
@@ -3277,7 +3239,7 @@ fn bindgen_test_layout_JSCFunctionType() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSCFunctionType>())).constructor as *const _ as usize },
+        ::std::mem::offset_of!(JSCFunctionType, constructor),
         0usize,
         concat!(
             "Offset of field: ",
@@ -3287,9 +3249,7 @@ fn bindgen_test_layout_JSCFunctionType() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<JSCFunctionType>())).constructor_magic as *const _ as usize
-        },
+        ::std::mem::offset_of!(JSCFunctionType, constructor_magic),
         0usize,
         concat!(
             "Offset of field: ",
@@ -3299,9 +3259,7 @@ fn bindgen_test_layout_JSCFunctionType() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<JSCFunctionType>())).constructor_or_func as *const _ as usize
-        },
+        ::std::mem::offset_of!(JSCFunctionType, constructor_or_func),
         0usize,
         concat!(
             "Offset of field: ",
@@ -3311,7 +3269,7 @@ fn bindgen_test_layout_JSCFunctionType() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSCFunctionType>())).f_f as *const _ as usize },
+        ::std::mem::offset_of!(JSCFunctionType, f_f),
         0usize,
         concat!(
             "Offset of field: ",
@@ -3321,7 +3279,7 @@ fn bindgen_test_layout_JSCFunctionType() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSCFunctionType>())).f_f_f as *const _ as usize },
+        ::std::mem::offset_of!(JSCFunctionType, f_f_f),
         0usize,
         concat!(
             "Offset of field: ",
@@ -3331,7 +3289,7 @@ fn bindgen_test_layout_JSCFunctionType() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSCFunctionType>())).getter as *const _ as usize },
+        ::std::mem::offset_of!(JSCFunctionType, getter),
         0usize,
         concat!(
             "Offset of field: ",
@@ -3341,7 +3299,7 @@ fn bindgen_test_layout_JSCFunctionType() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSCFunctionType>())).setter as *const _ as usize },
+        ::std::mem::offset_of!(JSCFunctionType, setter),
         0usize,
         concat!(
             "Offset of field: ",
@@ -3351,7 +3309,7 @@ fn bindgen_test_layout_JSCFunctionType() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSCFunctionType>())).getter_magic as *const _ as usize },
+        ::std::mem::offset_of!(JSCFunctionType, getter_magic),
         0usize,
         concat!(
             "Offset of field: ",
@@ -3361,7 +3319,7 @@ fn bindgen_test_layout_JSCFunctionType() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSCFunctionType>())).setter_magic as *const _ as usize },
+        ::std::mem::offset_of!(JSCFunctionType, setter_magic),
         0usize,
         concat!(
             "Offset of field: ",
@@ -3371,7 +3329,7 @@ fn bindgen_test_layout_JSCFunctionType() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSCFunctionType>())).iterator_next as *const _ as usize },
+        ::std::mem::offset_of!(JSCFunctionType, iterator_next),
         0usize,
         concat!(
             "Offset of field: ",
@@ -3452,10 +3410,7 @@ fn bindgen_test_layout_JSCFunctionListEntry__bindgen_ty_1__bindgen_ty_1() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<JSCFunctionListEntry__bindgen_ty_1__bindgen_ty_1>())).length
-                as *const _ as usize
-        },
+        ::std::mem::offset_of!(JSCFunctionListEntry__bindgen_ty_1__bindgen_ty_1, length),
         0usize,
         concat!(
             "Offset of field: ",
@@ -3465,10 +3420,7 @@ fn bindgen_test_layout_JSCFunctionListEntry__bindgen_ty_1__bindgen_ty_1() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<JSCFunctionListEntry__bindgen_ty_1__bindgen_ty_1>())).cproto
-                as *const _ as usize
-        },
+        ::std::mem::offset_of!(JSCFunctionListEntry__bindgen_ty_1__bindgen_ty_1, cproto),
         1usize,
         concat!(
             "Offset of field: ",
@@ -3478,10 +3430,7 @@ fn bindgen_test_layout_JSCFunctionListEntry__bindgen_ty_1__bindgen_ty_1() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<JSCFunctionListEntry__bindgen_ty_1__bindgen_ty_1>())).cfunc
-                as *const _ as usize
-        },
+        ::std::mem::offset_of!(JSCFunctionListEntry__bindgen_ty_1__bindgen_ty_1, cfunc),
         8usize,
         concat!(
             "Offset of field: ",
@@ -3516,10 +3465,7 @@ fn bindgen_test_layout_JSCFunctionListEntry__bindgen_ty_1__bindgen_ty_2() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<JSCFunctionListEntry__bindgen_ty_1__bindgen_ty_2>())).get
-                as *const _ as usize
-        },
+        ::std::mem::offset_of!(JSCFunctionListEntry__bindgen_ty_1__bindgen_ty_2, get),
         0usize,
         concat!(
             "Offset of field: ",
@@ -3529,10 +3475,7 @@ fn bindgen_test_layout_JSCFunctionListEntry__bindgen_ty_1__bindgen_ty_2() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<JSCFunctionListEntry__bindgen_ty_1__bindgen_ty_2>())).set
-                as *const _ as usize
-        },
+        ::std::mem::offset_of!(JSCFunctionListEntry__bindgen_ty_1__bindgen_ty_2, set),
         8usize,
         concat!(
             "Offset of field: ",
@@ -3567,10 +3510,7 @@ fn bindgen_test_layout_JSCFunctionListEntry__bindgen_ty_1__bindgen_ty_3() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<JSCFunctionListEntry__bindgen_ty_1__bindgen_ty_3>())).name
-                as *const _ as usize
-        },
+        ::std::mem::offset_of!(JSCFunctionListEntry__bindgen_ty_1__bindgen_ty_3, name),
         0usize,
         concat!(
             "Offset of field: ",
@@ -3580,10 +3520,7 @@ fn bindgen_test_layout_JSCFunctionListEntry__bindgen_ty_1__bindgen_ty_3() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<JSCFunctionListEntry__bindgen_ty_1__bindgen_ty_3>())).base
-                as *const _ as usize
-        },
+        ::std::mem::offset_of!(JSCFunctionListEntry__bindgen_ty_1__bindgen_ty_3, base),
         8usize,
         concat!(
             "Offset of field: ",
@@ -3618,10 +3555,7 @@ fn bindgen_test_layout_JSCFunctionListEntry__bindgen_ty_1__bindgen_ty_4() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<JSCFunctionListEntry__bindgen_ty_1__bindgen_ty_4>())).tab
-                as *const _ as usize
-        },
+        ::std::mem::offset_of!(JSCFunctionListEntry__bindgen_ty_1__bindgen_ty_4, tab),
         0usize,
         concat!(
             "Offset of field: ",
@@ -3631,10 +3565,7 @@ fn bindgen_test_layout_JSCFunctionListEntry__bindgen_ty_1__bindgen_ty_4() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<JSCFunctionListEntry__bindgen_ty_1__bindgen_ty_4>())).len
-                as *const _ as usize
-        },
+        ::std::mem::offset_of!(JSCFunctionListEntry__bindgen_ty_1__bindgen_ty_4, len),
         8usize,
         concat!(
             "Offset of field: ",
@@ -3660,9 +3591,7 @@ fn bindgen_test_layout_JSCFunctionListEntry__bindgen_ty_1() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<JSCFunctionListEntry__bindgen_ty_1>())).func as *const _ as usize
-        },
+        ::std::mem::offset_of!(JSCFunctionListEntry__bindgen_ty_1, func),
         0usize,
         concat!(
             "Offset of field: ",
@@ -3672,10 +3601,7 @@ fn bindgen_test_layout_JSCFunctionListEntry__bindgen_ty_1() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<JSCFunctionListEntry__bindgen_ty_1>())).getset as *const _
-                as usize
-        },
+        ::std::mem::offset_of!(JSCFunctionListEntry__bindgen_ty_1, getset),
         0usize,
         concat!(
             "Offset of field: ",
@@ -3685,10 +3611,7 @@ fn bindgen_test_layout_JSCFunctionListEntry__bindgen_ty_1() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<JSCFunctionListEntry__bindgen_ty_1>())).alias as *const _
-                as usize
-        },
+        ::std::mem::offset_of!(JSCFunctionListEntry__bindgen_ty_1, alias),
         0usize,
         concat!(
             "Offset of field: ",
@@ -3698,10 +3621,7 @@ fn bindgen_test_layout_JSCFunctionListEntry__bindgen_ty_1() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<JSCFunctionListEntry__bindgen_ty_1>())).prop_list as *const _
-                as usize
-        },
+        ::std::mem::offset_of!(JSCFunctionListEntry__bindgen_ty_1, prop_list),
         0usize,
         concat!(
             "Offset of field: ",
@@ -3711,9 +3631,7 @@ fn bindgen_test_layout_JSCFunctionListEntry__bindgen_ty_1() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<JSCFunctionListEntry__bindgen_ty_1>())).str_ as *const _ as usize
-        },
+        ::std::mem::offset_of!(JSCFunctionListEntry__bindgen_ty_1, str_),
         0usize,
         concat!(
             "Offset of field: ",
@@ -3723,9 +3641,7 @@ fn bindgen_test_layout_JSCFunctionListEntry__bindgen_ty_1() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<JSCFunctionListEntry__bindgen_ty_1>())).i32_ as *const _ as usize
-        },
+        ::std::mem::offset_of!(JSCFunctionListEntry__bindgen_ty_1, i32_),
         0usize,
         concat!(
             "Offset of field: ",
@@ -3735,9 +3651,7 @@ fn bindgen_test_layout_JSCFunctionListEntry__bindgen_ty_1() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<JSCFunctionListEntry__bindgen_ty_1>())).i64_ as *const _ as usize
-        },
+        ::std::mem::offset_of!(JSCFunctionListEntry__bindgen_ty_1, i64_),
         0usize,
         concat!(
             "Offset of field: ",
@@ -3747,9 +3661,7 @@ fn bindgen_test_layout_JSCFunctionListEntry__bindgen_ty_1() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<JSCFunctionListEntry__bindgen_ty_1>())).f64_ as *const _ as usize
-        },
+        ::std::mem::offset_of!(JSCFunctionListEntry__bindgen_ty_1, f64_),
         0usize,
         concat!(
             "Offset of field: ",
@@ -3772,7 +3684,7 @@ fn bindgen_test_layout_JSCFunctionListEntry() {
         concat!("Alignment of ", stringify!(JSCFunctionListEntry))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSCFunctionListEntry>())).name as *const _ as usize },
+        ::std::mem::offset_of!(JSCFunctionListEntry, name),
         0usize,
         concat!(
             "Offset of field: ",
@@ -3782,7 +3694,7 @@ fn bindgen_test_layout_JSCFunctionListEntry() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSCFunctionListEntry>())).prop_flags as *const _ as usize },
+        ::std::mem::offset_of!(JSCFunctionListEntry, prop_flags),
         8usize,
         concat!(
             "Offset of field: ",
@@ -3792,7 +3704,7 @@ fn bindgen_test_layout_JSCFunctionListEntry() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSCFunctionListEntry>())).def_type as *const _ as usize },
+        ::std::mem::offset_of!(JSCFunctionListEntry, def_type),
         9usize,
         concat!(
             "Offset of field: ",
@@ -3802,7 +3714,7 @@ fn bindgen_test_layout_JSCFunctionListEntry() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSCFunctionListEntry>())).magic as *const _ as usize },
+        ::std::mem::offset_of!(JSCFunctionListEntry, magic),
         10usize,
         concat!(
             "Offset of field: ",
@@ -3812,7 +3724,7 @@ fn bindgen_test_layout_JSCFunctionListEntry() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<JSCFunctionListEntry>())).u as *const _ as usize },
+        ::std::mem::offset_of!(JSCFunctionListEntry, u),
         16usize,
         concat!(
             "Offset of field: ",
@@ -3893,7 +3805,7 @@ fn bindgen_test_layout___va_list_tag() {
         concat!("Alignment of ", stringify!(__va_list_tag))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<__va_list_tag>())).gp_offset as *const _ as usize },
+        ::std::mem::offset_of!(__va_list_tag, gp_offset),
         0usize,
         concat!(
             "Offset of field: ",
@@ -3903,7 +3815,7 @@ fn bindgen_test_layout___va_list_tag() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<__va_list_tag>())).fp_offset as *const _ as usize },
+        ::std::mem::offset_of!(__va_list_tag, fp_offset),
         4usize,
         concat!(
             "Offset of field: ",
@@ -3913,7 +3825,7 @@ fn bindgen_test_layout___va_list_tag() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<__va_list_tag>())).overflow_arg_area as *const _ as usize },
+        ::std::mem::offset_of!(__va_list_tag, overflow_arg_area),
         8usize,
         concat!(
             "Offset of field: ",
@@ -3923,7 +3835,7 @@ fn bindgen_test_layout___va_list_tag() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<__va_list_tag>())).reg_save_area as *const _ as usize },
+        ::std::mem::offset_of!(__va_list_tag, reg_save_area),
         16usize,
         concat!(
             "Offset of field: ",
@@ -0,0 +1,203 @@
+//! Custom memory allocation for the QuickJS runtime.
+//!
+//! By default the runtime allocates all memory with the [SystemAllocator],
+//! which is invisible to Rust allocator instrumentation.
+//! Another [Allocator] can be registered via `ContextBuilder::allocator` to
+//! track or control every allocation of the runtime.
+//!
+//! ```rust
+//! use quick_js::{allocator::SharedCountingAllocator, Context};
+//!
+//! let allocator = SharedCountingAllocator::new();
+//! let context = Context::builder()
+//!     .allocator(allocator.clone())
+//!     .build()
+//!     .unwrap();
+//!
+//! context.eval(" 'abc'.repeat(1000) ").unwrap();
+//! assert!(allocator.stats().peak_bytes > 0);
+//! ```
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+/// A memory allocator for a QuickJS runtime.
+///
+/// The interface mirrors [std::alloc::GlobalAlloc]. All memory requested by
+/// the runtime is passed through the allocator, which allows implementing
+/// arena or pooled allocation strategies, or tracking memory usage.
+///
+/// The same allocator instance is used for all runtimes built from one
+/// `ContextBuilder`, which may live on different threads, so an allocator
+/// must be `Send` and `Sync`.
+///
+/// # Safety
+///
+/// Implementations must uphold the same contract as `GlobalAlloc`:
+/// a returned block must be valid for the requested layout and must stay
+/// valid until it is passed to `dealloc` or `realloc`.
+pub unsafe trait Allocator: Send + Sync + 'static {
+    /// Allocate a block of memory as described by `layout`.
+    ///
+    /// Returns a null pointer if the allocation failed.
+    ///
+    /// # Safety
+    ///
+    /// `layout` must have a non-zero size.
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8;
+
+    /// Deallocate a block of memory.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by this allocator for the given `layout`.
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout);
+
+    /// Shrink or grow a block of memory to `new_size` bytes.
+    ///
+    /// Returns a null pointer if the allocation failed, in which case the
+    /// old block stays valid.
+    ///
+    /// The default implementation allocates a new block, copies the data and
+    /// frees the old block.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by this allocator for the given `layout`,
+    /// and `new_size` must be non-zero.
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_layout = Layout::from_size_align_unchecked(new_size, layout.align());
+        let new_ptr = self.alloc(new_layout);
+        if !new_ptr.is_null() {
+            std::ptr::copy_nonoverlapping(ptr, new_ptr, layout.size().min(new_size));
+            self.dealloc(ptr, layout);
+        }
+        new_ptr
+    }
+}
+
+/// An allocator that forwards to the Rust system allocator.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct SystemAllocator;
+
+unsafe impl Allocator for SystemAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+/// Memory usage statistics collected by a [SharedCountingAllocator], or of
+/// a single runtime by `Context::allocation_stats`.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct AllocationStats {
+    /// Number of bytes currently allocated.
+    pub current_bytes: usize,
+    /// Highest number of bytes that were allocated at the same time.
+    pub peak_bytes: usize,
+    /// Number of live allocations.
+    pub allocations: usize,
+}
+
+#[derive(Default, Debug)]
+struct Counters {
+    current_bytes: AtomicUsize,
+    peak_bytes: AtomicUsize,
+    allocations: AtomicUsize,
+}
+
+impl Counters {
+    fn grow(&self, bytes: usize) {
+        let current = self.current_bytes.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        self.peak_bytes.fetch_max(current, Ordering::Relaxed);
+    }
+
+    fn shrink(&self, bytes: usize) {
+        self.current_bytes.fetch_sub(bytes, Ordering::Relaxed);
+    }
+}
+
+/// An allocator that keeps track of the current and peak memory usage.
+///
+/// Allocations are forwarded to an inner allocator, which defaults to the
+/// [SystemAllocator].
+///
+/// Clones share the same counters, so a clone can be kept around to inspect
+/// the memory usage of the runtimes the allocator was registered with.
+///
+/// The counters are shared by every runtime that uses the allocator. Contexts
+/// built from clones of one `ContextBuilder`, like the contexts of a pool,
+/// all add to the same statistics. The statistics of a single runtime are
+/// available from `Context::allocation_stats`, with any allocator.
+#[derive(Clone, Default, Debug)]
+pub struct SharedCountingAllocator<A = SystemAllocator> {
+    inner: A,
+    counters: Arc<Counters>,
+}
+
+impl SharedCountingAllocator {
+    /// Create a new counting allocator backed by the [SystemAllocator].
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<A: Allocator> SharedCountingAllocator<A> {
+    /// Create a new counting allocator that forwards to the given allocator.
+    pub fn with_inner(inner: A) -> Self {
+        Self {
+            inner,
+            counters: Arc::new(Counters::default()),
+        }
+    }
+
+    /// Get the current memory usage statistics.
+    pub fn stats(&self) -> AllocationStats {
+        AllocationStats {
+            current_bytes: self.counters.current_bytes.load(Ordering::Relaxed),
+            peak_bytes: self.counters.peak_bytes.load(Ordering::Relaxed),
+            allocations: self.counters.allocations.load(Ordering::Relaxed),
+        }
+    }
+}
+
+unsafe impl<A: Allocator> Allocator for SharedCountingAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc(layout);
+        if !ptr.is_null() {
+            self.counters.allocations.fetch_add(1, Ordering::Relaxed);
+            self.counters.grow(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.inner.dealloc(ptr, layout);
+        self.counters.allocations.fetch_sub(1, Ordering::Relaxed);
+        self.counters.shrink(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = self.inner.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            if new_size > layout.size() {
+                self.counters.grow(new_size - layout.size());
+            } else {
+                self.counters.shrink(layout.size() - new_size);
+            }
+        }
+        new_ptr
+    }
+}
@@ -0,0 +1,230 @@
+//! Glue for routing QuickJS memory allocations through an [Allocator].
+
+use std::{alloc::Layout, cell::Cell, os::raw::c_void, sync::Arc};
+
+use libquickjs_sys as q;
+
+use crate::allocator::{AllocationStats, Allocator};
+
+/// Every block is prefixed with a header that stores the requested size,
+/// since QuickJS does not pass the size to `free`.
+/// 16 bytes keep the returned pointer aligned for any type, like `malloc`.
+const HEADER_SIZE: usize = 16;
+const ALIGN: usize = 16;
+
+/// The allocator of a runtime.
+///
+/// A pointer to it is stored as the opaque value of the runtime malloc
+/// state, so it is boxed to keep a stable address.
+pub(super) struct RuntimeAllocator {
+    allocator: Arc<dyn Allocator>,
+    /// Whether an allocation failed since the last call of `take_failure`,
+    /// because of the memory limit or because the allocator returned null.
+    failed: Cell<bool>,
+    /// Highest number of bytes allocated by this runtime at the same time,
+    /// including the block headers.
+    peak_bytes: Cell<usize>,
+}
+
+impl RuntimeAllocator {
+    pub fn new(allocator: Arc<dyn Allocator>) -> Box<Self> {
+        Box::new(Self {
+            allocator,
+            failed: Cell::new(false),
+            peak_bytes: Cell::new(0),
+        })
+    }
+
+    /// Get the memory usage statistics of the runtime.
+    ///
+    /// The current size and count are kept by QuickJS in the malloc state,
+    /// only the peak is tracked here.
+    pub fn stats(&self, usage: &q::JSMemoryUsage) -> AllocationStats {
+        AllocationStats {
+            current_bytes: usage.malloc_size as usize,
+            peak_bytes: self.peak_bytes.get(),
+            allocations: usage.malloc_count as usize,
+        }
+    }
+
+    /// The opaque value for `JS_NewRuntime2`.
+    pub fn opaque(&self) -> *mut c_void {
+        self as *const Self as *mut c_void
+    }
+
+    /// Returns whether an allocation failed since the last call, which means
+    /// the runtime ran out of memory.
+    ///
+    /// Unlike the message of the thrown error, this can not be faked by
+    /// scripts.
+    pub fn take_failure(&self) -> bool {
+        self.failed.replace(false)
+    }
+}
+
+pub(super) fn malloc_functions() -> q::JSMallocFunctions {
+    q::JSMallocFunctions {
+        js_malloc: Some(js_malloc),
+        js_free: Some(js_free),
+        js_realloc: Some(js_realloc),
+        js_malloc_usable_size: Some(js_malloc_usable_size),
+    }
+}
+
+unsafe fn runtime_allocator<'a>(state: *mut q::JSMallocState) -> &'a RuntimeAllocator {
+    &*((*state).opaque as *const RuntimeAllocator)
+}
+
+unsafe fn allocator<'a>(state: *mut q::JSMallocState) -> &'a dyn Allocator {
+    &*runtime_allocator(state).allocator
+}
+
+/// Record a failed allocation and return null.
+unsafe fn fail(state: *mut q::JSMallocState) -> *mut c_void {
+    runtime_allocator(state).failed.set(true);
+    std::ptr::null_mut()
+}
+
+/// Record the current size of the runtime as the peak, if it is higher.
+unsafe fn update_peak(state: *mut q::JSMallocState) {
+    let peak = &runtime_allocator(state).peak_bytes;
+    peak.set(peak.get().max((*state).malloc_size as usize));
+}
+
+/// Returns `None` if the size is too large, which must be reported to QuickJS
+/// as a failed allocation since a panic can not unwind across the FFI boundary.
+fn block_layout(size: usize) -> Option<Layout> {
+    let block_size = size.checked_add(HEADER_SIZE)?;
+    Layout::from_size_align(block_size, ALIGN).ok()
+}
+
+/// The layout of a block that was already allocated, so its size is known to
+/// be valid.
+unsafe fn allocated_layout(size: usize) -> Layout {
+    Layout::from_size_align_unchecked(size + HEADER_SIZE, ALIGN)
+}
+
+/// Get the start of the block and the requested size from a user pointer.
+unsafe fn block_of(ptr: *const c_void) -> (*mut u8, usize) {
+    let block = (ptr as *mut u8).sub(HEADER_SIZE);
+    (block, *(block as *const usize))
+}
+
+unsafe extern "C" fn js_malloc(state: *mut q::JSMallocState, size: q::size_t) -> *mut c_void {
+    let alloc = allocator(state);
+    let s = &mut *state;
+    let block_size = size.saturating_add(HEADER_SIZE as q::size_t);
+    if s.malloc_size.saturating_add(block_size) > s.malloc_limit {
+        return fail(state);
+    }
+
+    let size = size as usize;
+    let layout = match block_layout(size) {
+        Some(layout) => layout,
+        None => return fail(state),
+    };
+    let block = alloc.alloc(layout);
+    if block.is_null() {
+        return fail(state);
+    }
+    *(block as *mut usize) = size;
+
+    s.malloc_count += 1;
+    s.malloc_size += (size + HEADER_SIZE) as q::size_t;
+    update_peak(state);
+    block.add(HEADER_SIZE) as *mut c_void
+}
+
+unsafe extern "C" fn js_free(state: *mut q::JSMallocState, ptr: *mut c_void) {
+    if ptr.is_null() {
+        return;
+    }
+    let (block, size) = block_of(ptr);
+    allocator(state).dealloc(block, allocated_layout(size));
+
+    let s = &mut *state;
+    s.malloc_count -= 1;
+    s.malloc_size -= (size + HEADER_SIZE) as q::size_t;
+}
+
+unsafe extern "C" fn js_realloc(
+    state: *mut q::JSMallocState,
+    ptr: *mut c_void,
+    size: q::size_t,
+) -> *mut c_void {
+    if ptr.is_null() {
+        if size == 0 {
+            return std::ptr::null_mut();
+        }
+        return js_malloc(state, size);
+    }
+    if size == 0 {
+        js_free(state, ptr);
+        return std::ptr::null_mut();
+    }
+
+    let alloc = allocator(state);
+    let (block, old_size) = block_of(ptr);
+    let s = &mut *state;
+    if (s.malloc_size - old_size as q::size_t).saturating_add(size) > s.malloc_limit {
+        return fail(state);
+    }
+
+    let size = size as usize;
+    let new_layout = match block_layout(size) {
+        Some(layout) => layout,
+        None => return fail(state),
+    };
+    let new_block = alloc.realloc(block, allocated_layout(old_size), new_layout.size());
+    if new_block.is_null() {
+        return fail(state);
+    }
+    *(new_block as *mut usize) = size;
+
+    s.malloc_size = s.malloc_size - old_size as q::size_t + size as q::size_t;
+    update_peak(state);
+    new_block.add(HEADER_SIZE) as *mut c_void
+}
+
+unsafe extern "C" fn js_malloc_usable_size(ptr: *const c_void) -> q::size_t {
+    if ptr.is_null() {
+        return 0;
+    }
+    block_of(ptr).1 as q::size_t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_layout_overflow() {
+        assert_eq!(block_layout(10).unwrap().size(), 10 + HEADER_SIZE);
+        assert!(block_layout(usize::MAX).is_none());
+        assert!(block_layout(isize::MAX as usize).is_none());
+    }
+
+    #[test]
+    fn test_limit_counts_header() {
+        let allocator = RuntimeAllocator::new(Arc::new(crate::allocator::SystemAllocator));
+        let mut state = q::JSMallocState {
+            malloc_count: 0,
+            malloc_size: 0,
+            malloc_limit: 100,
+            opaque: allocator.opaque(),
+        };
+        unsafe {
+            assert!(js_malloc(&mut state, 90).is_null());
+            assert!(allocator.take_failure());
+
+            let ptr = js_malloc(&mut state, 80);
+            assert!(!ptr.is_null());
+            assert_eq!(state.malloc_size, 80 + HEADER_SIZE as q::size_t);
+            assert!(js_realloc(&mut state, ptr, 90).is_null());
+
+            js_free(&mut state, ptr);
+            assert_eq!(state.malloc_size, 0);
+        }
+        assert_eq!(allocator.peak_bytes.get(), 80 + HEADER_SIZE);
+    }
+}
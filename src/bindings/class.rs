@@ -0,0 +1,182 @@
+use crate::{callback::Callback, ExecutionError, JsValue};
+
+use super::{CallbackEntry, ContextWrapper, OwnedJsValue};
+
+/// Creates a class whose instances are the objects returned by the native
+/// constructor, with the class prototype.
+///
+/// Returns a builder object for adding methods and operators, which must be
+/// finished with `finish`.
+const CLASS_SCRIPT: &str = r#"
+    (function (intrinsics, name, construct) {
+        const { TypeError, setPrototypeOf, defineProperty, keys, apply, bind } = intrinsics;
+        const wrap = value => {
+            if (typeof value !== "object" || value === null) {
+                throw new TypeError(`${name} instances must be objects`);
+            }
+            return setPrototypeOf(value, Class.prototype);
+        };
+        const Class = {
+            [name]: function (...args) {
+                if (new.target === undefined) {
+                    throw new TypeError(`Class constructor ${name} cannot be invoked without 'new'`);
+                }
+                return wrap(apply(construct, undefined, args));
+            },
+        }[name];
+
+        // Objects without a prototype, so properties inherited from
+        // `Object.prototype` can not interfere.
+        const self = { __proto__: null };
+        const sides = { __proto__: null };
+        return {
+            method(name, f) {
+                defineProperty(Class.prototype, name, {
+                    __proto__: null,
+                    value: function (...args) {
+                        return apply(apply(bind, f, [undefined, this]), undefined, args);
+                    },
+                    writable: true,
+                    configurable: true,
+                });
+            },
+            operator(op, side, wraps, f) {
+                const g = wraps ? (...args) => wrap(apply(f, undefined, args)) : f;
+                if (side === null) {
+                    self[op] = g;
+                } else {
+                    if (sides[side] === undefined) {
+                        sides[side] = { __proto__: null, [side]: intrinsics.Number };
+                    }
+                    sides[side][op] = g;
+                }
+            },
+            finish() {
+                const sets = keys(sides);
+                if (keys(self).length > 0 || sets.length > 0) {
+                    const args = [self];
+                    for (let i = 0; i < sets.length; i++) {
+                        defineProperty(args, i + 1, { __proto__: null, value: sides[sets[i]] });
+                    }
+                    defineProperty(Class.prototype, intrinsics.operatorSet, {
+                        __proto__: null,
+                        value: apply(intrinsics.operatorCreate, intrinsics.Operators, args),
+                    });
+                }
+                return Class;
+            },
+        };
+    })
+"#;
+
+/// An overloaded operator of a class.
+#[derive(Clone)]
+struct HostOperator {
+    /// The operator name used by `Operators.create`, like `+` or `neg`.
+    op: &'static str,
+    /// `left` or `right` if the other operand is a number on that side.
+    side: Option<&'static str>,
+    /// Whether the result is an instance of the class.
+    wraps: bool,
+    entry: CallbackEntry,
+}
+
+/// A class backed by Rust callbacks, installed as a property of a host
+/// object.
+#[derive(Clone)]
+pub(crate) struct HostClass {
+    constructor: CallbackEntry,
+    methods: Vec<(String, CallbackEntry)>,
+    operators: Vec<HostOperator>,
+}
+
+impl HostClass {
+    pub fn new<F>(context: &ContextWrapper, constructor: impl Callback<F> + 'static) -> Self {
+        Self {
+            constructor: context.callback_entry(constructor),
+            methods: Vec::new(),
+            operators: Vec::new(),
+        }
+    }
+
+    pub fn set_constructor<F>(
+        &mut self,
+        context: &ContextWrapper,
+        constructor: impl Callback<F> + 'static,
+    ) {
+        self.constructor = context.callback_entry(constructor);
+    }
+
+    /// Add a method, which is called with `this` as the first argument.
+    pub fn add_method<F>(
+        &mut self,
+        context: &ContextWrapper,
+        name: &str,
+        callback: impl Callback<F> + 'static,
+    ) {
+        let entry = context.callback_entry(callback);
+        self.methods.push((name.to_string(), entry));
+    }
+
+    #[cfg(feature = "bignum")]
+    pub fn add_operator<F>(
+        &mut self,
+        context: &ContextWrapper,
+        op: &'static str,
+        side: Option<&'static str>,
+        wraps: bool,
+        callback: impl Callback<F> + 'static,
+    ) {
+        let entry = context.callback_entry(callback);
+        self.operators.push(HostOperator {
+            op,
+            side,
+            wraps,
+            entry,
+        });
+    }
+}
+
+impl ContextWrapper {
+    /// Create the constructor of a class.
+    pub(super) fn new_class(
+        &self,
+        name: &str,
+        class: &HostClass,
+    ) -> Result<OwnedJsValue<'_>, ExecutionError> {
+        let new_function =
+            |entry: &CallbackEntry| OwnedJsValue::new(self, entry.new_function(self.context));
+        let string = |s: &str| self.serialize_value(JsValue::String(s.to_string()));
+
+        let create = self.eval(CLASS_SCRIPT)?.try_into_function()?;
+        let builder = create
+            .call(vec![
+                self.intrinsics(),
+                string(name)?,
+                new_function(&class.constructor),
+            ])?
+            .try_into_object()?;
+
+        let method = builder.property_require("method")?.try_into_function()?;
+        for (name, entry) in &class.methods {
+            method.call(vec![string(name)?, new_function(entry)])?;
+        }
+
+        let operator = builder.property_require("operator")?.try_into_function()?;
+        for operator_def in &class.operators {
+            let side = match operator_def.side {
+                Some(side) => string(side)?,
+                None => self.serialize_value(JsValue::Null)?,
+            };
+            operator.call(vec![
+                string(operator_def.op)?,
+                side,
+                self.serialize_value(JsValue::Bool(operator_def.wraps))?,
+                new_function(&operator_def.entry),
+            ])?;
+        }
+
+        let finish = builder.property_require("finish")?.try_into_function()?;
+        finish.call(vec![])
+    }
+}
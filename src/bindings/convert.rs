@@ -1,57 +1,407 @@
-use std::{collections::HashMap, os::raw::c_char};
+use std::{
+    collections::HashMap,
+    os::raw::{c_char, c_void},
+};
 
 use libquickjs_sys as q;
 
-use crate::{JsValue, ValueError};
+use crate::{callback::JsErrorRepr, IndexMap, JsError, JsValue, Symbol, TypedArray, ValueError};
 
-use super::{droppable_value::DroppableValue, make_cstring};
+use super::{
+    droppable_value::DroppableValue, intrinsics::BuiltinClass, make_cstring, state::ContextState,
+};
 
 use super::{
-    TAG_BOOL, TAG_EXCEPTION, TAG_FLOAT64, TAG_INT, TAG_NULL, TAG_OBJECT, TAG_STRING, TAG_UNDEFINED,
+    TAG_BOOL, TAG_EXCEPTION, TAG_FLOAT64, TAG_INT, TAG_NULL, TAG_OBJECT, TAG_STRING, TAG_SYMBOL,
+    TAG_UNDEFINED,
 };
 
 #[cfg(feature = "bigint")]
-use {
-    super::TAG_BIG_INT,
-    crate::value::bigint::{BigInt, BigIntOrI64},
-};
+use {super::TAG_BIG_INT, crate::value::bigint::BigIntOrI64};
 
-#[cfg(feature = "chrono")]
-fn js_date_constructor(context: *mut q::JSContext) -> q::JSValue {
-    let global = unsafe { q::JS_GetGlobalObject(context) };
-    assert_eq!(global.tag, TAG_OBJECT);
+#[cfg(feature = "bignum")]
+use super::{TAG_BIG_DECIMAL, TAG_BIG_FLOAT};
 
-    let date_constructor = unsafe {
-        q::JS_GetPropertyStr(
+fn js_null() -> q::JSValue {
+    q::JSValue {
+        u: q::JSValueUnion { int32: 0 },
+        tag: TAG_NULL,
+    }
+}
+
+/// Create a value by calling the builtin function `name` with a string, like
+/// `BigInt("123")`, and check that the result has the expected tag.
+#[cfg(any(feature = "bigint", feature = "bignum"))]
+fn js_call_with_string(
+    context: *mut q::JSContext,
+    name: &str,
+    value: &str,
+    tag: i64,
+) -> Result<q::JSValue, ValueError> {
+    let s = unsafe {
+        q::JS_NewStringLen(
             context,
-            global,
-            std::ffi::CStr::from_bytes_with_nul(b"Date\0")
-                .unwrap()
-                .as_ptr(),
+            value.as_ptr() as *const c_char,
+            value.len() as q::size_t,
         )
     };
-    assert_eq!(date_constructor.tag, TAG_OBJECT);
-    unsafe { q::JS_FreeValue(context, global) };
-    date_constructor
+    if s.tag != TAG_STRING {
+        unsafe { q::JS_FreeValue(context, s) };
+        return Err(ValueError::Internal(format!(
+            "Could not construct String object needed to create {} object",
+            name
+        )));
+    }
+    js_call_with_tag(context, name, s, tag)
 }
 
-#[cfg(feature = "bigint")]
-fn js_create_bigint_function(context: *mut q::JSContext) -> q::JSValue {
-    let global = unsafe { q::JS_GetGlobalObject(context) };
-    assert_eq!(global.tag, TAG_OBJECT);
+/// Call the builtin function `name` with a single argument, which is freed,
+/// and check that the result has the expected tag.
+#[cfg(any(feature = "bigint", feature = "bignum"))]
+fn js_call_with_tag(
+    context: *mut q::JSContext,
+    name: &str,
+    arg: q::JSValue,
+    tag: i64,
+) -> Result<q::JSValue, ValueError> {
+    let value = js_call_intrinsic(context, name, js_null(), vec![arg]);
+    unsafe { q::JS_FreeValue(context, arg) };
+    let value = value?;
+
+    if value.tag != tag {
+        unsafe { q::JS_FreeValue(context, value) };
+        return Err(ValueError::Internal(format!(
+            "Could not construct {} object",
+            name
+        )));
+    }
+
+    Ok(value)
+}
+
+/// Convert a numeric value like a `BigInt` to its decimal representation.
+#[cfg(any(feature = "bigint", feature = "bignum"))]
+fn js_number_to_string(
+    context: *mut q::JSContext,
+    value: &q::JSValue,
+    name: &str,
+) -> Result<String, ValueError> {
+    let ptr = unsafe { q::JS_ToCStringLen2(context, std::ptr::null_mut(), *value, 0) };
+
+    if ptr.is_null() {
+        return Err(ValueError::Internal(format!(
+            "Could not convert {} to string: got a null pointer",
+            name
+        )));
+    }
+
+    let cstr = unsafe { std::ffi::CStr::from_ptr(ptr) };
+    let s = cstr.to_string_lossy().into_owned();
 
-    let bigint_function = unsafe {
-        q::JS_GetPropertyStr(
+    // Free the c string.
+    unsafe { q::JS_FreeCString(context, ptr) };
+
+    Ok(s)
+}
+
+/// Convert a `BigFloat` to its exact hexadecimal representation with
+/// `toString(16)`.
+#[cfg(feature = "bignum")]
+fn js_bigfloat_to_hex(
+    context: *mut q::JSContext,
+    value: &q::JSValue,
+) -> Result<String, ValueError> {
+    let name = make_cstring("toString")?;
+    let atom = unsafe { q::JS_NewAtom(context, name.as_ptr()) };
+    let mut args = vec![q::JSValue {
+        u: q::JSValueUnion { int32: 16 },
+        tag: TAG_INT,
+    }];
+    let s = unsafe { q::JS_Invoke(context, *value, atom, 1, args.as_mut_ptr()) };
+    unsafe { q::JS_FreeAtom(context, atom) };
+    let s = DroppableValue::new(s, |&mut s| unsafe {
+        q::JS_FreeValue(context, s);
+    });
+    if s.tag != TAG_STRING {
+        return Err(ValueError::Internal(
+            "Could not convert BigFloat to string".into(),
+        ));
+    }
+    js_number_to_string(context, &s, "BigFloat")
+}
+
+unsafe extern "C" fn free_array_buffer_data<T>(
+    _rt: *mut q::JSRuntime,
+    opaque: *mut c_void,
+    _ptr: *mut c_void,
+) {
+    drop(Box::from_raw(opaque as *mut Vec<T>));
+}
+
+/// Create an ArrayBuffer that takes ownership of the memory of `values`,
+/// without copying.
+///
+/// The vector is dropped when the buffer is garbage collected.
+fn new_array_buffer<T>(
+    context: *mut q::JSContext,
+    values: Vec<T>,
+) -> Result<q::JSValue, ValueError> {
+    let byte_length = values.len() * std::mem::size_of::<T>();
+    let mut values = Box::new(values);
+    let data = values.as_mut_ptr() as *mut u8;
+    let opaque = Box::into_raw(values);
+
+    let buffer = unsafe {
+        q::JS_NewArrayBuffer(
             context,
-            global,
-            std::ffi::CStr::from_bytes_with_nul(b"BigInt\0")
-                .unwrap()
-                .as_ptr(),
+            data,
+            byte_length as q::size_t,
+            Some(free_array_buffer_data::<T>),
+            opaque as *mut c_void,
+            0,
         )
     };
-    assert_eq!(bigint_function.tag, TAG_OBJECT);
-    unsafe { q::JS_FreeValue(context, global) };
-    bigint_function
+    if buffer.tag == TAG_EXCEPTION {
+        // The free function is not called if the buffer could not be created.
+        drop(unsafe { Box::from_raw(opaque) });
+        return Err(ValueError::Internal(
+            "Could not create ArrayBuffer in runtime".into(),
+        ));
+    }
+    Ok(buffer)
+}
+
+fn serialize_typed_array(
+    context: *mut q::JSContext,
+    array: TypedArray,
+) -> Result<q::JSValue, ValueError> {
+    let constructor = js_intrinsic(context, array.constructor_name())?;
+    let constructor = DroppableValue::new(constructor, |&mut constructor| unsafe {
+        q::JS_FreeValue(context, constructor);
+    });
+
+    let buffer = match array {
+        TypedArray::Int8(v) => new_array_buffer(context, v),
+        TypedArray::Uint8(v) | TypedArray::Uint8Clamped(v) => new_array_buffer(context, v),
+        TypedArray::Int16(v) => new_array_buffer(context, v),
+        TypedArray::Uint16(v) => new_array_buffer(context, v),
+        TypedArray::Int32(v) => new_array_buffer(context, v),
+        TypedArray::Uint32(v) => new_array_buffer(context, v),
+        TypedArray::Float32(v) => new_array_buffer(context, v),
+        TypedArray::Float64(v) => new_array_buffer(context, v),
+        TypedArray::BigInt64(v) => new_array_buffer(context, v),
+        TypedArray::BigUint64(v) => new_array_buffer(context, v),
+    }?;
+    let buffer = DroppableValue::new(buffer, |&mut buffer| unsafe {
+        q::JS_FreeValue(context, buffer);
+    });
+
+    let mut args = vec![*buffer];
+    let value = unsafe { q::JS_CallConstructor(context, *constructor, 1, args.as_mut_ptr()) };
+    if value.tag != TAG_OBJECT {
+        return Err(ValueError::Internal(
+            "Could not construct typed array".into(),
+        ));
+    }
+    Ok(value)
+}
+
+/// Get a property of a value by name.
+fn js_get_property(
+    context: *mut q::JSContext,
+    value: q::JSValue,
+    name: &str,
+) -> Result<q::JSValue, ValueError> {
+    let cname = make_cstring(name)?;
+    let property = unsafe { q::JS_GetPropertyStr(context, value, cname.as_ptr()) };
+    if property.tag == TAG_EXCEPTION {
+        return Err(ValueError::Internal(format!(
+            "Could not get property '{}'",
+            name
+        )));
+    }
+    Ok(property)
+}
+
+/// Get a builtin captured when the context was created.
+fn js_intrinsic(context: *mut q::JSContext, name: &str) -> Result<q::JSValue, ValueError> {
+    let state = unsafe { ContextState::from_context(context) }
+        .ok_or_else(|| ValueError::Internal("Context has no state".into()))?;
+    state.intrinsics.get(context, name)
+}
+
+/// Get the builtin class of an object, detected with the class ids captured
+/// when the context was created.
+fn js_builtin_class(
+    context: *mut q::JSContext,
+    obj: &q::JSValue,
+) -> Result<Option<BuiltinClass>, ValueError> {
+    let state = unsafe { ContextState::from_context(context) }
+        .ok_or_else(|| ValueError::Internal("Context has no state".into()))?;
+    Ok(state.intrinsics.class_of(obj))
+}
+
+/// Call a captured builtin function.
+fn js_call_intrinsic(
+    context: *mut q::JSContext,
+    name: &str,
+    this: q::JSValue,
+    mut args: Vec<q::JSValue>,
+) -> Result<q::JSValue, ValueError> {
+    let function = js_intrinsic(context, name)?;
+    let value = unsafe {
+        q::JS_Call(
+            context,
+            function,
+            this,
+            args.len() as i32,
+            args.as_mut_ptr(),
+        )
+    };
+    unsafe { q::JS_FreeValue(context, function) };
+    if value.tag == TAG_EXCEPTION {
+        return Err(ValueError::Internal(format!("Could not call {}", name)));
+    }
+    Ok(value)
+}
+
+/// Construct a `Map` or `Set` from a Javascript array of entries.
+fn js_construct_collection(
+    context: *mut q::JSContext,
+    name: &str,
+    entries: JsValue,
+) -> Result<q::JSValue, ValueError> {
+    let constructor = js_intrinsic(context, name)?;
+    let constructor = DroppableValue::new(constructor, |&mut constructor| unsafe {
+        q::JS_FreeValue(context, constructor);
+    });
+    let entries = serialize_value(context, entries)?;
+    let entries = DroppableValue::new(entries, |&mut entries| unsafe {
+        q::JS_FreeValue(context, entries);
+    });
+
+    let mut args = vec![*entries];
+    let value = unsafe { q::JS_CallConstructor(context, *constructor, 1, args.as_mut_ptr()) };
+    if value.tag != TAG_OBJECT {
+        return Err(ValueError::Internal(format!(
+            "Could not construct {}",
+            name
+        )));
+    }
+    Ok(value)
+}
+
+/// Serialize an error thrown by a callback.
+///
+/// Error objects are created with their global constructor, so they have a
+/// stack trace.
+pub(super) fn serialize_error(
+    context: *mut q::JSContext,
+    error: JsError,
+) -> Result<q::JSValue, ValueError> {
+    let (kind, message, properties) = match error.repr {
+        JsErrorRepr::Error {
+            kind,
+            message,
+            properties,
+        } => (kind, message, properties),
+        JsErrorRepr::Value(value) => return serialize_value(context, value),
+    };
+
+    let constructor = js_intrinsic(context, kind.constructor_name())?;
+    let constructor = DroppableValue::new(constructor, |&mut constructor| unsafe {
+        q::JS_FreeValue(context, constructor);
+    });
+    let message = serialize_value(context, JsValue::String(message))?;
+    let message = DroppableValue::new(message, |&mut message| unsafe {
+        q::JS_FreeValue(context, message);
+    });
+
+    let mut args = vec![*message];
+    let obj = unsafe { q::JS_CallConstructor(context, *constructor, 1, args.as_mut_ptr()) };
+    if obj.tag != TAG_OBJECT {
+        return Err(ValueError::Internal(format!(
+            "Could not construct {}",
+            kind.constructor_name()
+        )));
+    }
+
+    for (key, value) in properties {
+        let qvalue = make_cstring(key)
+            .and_then(|ckey| Ok((ckey, serialize_value(context, value)?)))
+            .inspect_err(|_| unsafe {
+                q::JS_FreeValue(context, obj);
+            });
+        let (ckey, qvalue) = qvalue?;
+
+        let ret = unsafe {
+            q::JS_DefinePropertyValueStr(
+                context,
+                obj,
+                ckey.as_ptr(),
+                qvalue,
+                q::JS_PROP_C_W_E as i32,
+            )
+        };
+        if ret < 0 {
+            unsafe { q::JS_FreeValue(context, obj) };
+            return Err(ValueError::Internal(
+                "Could not add property to error".into(),
+            ));
+        }
+    }
+
+    Ok(obj)
+}
+
+fn serialize_symbol(context: *mut q::JSContext, symbol: Symbol) -> Result<q::JSValue, ValueError> {
+    let value = match symbol {
+        Symbol::Local(description) => {
+            let args = match description {
+                Some(description) => vec![serialize_value(context, JsValue::String(description))?],
+                None => Vec::new(),
+            };
+            let args = DroppableValue::new(args, |args| {
+                for arg in args.iter() {
+                    unsafe { q::JS_FreeValue(context, *arg) };
+                }
+            });
+
+            let function = js_intrinsic(context, "Symbol")?;
+            let null = q::JSValue {
+                u: q::JSValueUnion { int32: 0 },
+                tag: TAG_NULL,
+            };
+            let value = unsafe {
+                q::JS_Call(
+                    context,
+                    function,
+                    null,
+                    args.len() as i32,
+                    args.as_ptr() as *mut q::JSValue,
+                )
+            };
+            unsafe { q::JS_FreeValue(context, function) };
+            value
+        }
+        Symbol::Registered(key) => {
+            let key = serialize_value(context, JsValue::String(key))?;
+            let value = js_call_intrinsic(context, "symbolFor", js_null(), vec![key]);
+            unsafe { q::JS_FreeValue(context, key) };
+            value?
+        }
+        Symbol::WellKnown(name) => {
+            let function = js_intrinsic(context, "Symbol")?;
+            let value = js_get_property(context, function, &name);
+            unsafe { q::JS_FreeValue(context, function) };
+            value?
+        }
+    };
+    if value.tag != TAG_SYMBOL {
+        unsafe { q::JS_FreeValue(context, value) };
+        return Err(ValueError::Internal("Could not create Symbol".into()));
+    }
+    Ok(value)
 }
 
 /// Serialize a Rust value into a quickjs runtime value.
@@ -150,12 +500,11 @@ pub(super) fn serialize_value(
             for (key, value) in map {
                 let ckey = make_cstring(key)?;
 
-                let qvalue = serialize_value(context, value).map_err(|e| {
+                let qvalue = serialize_value(context, value).inspect_err(|_| {
                     // Free the object if a property failed.
                     unsafe {
                         q::JS_FreeValue(context, obj);
                     }
-                    e
                 })?;
 
                 let ret = unsafe {
@@ -180,9 +529,27 @@ pub(super) fn serialize_value(
 
             obj
         }
+        JsValue::Map(entries) => {
+            let entries = entries
+                .into_iter()
+                .map(|(key, value)| JsValue::Array(vec![key, value]))
+                .collect();
+            js_construct_collection(context, "Map", JsValue::Array(entries))?
+        }
+        JsValue::Set(values) => js_construct_collection(context, "Set", JsValue::Array(values))?,
+        JsValue::Symbol(symbol) => serialize_symbol(context, symbol)?,
+        JsValue::Function(function) => unsafe { ContextState::from_context(context) }
+            .and_then(|state| state.functions.get(context, &function))
+            .ok_or_else(|| {
+                ValueError::Internal(
+                    "Function belongs to a different context or was released".into(),
+                )
+            })?,
+        JsValue::Bytes(bytes) => new_array_buffer(context, bytes)?,
+        JsValue::TypedArray(array) => serialize_typed_array(context, array)?,
         #[cfg(feature = "chrono")]
         JsValue::Date(datetime) => {
-            let date_constructor = js_date_constructor(context);
+            let date_constructor = js_intrinsic(context, "Date")?;
 
             let f = datetime.timestamp_millis() as f64;
 
@@ -216,50 +583,27 @@ pub(super) fn serialize_value(
         JsValue::BigInt(int) => match int.inner {
             BigIntOrI64::Int(int) => unsafe { q::JS_NewBigInt64(context, int) },
             BigIntOrI64::BigInt(bigint) => {
-                let bigint_string = bigint.to_str_radix(10);
-                let s = unsafe {
-                    q::JS_NewStringLen(
-                        context,
-                        bigint_string.as_ptr() as *const c_char,
-                        bigint_string.len() as q::size_t,
-                    )
-                };
-                let s = DroppableValue::new(s, |&mut s| unsafe {
-                    q::JS_FreeValue(context, s);
-                });
-                if (*s).tag != TAG_STRING {
-                    return Err(ValueError::Internal(
-                        "Could not construct String object needed to create BigInt object".into(),
-                    ));
-                }
-
-                let mut args = vec![*s];
-
-                let bigint_function = js_create_bigint_function(context);
-                let bigint_function =
-                    DroppableValue::new(bigint_function, |&mut bigint_function| unsafe {
-                        q::JS_FreeValue(context, bigint_function);
-                    });
-                let js_bigint = unsafe {
-                    q::JS_Call(
-                        context,
-                        *bigint_function,
-                        q::JSValue {
-                            u: q::JSValueUnion { int32: 0 },
-                            tag: TAG_NULL,
-                        },
-                        1,
-                        args.as_mut_ptr(),
-                    )
+                js_call_with_string(context, "BigInt", &bigint.to_str_radix(10), TAG_BIG_INT)?
+            }
+        },
+        #[cfg(feature = "bignum")]
+        JsValue::BigDecimal(decimal) => {
+            js_call_with_string(context, "BigDecimal", &decimal.to_string(), TAG_BIG_DECIMAL)?
+        }
+        #[cfg(feature = "bignum")]
+        JsValue::BigFloat(float) => match float.as_decimal() {
+            Some(decimal) => {
+                js_call_with_string(context, "BigFloat", &decimal.to_string(), TAG_BIG_FLOAT)?
+            }
+            // The constructor only parses finite numbers.
+            None => {
+                let number = q::JSValue {
+                    u: q::JSValueUnion {
+                        float64: float.to_f64(),
+                    },
+                    tag: TAG_FLOAT64,
                 };
-
-                if js_bigint.tag != TAG_BIG_INT {
-                    return Err(ValueError::Internal(
-                        "Could not construct BigInt object".into(),
-                    ));
-                }
-
-                js_bigint
+                js_call_with_tag(context, "BigFloat", number, TAG_BIG_FLOAT)?
             }
         },
         JsValue::__NonExhaustive => unreachable!(),
@@ -267,50 +611,201 @@ pub(super) fn serialize_value(
     Ok(v)
 }
 
-fn deserialize_array(
+/// Limits applied when converting runtime values to `JsValue`s.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ConversionLimits {
+    /// Maximum nesting depth of objects, arrays and collections.
+    pub max_depth: usize,
+    /// Maximum total number of converted values.
+    pub max_size: Option<usize>,
+    /// Never fail, for values that are only displayed, like console
+    /// arguments.
+    ///
+    /// References to an object from within itself are replaced by a marker
+    /// that [JsValue::inspect] shows as `[Circular]`, and values that can not
+    /// be converted are replaced by the error message.
+    pub lossy: bool,
+}
+
+impl ConversionLimits {
+    pub const DEFAULT_MAX_DEPTH: usize = 128;
+    pub const DEFAULT_MAX_SIZE: usize = 1_000_000;
+}
+
+impl Default for ConversionLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: Self::DEFAULT_MAX_DEPTH,
+            max_size: Some(Self::DEFAULT_MAX_SIZE),
+            lossy: false,
+        }
+    }
+}
+
+/// State of a single deserialization.
+struct DeserializeState {
     context: *mut q::JSContext,
-    raw_value: &q::JSValue,
+    limits: ConversionLimits,
+    /// Number of values converted so far.
+    size: usize,
+    /// Objects on the path from the root value to the current value.
+    path: Vec<*mut c_void>,
+    /// Sizes of the objects converted so far.
+    ///
+    /// A reference to each object is kept until the end of the conversion,
+    /// so the address is not reused by a new object.
+    converted: HashMap<*mut c_void, usize>,
+    /// Values of objects that occur more than once, which are only
+    /// converted again on their second occurrence.
+    shared: HashMap<*mut c_void, JsValue>,
+}
+
+impl DeserializeState {
+    fn new(context: *mut q::JSContext, limits: ConversionLimits) -> Self {
+        Self {
+            context,
+            limits,
+            size: 0,
+            path: Vec::new(),
+            converted: HashMap::new(),
+            shared: HashMap::new(),
+        }
+    }
+
+    fn count_value(&mut self) -> Result<(), ValueError> {
+        self.count_values(1)
+    }
+
+    fn count_values(&mut self, count: usize) -> Result<(), ValueError> {
+        self.check_size(count)?;
+        self.size += count;
+        Ok(())
+    }
+
+    /// Check that `count` more values do not exceed the size limit.
+    fn check_size(&self, count: usize) -> Result<(), ValueError> {
+        match self.limits.max_size {
+            Some(max) if self.size.saturating_add(count) > max => {
+                Err(ValueError::SizeLimitExceeded)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Enter an object, checking for cycles and the depth limit.
+    fn enter(&mut self, obj: &q::JSValue) -> Result<(), ValueError> {
+        let ptr = unsafe { obj.u.ptr };
+        if self.path.contains(&ptr) {
+            return Err(ValueError::CyclicValue);
+        }
+        if self.path.len() >= self.limits.max_depth {
+            return Err(ValueError::DepthLimitExceeded);
+        }
+        self.path.push(ptr);
+        Ok(())
+    }
+
+    fn leave(&mut self) {
+        self.path.pop();
+    }
+
+    /// Get the value of an object that was already converted more than once.
+    ///
+    /// The size of the value counts towards the size limit again.
+    fn shared_value(&mut self, obj: &q::JSValue) -> Result<Option<JsValue>, ValueError> {
+        let ptr = unsafe { obj.u.ptr };
+        if !self.shared.contains_key(&ptr) {
+            return Ok(None);
+        }
+        // The object itself was already counted.
+        self.count_values(self.converted[&ptr] - 1)?;
+        Ok(self.shared.get(&ptr).cloned())
+    }
+
+    /// Record a converted object whose value consists of `size` values.
+    fn record(&mut self, obj: &q::JSValue, value: &JsValue, size: usize) {
+        let ptr = unsafe { obj.u.ptr };
+        if self.converted.insert(ptr, size).is_some() {
+            self.shared.insert(ptr, value.clone());
+        } else {
+            unsafe { q::JS_DupValue(self.context, *obj) };
+        }
+    }
+}
+
+impl Drop for DeserializeState {
+    fn drop(&mut self) {
+        for ptr in self.converted.keys() {
+            let obj = q::JSValue {
+                u: q::JSValueUnion { ptr: *ptr },
+                tag: TAG_OBJECT,
+            };
+            unsafe { q::JS_FreeValue(self.context, obj) };
+        }
+    }
+}
+
+/// Deserialize a value that is known to be a primitive, like a property name.
+fn deserialize_primitive(
+    context: *mut q::JSContext,
+    value: &q::JSValue,
 ) -> Result<JsValue, ValueError> {
-    assert_eq!(raw_value.tag, TAG_OBJECT);
+    deserialize(
+        context,
+        value,
+        &mut DeserializeState::new(context, ConversionLimits::default()),
+    )
+}
+
+fn deserialize_array_items(
+    context: *mut q::JSContext,
+    raw_value: &q::JSValue,
+    state: &mut DeserializeState,
+) -> Result<Vec<JsValue>, ValueError> {
+    if raw_value.tag != TAG_OBJECT {
+        return Err(ValueError::Internal("Expected an array object".into()));
+    }
 
     let length_name = make_cstring("length")?;
 
     let len_raw = unsafe { q::JS_GetPropertyStr(context, *raw_value, length_name.as_ptr()) };
 
-    let len_res = deserialize_value(context, &len_raw);
+    let len_res = deserialize_primitive(context, &len_raw);
     unsafe { q::JS_FreeValue(context, len_raw) };
     let len = match len_res? {
-        JsValue::Int(x) => x,
+        JsValue::Int(x) if x >= 0 => x as u32,
+        // Lengths above i32::MAX.
+        JsValue::Float(x) if x >= 0.0 && x <= u32::MAX as f64 => x as u32,
         _ => {
             return Err(ValueError::Internal(
                 "Could not determine array length".into(),
             ));
         }
     };
+    // Fail before iterating over large sparse arrays.
+    state.check_size(len as usize)?;
 
     let mut values = Vec::new();
-    for index in 0..(len as usize) {
-        let value_raw = unsafe { q::JS_GetPropertyUint32(context, *raw_value, index as u32) };
-        if value_raw.tag == TAG_EXCEPTION {
-            return Err(ValueError::Internal("Could not build array".into()));
-        }
-        let value_res = deserialize_value(context, &value_raw);
-        unsafe { q::JS_FreeValue(context, value_raw) };
-
-        let value = value_res?;
-        values.push(value);
+    for index in 0..len {
+        values.push(deserialize_element(context, *raw_value, index, state)?);
     }
 
-    Ok(JsValue::Array(values))
+    Ok(values)
 }
 
-fn deserialize_object(context: *mut q::JSContext, obj: &q::JSValue) -> Result<JsValue, ValueError> {
+fn deserialize_object(
+    context: *mut q::JSContext,
+    obj: &q::JSValue,
+    state: &mut DeserializeState,
+) -> Result<JsValue, ValueError> {
     assert_eq!(obj.tag, TAG_OBJECT);
 
     let mut properties: *mut q::JSPropertyEnum = std::ptr::null_mut();
     let mut count: u32 = 0;
 
-    let flags = (q::JS_GPN_STRING_MASK | q::JS_GPN_SYMBOL_MASK | q::JS_GPN_ENUM_ONLY) as i32;
+    // Symbol keys can not be represented in a `JsValue::Object`, so they are
+    // skipped like non-enumerable properties.
+    let flags = (q::JS_GPN_STRING_MASK | q::JS_GPN_ENUM_ONLY) as i32;
     let ret =
         unsafe { q::JS_GetOwnPropertyNames(context, &mut properties, &mut count, *obj, flags) };
     if ret != 0 {
@@ -332,7 +827,8 @@ fn deserialize_object(context: *mut q::JSContext, obj: &q::JSValue) -> Result<Js
         }
     });
 
-    let mut map = HashMap::new();
+    // Properties are returned in enumeration order.
+    let mut map = IndexMap::with_capacity(count as usize);
     for index in 0..count {
         let prop = unsafe { (*properties).offset(index as isize) };
         let raw_value = unsafe { q::JS_GetPropertyInternal(context, *obj, (*prop).atom, *obj, 0) };
@@ -340,7 +836,7 @@ fn deserialize_object(context: *mut q::JSContext, obj: &q::JSValue) -> Result<Js
             return Err(ValueError::Internal("Could not get object property".into()));
         }
 
-        let value_res = deserialize_value(context, &raw_value);
+        let value_res = deserialize(context, &raw_value, state);
         unsafe {
             q::JS_FreeValue(context, raw_value);
         }
@@ -353,7 +849,7 @@ fn deserialize_object(context: *mut q::JSContext, obj: &q::JSValue) -> Result<Js
             ));
         }
 
-        let key_res = deserialize_value(context, &key_value);
+        let key_res = deserialize_primitive(context, &key_value);
         unsafe {
             q::JS_FreeValue(context, key_value);
         }
@@ -369,10 +865,339 @@ fn deserialize_object(context: *mut q::JSContext, obj: &q::JSValue) -> Result<Js
     Ok(JsValue::Object(map))
 }
 
+/// Copy the elements of a typed array from its raw bytes.
+fn copy_elements<T: Copy>(bytes: &[u8]) -> Vec<T> {
+    let len = bytes.len() / std::mem::size_of::<T>();
+    let mut values = Vec::<T>::with_capacity(len);
+    unsafe {
+        // Copy bytewise, the source is not necessarily aligned.
+        std::ptr::copy_nonoverlapping(
+            bytes.as_ptr(),
+            values.as_mut_ptr() as *mut u8,
+            len * std::mem::size_of::<T>(),
+        );
+        values.set_len(len);
+    }
+    values
+}
+
+type TypedArrayFromBytes = fn(&[u8]) -> TypedArray;
+
+/// Typed array classes.
+const TYPED_ARRAYS: &[(BuiltinClass, TypedArrayFromBytes)] = &[
+    (BuiltinClass::Uint8ClampedArray, |b| {
+        TypedArray::Uint8Clamped(b.to_vec())
+    }),
+    (BuiltinClass::Int8Array, |b| {
+        TypedArray::Int8(copy_elements(b))
+    }),
+    (BuiltinClass::Uint8Array, |b| TypedArray::Uint8(b.to_vec())),
+    (BuiltinClass::Int16Array, |b| {
+        TypedArray::Int16(copy_elements(b))
+    }),
+    (BuiltinClass::Uint16Array, |b| {
+        TypedArray::Uint16(copy_elements(b))
+    }),
+    (BuiltinClass::Int32Array, |b| {
+        TypedArray::Int32(copy_elements(b))
+    }),
+    (BuiltinClass::Uint32Array, |b| {
+        TypedArray::Uint32(copy_elements(b))
+    }),
+    (BuiltinClass::BigInt64Array, |b| {
+        TypedArray::BigInt64(copy_elements(b))
+    }),
+    (BuiltinClass::BigUint64Array, |b| {
+        TypedArray::BigUint64(copy_elements(b))
+    }),
+    (BuiltinClass::Float32Array, |b| {
+        TypedArray::Float32(copy_elements(b))
+    }),
+    (BuiltinClass::Float64Array, |b| {
+        TypedArray::Float64(copy_elements(b))
+    }),
+];
+
+/// Get the data of an ArrayBuffer.
+///
+/// Fails for detached buffers.
+fn array_buffer_data(context: *mut q::JSContext, buffer: &q::JSValue) -> Result<&[u8], ValueError> {
+    let mut size: q::size_t = 0;
+    let data = unsafe { q::JS_GetArrayBuffer(context, &mut size, *buffer) };
+    if data.is_null() {
+        // Clear the TypeError thrown for detached buffers.
+        unsafe { q::JS_FreeValue(context, q::JS_GetException(context)) };
+        return Err(ValueError::Internal("ArrayBuffer is detached".into()));
+    }
+    Ok(unsafe { std::slice::from_raw_parts(data, size as usize) })
+}
+
+/// Deserialize an ArrayBuffer or a typed array.
+///
+/// Returns `None` if the object is neither.
+fn deserialize_binary(
+    context: *mut q::JSContext,
+    obj: &q::JSValue,
+    class: Option<BuiltinClass>,
+) -> Result<Option<JsValue>, ValueError> {
+    if class == Some(BuiltinClass::ArrayBuffer) {
+        let bytes = array_buffer_data(context, obj)?;
+        return Ok(Some(JsValue::Bytes(bytes.to_vec())));
+    }
+
+    let from_bytes = TYPED_ARRAYS.iter().find(|(c, _)| Some(*c) == class);
+    let from_bytes = match from_bytes {
+        Some((_, from_bytes)) => from_bytes,
+        None => return Ok(None),
+    };
+
+    let mut offset: q::size_t = 0;
+    let mut length: q::size_t = 0;
+    let mut element_size: q::size_t = 0;
+    let buffer = unsafe {
+        q::JS_GetTypedArrayBuffer(context, *obj, &mut offset, &mut length, &mut element_size)
+    };
+    if buffer.tag == TAG_EXCEPTION {
+        unsafe { q::JS_FreeValue(context, q::JS_GetException(context)) };
+        return Err(ValueError::Internal(
+            "Could not get typed array buffer".into(),
+        ));
+    }
+    let buffer = DroppableValue::new(buffer, |&mut buffer| unsafe {
+        q::JS_FreeValue(context, buffer);
+    });
+
+    let data = array_buffer_data(context, &buffer)?;
+    let bytes = data
+        .get(offset as usize..(offset + length) as usize)
+        .ok_or_else(|| ValueError::Internal("Typed array is out of bounds".into()))?;
+
+    Ok(Some(JsValue::TypedArray(from_bytes(bytes))))
+}
+
+/// Deserialize a `Map` or a `Set`.
+///
+/// Returns `None` if the object is neither.
+fn deserialize_collection(
+    context: *mut q::JSContext,
+    obj: &q::JSValue,
+    class: Option<BuiltinClass>,
+    state: &mut DeserializeState,
+) -> Result<Option<JsValue>, ValueError> {
+    let is_map = match class {
+        Some(BuiltinClass::Map) => true,
+        Some(BuiltinClass::Set) => false,
+        _ => return Ok(None),
+    };
+
+    // Iterate with the captured builtins, which scripts can not replace.
+    let (iterate, next) = if is_map {
+        ("mapEntries", "mapIteratorNext")
+    } else {
+        ("setValues", "setIteratorNext")
+    };
+    let iterator = js_call_intrinsic(context, iterate, *obj, vec![])?;
+    let iterator = DroppableValue::new(iterator, |&mut iterator| unsafe {
+        q::JS_FreeValue(context, iterator);
+    });
+
+    let mut items = Vec::new();
+    let mut entries = Vec::new();
+    loop {
+        let result = js_call_intrinsic(context, next, *iterator, vec![])?;
+        let result = DroppableValue::new(result, |&mut result| unsafe {
+            q::JS_FreeValue(context, result);
+        });
+        let done = js_get_property(context, *result, "done")?;
+        let is_done = unsafe { q::JS_ToBool(context, done) } != 0;
+        unsafe { q::JS_FreeValue(context, done) };
+        if is_done {
+            break;
+        }
+        let value = js_get_property(context, *result, "value")?;
+        let value = DroppableValue::new(value, |&mut value| unsafe {
+            q::JS_FreeValue(context, value);
+        });
+
+        if is_map {
+            // Entries are fresh `[key, value]` arrays.
+            let key = deserialize_element(context, *value, 0, state)?;
+            let value = deserialize_element(context, *value, 1, state)?;
+            entries.push((key, value));
+        } else {
+            items.push(deserialize(context, &value, state)?);
+        }
+    }
+
+    if is_map {
+        Ok(Some(JsValue::Map(entries)))
+    } else {
+        Ok(Some(JsValue::Set(items)))
+    }
+}
+
+/// Deserialize the element of an array at `index`.
+fn deserialize_element(
+    context: *mut q::JSContext,
+    array: q::JSValue,
+    index: u32,
+    state: &mut DeserializeState,
+) -> Result<JsValue, ValueError> {
+    let value_raw = unsafe { q::JS_GetPropertyUint32(context, array, index) };
+    if value_raw.tag == TAG_EXCEPTION {
+        return Err(ValueError::Internal("Could not get array element".into()));
+    }
+    let value_res = deserialize(context, &value_raw, state);
+    unsafe { q::JS_FreeValue(context, value_raw) };
+    value_res
+}
+
+fn deserialize_symbol(
+    context: *mut q::JSContext,
+    value: &q::JSValue,
+) -> Result<JsValue, ValueError> {
+    let key = js_call_intrinsic(context, "symbolKeyFor", js_null(), vec![*value])?;
+    let key_res = deserialize_primitive(context, &key);
+    unsafe { q::JS_FreeValue(context, key) };
+    if let JsValue::String(key) = key_res? {
+        return Ok(JsValue::Symbol(Symbol::Registered(key)));
+    }
+
+    let function = js_intrinsic(context, "Symbol")?;
+    let function = DroppableValue::new(function, |&mut function| unsafe {
+        q::JS_FreeValue(context, function);
+    });
+    for name in Symbol::WELL_KNOWN {
+        let well_known = js_get_property(context, *function, name)?;
+        // Symbols are compared by identity.
+        let is_same = well_known.tag == TAG_SYMBOL && unsafe { well_known.u.ptr == value.u.ptr };
+        unsafe { q::JS_FreeValue(context, well_known) };
+        if is_same {
+            return Ok(JsValue::Symbol(Symbol::WellKnown(name.to_string())));
+        }
+    }
+
+    let description = js_call_intrinsic(context, "symbolDescription", *value, vec![])?;
+    let description_res = deserialize_primitive(context, &description);
+    unsafe { q::JS_FreeValue(context, description) };
+    match description_res? {
+        JsValue::String(description) => Ok(JsValue::Symbol(Symbol::Local(Some(description)))),
+        _ => Ok(JsValue::Symbol(Symbol::Local(None))),
+    }
+}
+
+/// Deserialize a `Date`.
+///
+/// Returns `None` if the object is not a date.
+#[cfg(feature = "chrono")]
+fn deserialize_date(
+    context: *mut q::JSContext,
+    obj: &q::JSValue,
+    class: Option<BuiltinClass>,
+) -> Result<Option<JsValue>, ValueError> {
+    use chrono::offset::TimeZone;
+
+    // Dates keep their time value as the opaque data, which is null for the
+    // epoch itself. Such dates are recognized by their prototype, and the
+    // captured `getTime` checks that the object really is a date.
+    if class != Some(BuiltinClass::Date) {
+        let date_prototype = js_intrinsic(context, "datePrototype")?;
+        let prototype = unsafe { q::JS_GetPrototype(context, *obj) };
+        let is_date_prototype =
+            prototype.tag == TAG_OBJECT && unsafe { prototype.u.ptr == date_prototype.u.ptr };
+        unsafe {
+            if prototype.tag == TAG_EXCEPTION {
+                // Thrown by the `getPrototypeOf` trap of a proxy.
+                q::JS_FreeValue(context, q::JS_GetException(context));
+            }
+            q::JS_FreeValue(context, prototype);
+            q::JS_FreeValue(context, date_prototype);
+        }
+        if !is_date_prototype {
+            return Ok(None);
+        }
+    }
+
+    let timestamp_raw = match js_call_intrinsic(context, "dateGetTime", *obj, vec![]) {
+        Ok(timestamp_raw) => timestamp_raw,
+        Err(_) => {
+            // Not a date, but an object inheriting from the Date prototype.
+            unsafe { q::JS_FreeValue(context, q::JS_GetException(context)) };
+            return Ok(None);
+        }
+    };
+
+    let millis = if timestamp_raw.tag == TAG_FLOAT64 {
+        Some(unsafe { timestamp_raw.u.float64 } as i64)
+    } else if timestamp_raw.tag == TAG_INT {
+        Some(unsafe { timestamp_raw.u.int32 } as i64)
+    } else {
+        None
+    };
+    millis
+        .and_then(|f| chrono::Utc.timestamp_millis_opt(f).single())
+        .map(|datetime| Some(JsValue::Date(datetime)))
+        .ok_or_else(|| {
+            ValueError::Internal("Could not convert 'Date' instance to timestamp".into())
+        })
+}
+
+fn deserialize_object_value(
+    context: *mut q::JSContext,
+    r: &q::JSValue,
+    state: &mut DeserializeState,
+) -> Result<JsValue, ValueError> {
+    if unsafe { q::JS_IsFunction(context, *r) } != 0 {
+        let context_state = unsafe { ContextState::from_context(context) }
+            .ok_or_else(|| ValueError::Internal("Context has no function registry".into()))?;
+        return Ok(JsValue::Function(
+            context_state.functions.register(context, r),
+        ));
+    }
+
+    let is_array = unsafe { q::JS_IsArray(context, *r) } > 0;
+    if is_array {
+        deserialize_array_items(context, r, state).map(JsValue::Array)
+    } else {
+        let class = js_builtin_class(context, r)?;
+        if let Some(value) = deserialize_binary(context, r, class)? {
+            return Ok(value);
+        }
+        if let Some(value) = deserialize_collection(context, r, class, state)? {
+            return Ok(value);
+        }
+
+        #[cfg(feature = "chrono")]
+        if let Some(value) = deserialize_date(context, r, class)? {
+            return Ok(value);
+        }
+
+        deserialize_object(context, r, state)
+    }
+}
+
+/// Deserialize a quickjs runtime value into a Rust value.
+///
+/// Fails if the value contains a cycle or exceeds the limits, unless the
+/// limits allow a lossy conversion.
 pub(super) fn deserialize_value(
     context: *mut q::JSContext,
     value: &q::JSValue,
+    limits: ConversionLimits,
 ) -> Result<JsValue, ValueError> {
+    let res = deserialize(context, value, &mut DeserializeState::new(context, limits));
+    match res {
+        Err(e) if limits.lossy => Ok(JsValue::String(format!("[{}]", e))),
+        res => res,
+    }
+}
+
+fn deserialize(
+    context: *mut q::JSContext,
+    value: &q::JSValue,
+    state: &mut DeserializeState,
+) -> Result<JsValue, ValueError> {
+    state.count_value()?;
     let r = value;
 
     match r.tag {
@@ -420,87 +1245,59 @@ pub(super) fn deserialize_value(
         }
         // Object.
         TAG_OBJECT => {
-            let is_array = unsafe { q::JS_IsArray(context, *r) } > 0;
-            if is_array {
-                deserialize_array(context, r)
-            } else {
-                #[cfg(feature = "chrono")]
-                {
-                    use chrono::offset::TimeZone;
-
-                    let date_constructor = js_date_constructor(context);
-                    let is_date = unsafe { q::JS_IsInstanceOf(context, *r, date_constructor) > 0 };
-
-                    if is_date {
-                        let getter = unsafe {
-                            q::JS_GetPropertyStr(
-                                context,
-                                *r,
-                                std::ffi::CStr::from_bytes_with_nul(b"getTime\0")
-                                    .unwrap()
-                                    .as_ptr(),
-                            )
-                        };
-                        assert_eq!(getter.tag, TAG_OBJECT);
-
-                        let timestamp_raw =
-                            unsafe { q::JS_Call(context, getter, *r, 0, std::ptr::null_mut()) };
-
-                        unsafe {
-                            q::JS_FreeValue(context, getter);
-                            q::JS_FreeValue(context, date_constructor);
-                        };
-
-                        let res = if timestamp_raw.tag == TAG_FLOAT64 {
-                            let f = unsafe { timestamp_raw.u.float64 } as i64;
-                            let datetime = chrono::Utc.timestamp_millis(f);
-                            Ok(JsValue::Date(datetime))
-                        } else if timestamp_raw.tag == TAG_INT {
-                            let f = unsafe { timestamp_raw.u.int32 } as i64;
-                            let datetime = chrono::Utc.timestamp_millis(f);
-                            Ok(JsValue::Date(datetime))
-                        } else {
-                            Err(ValueError::Internal(
-                                "Could not convert 'Date' instance to timestamp".into(),
-                            ))
-                        };
-                        return res;
-                    } else {
-                        unsafe { q::JS_FreeValue(context, date_constructor) };
-                    }
+            if let Some(value) = state.shared_value(r)? {
+                return Ok(value);
+            }
+            let start = state.size;
+            match state.enter(r) {
+                Err(ValueError::CyclicValue) if state.limits.lossy => {
+                    return Ok(JsValue::String(crate::value::CIRCULAR.into()));
                 }
-
-                deserialize_object(context, r)
+                res => res?,
             }
+            let res = deserialize_object_value(context, r, state);
+            state.leave();
+            let value = res?;
+            state.record(r, &value, state.size - start + 1);
+            Ok(value)
         }
+        // Symbol.
+        TAG_SYMBOL => deserialize_symbol(context, r),
         // BigInt
         #[cfg(feature = "bigint")]
         TAG_BIG_INT => {
-            let mut int: i64 = 0;
-            let ret = unsafe { q::JS_ToBigInt64(context, &mut int, *r) };
-            if ret == 0 {
-                Ok(JsValue::BigInt(BigInt {
-                    inner: BigIntOrI64::Int(int),
-                }))
-            } else {
-                let ptr = unsafe { q::JS_ToCStringLen2(context, std::ptr::null_mut(), *r, 0) };
-
-                if ptr.is_null() {
-                    return Err(ValueError::Internal(
-                        "Could not convert BigInt to string: got a null pointer".into(),
-                    ));
-                }
-
-                let cstr = unsafe { std::ffi::CStr::from_ptr(ptr) };
-                let bigint = num_bigint::BigInt::parse_bytes(cstr.to_bytes(), 10).unwrap();
-
-                // Free the c string.
-                unsafe { q::JS_FreeCString(context, ptr) };
-
-                Ok(JsValue::BigInt(BigInt {
-                    inner: BigIntOrI64::BigInt(bigint),
-                }))
-            }
+            // Convert through the decimal representation: `JS_ToBigInt64`
+            // silently wraps out of range values in unpatched QuickJS builds.
+            let s = js_number_to_string(context, r, "BigInt")?;
+            let bigint = num_bigint::BigInt::parse_bytes(s.as_bytes(), 10).ok_or_else(|| {
+                ValueError::Internal("Could not parse BigInt string representation".into())
+            })?;
+            Ok(JsValue::BigInt(bigint.into()))
+        }
+        // BigDecimal
+        #[cfg(feature = "bignum")]
+        TAG_BIG_DECIMAL => {
+            let s = js_number_to_string(context, r, "BigDecimal")?;
+            // BigDecimal values are always finite, operations like a division
+            // by zero throw instead.
+            s.parse().map(JsValue::BigDecimal).map_err(|_| {
+                ValueError::Internal(format!("Could not convert BigDecimal {} to a decimal", s))
+            })
+        }
+        // BigFloat
+        #[cfg(feature = "bignum")]
+        TAG_BIG_FLOAT => {
+            // The decimal representation is rounded, the hexadecimal one
+            // is exact.
+            let s = js_bigfloat_to_hex(context, r)?;
+            crate::value::bigfloat::parse_hex(&s)
+                .map(JsValue::BigFloat)
+                .ok_or_else(|| {
+                    ValueError::Internal(format!(
+                        "Could not parse BigFloat string representation {}",
+                        s
+                    ))
+                })
         }
         x => Err(ValueError::Internal(format!(
             "Unhandled JS_TAG value: {}",
@@ -508,3 +1305,40 @@ pub(super) fn deserialize_value(
         ))),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bindings::ContextWrapper;
+
+    #[test]
+    fn test_detached_array_buffer() {
+        let ctx = ContextWrapper::new(None).unwrap();
+        let array = ctx.eval(" new Uint8Array([1, 2, 3]) ").unwrap();
+        let buffer = ctx.eval(" new ArrayBuffer(4) ").unwrap();
+
+        unsafe {
+            let array_buffer = q::JS_GetTypedArrayBuffer(
+                ctx.context,
+                *array.as_inner(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+            );
+            q::JS_DetachArrayBuffer(ctx.context, array_buffer);
+            q::JS_FreeValue(ctx.context, array_buffer);
+            q::JS_DetachArrayBuffer(ctx.context, *buffer.as_inner());
+        }
+
+        for value in &[&array, &buffer] {
+            let res = deserialize_value(
+                ctx.context,
+                unsafe { value.as_inner() },
+                ConversionLimits::default(),
+            );
+            assert!(matches!(res, Err(ValueError::Internal(_))), "{:?}", res);
+            // No exception is left pending.
+            assert!(ctx.get_exception().is_none());
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use libquickjs_sys as q;
+
+use crate::{value::function::ReleaseQueue, JsFunction};
+
+/// Keeps the Javascript functions referenced by [JsFunction] handles alive.
+pub(crate) struct FunctionRegistry {
+    next_id: Cell<u64>,
+    functions: RefCell<HashMap<u64, q::JSValue>>,
+    released: ReleaseQueue,
+}
+
+impl FunctionRegistry {
+    pub fn new() -> Self {
+        Self {
+            next_id: Cell::new(0),
+            functions: RefCell::new(HashMap::new()),
+            released: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Keep a reference to a function and return a handle to it.
+    pub fn register(&self, context: *mut q::JSContext, function: &q::JSValue) -> JsFunction {
+        self.free_released(context);
+
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        unsafe { q::JS_DupValue(context, *function) };
+        self.functions.borrow_mut().insert(id, *function);
+        JsFunction::new(id, self.released.clone())
+    }
+
+    /// Get a new reference to the function of a handle.
+    ///
+    /// Returns `None` if the handle belongs to a different registry or the
+    /// function was freed.
+    pub fn get(&self, context: *mut q::JSContext, function: &JsFunction) -> Option<q::JSValue> {
+        if !function.belongs_to(&self.released) {
+            return None;
+        }
+        let value = *self.functions.borrow().get(&function.id())?;
+        unsafe { q::JS_DupValue(context, value) };
+        Some(value)
+    }
+
+    /// Free the functions whose handles were dropped.
+    pub fn free_released(&self, context: *mut q::JSContext) {
+        let ids = std::mem::take(&mut *self.released.lock().unwrap());
+        let values = {
+            let mut functions = self.functions.borrow_mut();
+            ids.into_iter()
+                .filter_map(|id| functions.remove(&id))
+                .collect::<Vec<_>>()
+        };
+        for value in values {
+            unsafe { q::JS_FreeValue(context, value) };
+        }
+    }
+
+    /// Free all functions, invalidating all handles.
+    pub fn clear(&self, context: *mut q::JSContext) {
+        self.released.lock().unwrap().clear();
+        let values = std::mem::take(&mut *self.functions.borrow_mut());
+        for (_, value) in values {
+            unsafe { q::JS_FreeValue(context, value) };
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use libquickjs_sys as q;
+
+use crate::{
+    callback::{BoxedCallback, Callback},
+    ExecutionError, JsValue,
+};
+
+use super::{value::OwnedJsObject, CallbackEntry, ContextWrapper, HostClass, OwnedJsValue};
+
+/// Defines a data property that is replaced by the value of `init` on first
+/// access, or by the assigned value.
+const LAZY_PROPERTY_SCRIPT: &str = r#"
+    (function ({ defineProperty }, object, name, init) {
+        const define = value => defineProperty(object, name, {
+            __proto__: null,
+            value,
+            writable: true,
+            enumerable: true,
+            configurable: true,
+        });
+        defineProperty(object, name, {
+            __proto__: null,
+            get() {
+                const value = init();
+                define(value);
+                return value;
+            },
+            set: define,
+            enumerable: true,
+            configurable: true,
+        });
+    })
+"#;
+
+#[derive(Clone)]
+enum HostProperty {
+    Function(CallbackEntry),
+    /// A value, with the flags of the object if no flags are given.
+    Value {
+        value: JsValue,
+        flags: Option<i32>,
+    },
+    Accessor {
+        get: Option<CallbackEntry>,
+        set: Option<CallbackEntry>,
+        flags: i32,
+    },
+    Lazy(CallbackEntry),
+    Class(HostClass),
+}
+
+/// An object with host functions and constants, installed at a path of
+/// nested objects below the global object.
+#[derive(Clone)]
+pub(crate) struct HostObject {
+    path: Vec<String>,
+    properties: Vec<(String, HostProperty)>,
+    pub read_only: bool,
+    pub enumerable: bool,
+    pub frozen: bool,
+}
+
+impl HostObject {
+    /// Create an object for a dot separated path, like `host.fs`.
+    ///
+    /// An empty path is the global object.
+    pub fn new(path: &str) -> Self {
+        let path = if path.is_empty() {
+            Vec::new()
+        } else {
+            path.split('.').map(|name| name.to_string()).collect()
+        };
+        Self {
+            path,
+            properties: Vec::new(),
+            read_only: false,
+            enumerable: true,
+            frozen: false,
+        }
+    }
+
+    pub fn add_function<F>(
+        &mut self,
+        context: &ContextWrapper,
+        name: &str,
+        callback: impl Callback<F> + 'static,
+    ) {
+        let entry = context.callback_entry(callback);
+        self.properties
+            .push((name.to_string(), HostProperty::Function(entry)));
+    }
+
+    pub fn add_value(&mut self, name: &str, value: JsValue, flags: Option<i32>) {
+        self.properties
+            .push((name.to_string(), HostProperty::Value { value, flags }));
+    }
+
+    pub fn add_accessor(
+        &mut self,
+        context: &ContextWrapper,
+        name: &str,
+        get: Option<BoxedCallback>,
+        set: Option<BoxedCallback>,
+        flags: i32,
+    ) {
+        let get = get.map(|get| context.callback_entry(get));
+        let set = set.map(|set| context.callback_entry(set));
+        self.properties
+            .push((name.to_string(), HostProperty::Accessor { get, set, flags }));
+    }
+
+    pub fn add_lazy<F>(
+        &mut self,
+        context: &ContextWrapper,
+        name: &str,
+        init: impl Callback<F> + 'static,
+    ) {
+        let entry = context.callback_entry(init);
+        self.properties
+            .push((name.to_string(), HostProperty::Lazy(entry)));
+    }
+
+    pub fn add_class(&mut self, name: &str, class: HostClass) {
+        self.properties
+            .push((name.to_string(), HostProperty::Class(class)));
+    }
+
+    fn property_flags(&self) -> i32 {
+        let mut flags = 0;
+        if !self.read_only {
+            flags |= q::JS_PROP_WRITABLE | q::JS_PROP_CONFIGURABLE;
+        }
+        if self.enumerable {
+            flags |= q::JS_PROP_ENUMERABLE;
+        }
+        flags as i32
+    }
+}
+
+impl ContextWrapper {
+    /// Install a host object and remember it, so it is installed again on
+    /// reset.
+    ///
+    /// Properties replace the properties with the same name of host objects
+    /// added earlier at the same path.
+    pub fn add_host_object(&self, object: HostObject) -> Result<(), ExecutionError> {
+        if object.path.iter().any(|name| name.is_empty()) {
+            return Err(ExecutionError::Internal(format!(
+                "Invalid namespace path '{}'",
+                object.path.join(".")
+            )));
+        }
+        self.install_host_object(&object)?;
+
+        let mut objects = self.host_objects.lock().unwrap();
+        objects.retain_mut(|existing| {
+            if existing.path != object.path || existing.properties.is_empty() {
+                return true;
+            }
+            existing
+                .properties
+                .retain(|(name, _)| !object.properties.iter().any(|(n, _)| n == name));
+            // Objects whose properties were all replaced are dropped.
+            !existing.properties.is_empty()
+        });
+        objects.push(object);
+        Ok(())
+    }
+
+    /// Install a host object, reusing objects that already exist on the path.
+    pub(super) fn install_host_object(&self, object: &HostObject) -> Result<(), ExecutionError> {
+        let flags = object.property_flags();
+
+        let mut target = self.global()?;
+        for (index, name) in object.path.iter().enumerate() {
+            target = match target.property(name)? {
+                Some(value) if value.is_object() => value.try_into_object()?,
+                _ => {
+                    let raw = unsafe { q::JS_NewObject(self.context) };
+                    let value = OwnedJsValue::new(self, raw);
+                    if value.is_exception() {
+                        return Err(ExecutionError::Internal("Could not create object".into()));
+                    }
+                    let path_flags = if index + 1 == object.path.len() {
+                        flags
+                    } else {
+                        q::JS_PROP_C_W_E as i32
+                    };
+                    target.define_property(name, value.clone(), path_flags)?;
+                    value.try_into_object()?
+                }
+            };
+        }
+
+        for (name, property) in &object.properties {
+            match property {
+                HostProperty::Function(entry) => {
+                    let value = OwnedJsValue::new(self, entry.new_function(self.context));
+                    target.define_property(name, value, flags)?;
+                }
+                HostProperty::Value {
+                    value,
+                    flags: value_flags,
+                } => {
+                    let value = self.serialize_value(value.clone())?;
+                    target.define_property(name, value, value_flags.unwrap_or(flags))?;
+                }
+                HostProperty::Accessor {
+                    get,
+                    set,
+                    flags: accessor_flags,
+                } => {
+                    let new_function = |entry: &CallbackEntry| {
+                        OwnedJsValue::new(self, entry.new_function(self.context))
+                    };
+                    target.define_accessor(
+                        name,
+                        get.as_ref().map(new_function),
+                        set.as_ref().map(new_function),
+                        *accessor_flags,
+                    )?;
+                }
+                HostProperty::Lazy(entry) => {
+                    let init = OwnedJsValue::new(self, entry.new_function(self.context));
+                    let define = self.eval(LAZY_PROPERTY_SCRIPT)?.try_into_function()?;
+                    define.call(vec![
+                        self.intrinsics(),
+                        target.clone().into_value(),
+                        self.serialize_value(JsValue::String(name.clone()))?,
+                        init,
+                    ])?;
+                }
+                HostProperty::Class(class) => {
+                    let value = self.new_class(name, class)?;
+                    target.define_property(name, value, flags)?;
+                }
+            }
+        }
+
+        if object.frozen {
+            self.freeze(target)?;
+        }
+        Ok(())
+    }
+
+    fn freeze(&self, object: OwnedJsObject<'_>) -> Result<(), ExecutionError> {
+        let freeze = self
+            .intrinsics()
+            .try_into_object()?
+            .property_require("freeze")?
+            .try_into_function()?;
+        freeze.call(vec![object.into_value()])?;
+        Ok(())
+    }
+}
@@ -0,0 +1,256 @@
+use std::cell::{Cell, RefCell};
+
+use libquickjs_sys as q;
+
+use crate::ValueError;
+
+use super::{make_cstring, TAG_EXCEPTION, TAG_INT, TAG_OBJECT, TAG_UNDEFINED};
+
+/// Captures the constructors and builtin functions used by value
+/// conversions and the Javascript glue.
+///
+/// Evaluated when a context is created, before any user code runs, so later
+/// changes to the globals by scripts do not affect the conversions.
+const INTRINSICS_SCRIPT: &str = r#"
+    (() => {
+        const intrinsics = Object.create(null);
+        for (const name of [
+            "Date", "Map", "Set", "Symbol", "Promise", "ArrayBuffer", "BigInt", "BigFloat", "BigDecimal",
+            "String", "parseInt", "parseFloat",
+            "Error", "TypeError", "RangeError", "ReferenceError", "SyntaxError", "InternalError",
+            "Int8Array", "Uint8Array", "Uint8ClampedArray", "Int16Array", "Uint16Array",
+            "Int32Array", "Uint32Array", "Float32Array", "Float64Array",
+            "BigInt64Array", "BigUint64Array",
+        ]) {
+            intrinsics[name] = globalThis[name];
+        }
+        intrinsics.freeze = Object.freeze;
+        intrinsics.defineProperty = Object.defineProperty;
+        intrinsics.setPrototypeOf = Object.setPrototypeOf;
+        intrinsics.keys = Object.keys;
+        intrinsics.apply = Reflect.apply;
+        intrinsics.bind = Function.prototype.bind;
+        intrinsics.Number = Number;
+        intrinsics.isArray = Array.isArray;
+        if (typeof Operators !== "undefined") {
+            intrinsics.Operators = Operators;
+            intrinsics.operatorCreate = Operators.create;
+            intrinsics.operatorSet = Symbol.operatorSet;
+        }
+        intrinsics.datePrototype = Date.prototype;
+        intrinsics.dateGetTime = Date.prototype.getTime;
+        intrinsics.symbolFor = Symbol.for;
+        intrinsics.symbolKeyFor = Symbol.keyFor;
+        intrinsics.symbolDescription =
+            Object.getOwnPropertyDescriptor(Symbol.prototype, "description").get;
+        intrinsics.mapEntries = Map.prototype.entries;
+        intrinsics.mapIteratorNext = Object.getPrototypeOf(new Map().entries()).next;
+        intrinsics.setValues = Set.prototype.values;
+        intrinsics.setIteratorNext = Object.getPrototypeOf(new Set().values()).next;
+        intrinsics.promiseThen = Promise.prototype.then;
+        intrinsics.promiseResolve = Promise.resolve;
+        return intrinsics;
+    })()
+"#;
+
+/// Builtin classes whose instances are converted to dedicated `JsValue`s.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum BuiltinClass {
+    ArrayBuffer,
+    Map,
+    Set,
+    Date,
+    Uint8ClampedArray,
+    Int8Array,
+    Uint8Array,
+    Int16Array,
+    Uint16Array,
+    Int32Array,
+    Uint32Array,
+    BigInt64Array,
+    BigUint64Array,
+    Float32Array,
+    Float64Array,
+}
+
+impl BuiltinClass {
+    const ALL: [Self; 15] = [
+        Self::ArrayBuffer,
+        Self::Map,
+        Self::Set,
+        Self::Date,
+        Self::Uint8ClampedArray,
+        Self::Int8Array,
+        Self::Uint8Array,
+        Self::Int16Array,
+        Self::Uint16Array,
+        Self::Int32Array,
+        Self::Uint32Array,
+        Self::BigInt64Array,
+        Self::BigUint64Array,
+        Self::Float32Array,
+        Self::Float64Array,
+    ];
+
+    /// The name of the constructor.
+    fn name(self) -> &'static str {
+        match self {
+            Self::ArrayBuffer => "ArrayBuffer",
+            Self::Map => "Map",
+            Self::Set => "Set",
+            Self::Date => "Date",
+            Self::Uint8ClampedArray => "Uint8ClampedArray",
+            Self::Int8Array => "Int8Array",
+            Self::Uint8Array => "Uint8Array",
+            Self::Int16Array => "Int16Array",
+            Self::Uint16Array => "Uint16Array",
+            Self::Int32Array => "Int32Array",
+            Self::Uint32Array => "Uint32Array",
+            Self::BigInt64Array => "BigInt64Array",
+            Self::BigUint64Array => "BigUint64Array",
+            Self::Float32Array => "Float32Array",
+            Self::Float64Array => "Float64Array",
+        }
+    }
+}
+
+/// Upper bound for the class ids that are probed.
+const MAX_CLASS_ID: q::JSClassID = 1024;
+
+fn undefined() -> q::JSValue {
+    q::JSValue {
+        u: q::JSValueUnion { int32: 0 },
+        tag: TAG_UNDEFINED,
+    }
+}
+
+/// Builtins of a context, captured when the context is created.
+pub(crate) struct Intrinsics {
+    object: Cell<q::JSValue>,
+    /// The class ids of the builtin classes, if the engine has them.
+    classes: RefCell<Vec<(BuiltinClass, q::JSClassID)>>,
+}
+
+impl Intrinsics {
+    pub fn new() -> Self {
+        Self {
+            object: Cell::new(undefined()),
+            classes: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Capture the builtins of a fresh context.
+    ///
+    /// Must be called before any user code runs in the context.
+    pub fn capture(&self, context: *mut q::JSContext) -> Result<(), ValueError> {
+        self.clear(context);
+        let code = make_cstring(INTRINSICS_SCRIPT)?;
+        let filename = make_cstring("<intrinsics>")?;
+        let value = unsafe {
+            q::JS_Eval(
+                context,
+                code.as_ptr(),
+                INTRINSICS_SCRIPT.len() as _,
+                filename.as_ptr(),
+                q::JS_EVAL_TYPE_GLOBAL as i32,
+            )
+        };
+        if value.tag == TAG_EXCEPTION {
+            unsafe { q::JS_FreeValue(context, q::JS_GetException(context)) };
+        }
+        if value.tag != TAG_OBJECT {
+            unsafe { q::JS_FreeValue(context, value) };
+            return Err(ValueError::Internal(
+                "Could not capture the builtins of the context".into(),
+            ));
+        }
+        self.object.set(value);
+        self.capture_classes(context);
+        Ok(())
+    }
+
+    /// Find the class ids of the builtin classes.
+    ///
+    /// The ids are not exported and depend on the engine version and build
+    /// options, so an instance of every class is built with the captured
+    /// constructors and probed. The instances have non-null opaque data,
+    /// which `JS_GetOpaque` requires to recognize the class.
+    fn capture_classes(&self, context: *mut q::JSContext) {
+        let mut classes = Vec::with_capacity(BuiltinClass::ALL.len());
+        for class in BuiltinClass::ALL.iter() {
+            // Classes missing from the engine are skipped.
+            let constructor = match self.get(context, class.name()) {
+                Ok(constructor) => constructor,
+                Err(_) => continue,
+            };
+            // A length or time value of 1, collections are built empty.
+            let mut args = [q::JSValue {
+                u: q::JSValueUnion { int32: 1 },
+                tag: TAG_INT,
+            }];
+            let argc = match class {
+                BuiltinClass::Map | BuiltinClass::Set => 0,
+                _ => 1,
+            };
+            let sample =
+                unsafe { q::JS_CallConstructor(context, constructor, argc, args.as_mut_ptr()) };
+            unsafe { q::JS_FreeValue(context, constructor) };
+            if sample.tag == TAG_EXCEPTION {
+                unsafe { q::JS_FreeValue(context, q::JS_GetException(context)) };
+                continue;
+            }
+            let id =
+                (1..MAX_CLASS_ID).find(|&id| unsafe { !q::JS_GetOpaque(sample, id).is_null() });
+            unsafe { q::JS_FreeValue(context, sample) };
+            if let Some(id) = id {
+                classes.push((*class, id));
+            }
+        }
+        *self.classes.borrow_mut() = classes;
+    }
+
+    /// Get the builtin class of an object.
+    ///
+    /// Returns `None` for other objects, and for dates whose time value is
+    /// zero, which have null opaque data.
+    pub fn class_of(&self, value: &q::JSValue) -> Option<BuiltinClass> {
+        self.classes
+            .borrow()
+            .iter()
+            .find(|(_, id)| unsafe { !q::JS_GetOpaque(*value, *id).is_null() })
+            .map(|(class, _)| *class)
+    }
+
+    /// Get a builtin by name.
+    ///
+    /// The returned value must be freed.
+    pub fn get(&self, context: *mut q::JSContext, name: &str) -> Result<q::JSValue, ValueError> {
+        let cname = make_cstring(name)?;
+        let value = unsafe { q::JS_GetPropertyStr(context, self.object.get(), cname.as_ptr()) };
+        if value.tag != TAG_OBJECT {
+            unsafe { q::JS_FreeValue(context, value) };
+            return Err(ValueError::Internal(format!(
+                "Could not get builtin '{}'",
+                name
+            )));
+        }
+        Ok(value)
+    }
+
+    /// Get the object holding all builtins.
+    ///
+    /// The returned value must be freed.
+    pub fn object(&self, context: *mut q::JSContext) -> q::JSValue {
+        let object = self.object.get();
+        unsafe { q::JS_DupValue(context, object) };
+        object
+    }
+
+    /// Free the captured builtins.
+    ///
+    /// Must be called before the context is freed.
+    pub fn clear(&self, context: *mut q::JSContext) {
+        let value = self.object.replace(undefined());
+        unsafe { q::JS_FreeValue(context, value) };
+    }
+}
@@ -1,30 +1,55 @@
+mod allocator;
+mod class;
 mod compile;
 mod convert;
 mod droppable_value;
+mod function;
+mod host;
+mod intrinsics;
+mod state;
+#[allow(dead_code)]
 mod value;
 
 use std::{
     ffi::CString,
-    os::raw::{c_int, c_void},
-    sync::Mutex,
+    os::raw::{c_char, c_int, c_void},
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc, Mutex, Once,
+    },
 };
 
+use indexmap::IndexMap;
 use libquickjs_sys as q;
 
 use crate::{
-    callback::{Arguments, Callback},
-    console::ConsoleBackend,
-    ContextError, ExecutionError, JsValue, ValueError,
+    allocator::{AllocationStats, Allocator},
+    callback::{CallContext, Callback, Rest},
+    console::{Console, ConsoleBackend},
+    ContextError, ExecutionError, InspectOptions, JsError, JsValue, ValueError,
 };
 
 use value::{JsFunction, OwnedJsObject};
 
+pub(crate) use class::HostClass;
+pub(crate) use convert::ConversionLimits;
+pub(crate) use host::HostObject;
+pub(crate) use state::ContextState;
+
+use state::UserData;
+#[allow(unused_imports)]
 pub use value::{JsCompiledFunction, OwnedJsValue};
 
 // JS_TAG_* constants from quickjs.
 // For some reason bindgen does not pick them up.
+#[cfg(feature = "bignum")]
+const TAG_BIG_DECIMAL: i64 = -11;
 #[cfg(feature = "bigint")]
 const TAG_BIG_INT: i64 = -10;
+#[cfg(feature = "bignum")]
+const TAG_BIG_FLOAT: i64 = -9;
+const TAG_SYMBOL: i64 = -8;
 const TAG_STRING: i64 = -7;
 const TAG_FUNCTION_BYTECODE: i64 = -2;
 const TAG_OBJECT: i64 = -1;
@@ -40,51 +65,78 @@ fn make_cstring(value: impl Into<Vec<u8>>) -> Result<CString, ValueError> {
     CString::new(value).map_err(ValueError::StringWithZeroBytes)
 }
 
-type WrappedCallback = dyn Fn(c_int, *mut q::JSValue) -> q::JSValue;
-
-/// Taken from: https://s3.amazonaws.com/temp.michaelfbryan.com/callbacks/index.html
+/// Create a context with the default intrinsics.
 ///
-/// Create a C wrapper function for a Rust closure to enable using it as a
-/// callback function in the Quickjs runtime.
+/// With the `bignum` feature, `BigFloat`, `BigDecimal` and operator
+/// overloading with `Operators` are added as well.
+unsafe fn new_context(runtime: *mut q::JSRuntime) -> *mut q::JSContext {
+    let context = q::JS_NewContext(runtime);
+    #[cfg(feature = "bignum")]
+    if !context.is_null() {
+        q::JS_AddIntrinsicBigFloat(context);
+        q::JS_AddIntrinsicBigDecimal(context);
+        // Must be added after the other numeric types.
+        q::JS_AddIntrinsicOperators(context);
+    }
+    context
+}
+
+type WrappedCallback = dyn Fn(*mut q::JSContext, c_int, *mut q::JSValue) -> q::JSValue;
+
+/// Get the id of the class of callback data objects.
 ///
-/// Both the boxed closure and the boxed data are returned and must be stored
-/// by the caller to guarantee they stay alive.
-unsafe fn build_closure_trampoline<F>(
-    closure: F,
-) -> ((Box<WrappedCallback>, Box<q::JSValue>), q::JSCFunctionData)
-where
-    F: Fn(c_int, *mut q::JSValue) -> q::JSValue + 'static,
-{
-    unsafe extern "C" fn trampoline<F>(
-        _ctx: *mut q::JSContext,
-        _this: q::JSValue,
-        argc: c_int,
-        argv: *mut q::JSValue,
-        _magic: c_int,
-        data: *mut q::JSValue,
-    ) -> q::JSValue
-    where
-        F: Fn(c_int, *mut q::JSValue) -> q::JSValue,
-    {
-        let closure_ptr = (*data).u.ptr;
-        let closure: &mut F = &mut *(closure_ptr as *mut F);
-        (*closure)(argc, argv)
-    }
-
-    let boxed_f = Box::new(closure);
-
-    let data = Box::new(q::JSValue {
-        u: q::JSValueUnion {
-            ptr: (&*boxed_f) as *const F as *mut c_void,
-        },
-        tag: TAG_NULL,
+/// Every callback function keeps a data object of this class, whose opaque
+/// pointer owns a reference to the Rust closure. The closure is freed by the
+/// class finalizer when the function is collected.
+fn callback_class_id() -> q::JSClassID {
+    static CLASS_ID: AtomicU32 = AtomicU32::new(0);
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        let mut id = 0;
+        unsafe { q::JS_NewClassID(&mut id) };
+        CLASS_ID.store(id, Ordering::SeqCst);
     });
+    CLASS_ID.load(Ordering::SeqCst)
+}
+
+/// Register the class of callback data objects in a runtime.
+unsafe fn register_callback_class(runtime: *mut q::JSRuntime) -> bool {
+    unsafe extern "C" fn finalizer(_rt: *mut q::JSRuntime, value: q::JSValue) {
+        let closure = q::JS_GetOpaque(value, callback_class_id()) as *mut Rc<WrappedCallback>;
+        if !closure.is_null() {
+            drop(Box::from_raw(closure));
+        }
+    }
+
+    let class_def = q::JSClassDef {
+        class_name: b"RustCallback\0".as_ptr() as *const c_char,
+        finalizer: Some(finalizer),
+        gc_mark: None,
+        call: None,
+        exotic: std::ptr::null_mut(),
+    };
+    q::JS_NewClass(runtime, callback_class_id(), &class_def) == 0
+}
 
-    ((boxed_f, data), Some(trampoline::<F>))
+/// C function for calling the Rust closure of a callback data object.
+unsafe extern "C" fn callback_trampoline(
+    ctx: *mut q::JSContext,
+    _this: q::JSValue,
+    argc: c_int,
+    argv: *mut q::JSValue,
+    _magic: c_int,
+    data: *mut q::JSValue,
+) -> q::JSValue {
+    // The function keeps the data object, and thus the closure, alive while
+    // it is called.
+    let closure = q::JS_GetOpaque(*data, callback_class_id()) as *const Rc<WrappedCallback>;
+    (**closure)(ctx, argc, argv)
 }
 
 /// OwnedValueRef wraps a Javascript value from the quickjs runtime.
 /// It prevents leaks by ensuring that the inner value is deallocated on drop.
+#[allow(dead_code)]
 pub struct OwnedValueRef<'a> {
     context: &'a ContextWrapper,
     value: q::JSValue,
@@ -121,6 +173,7 @@ impl<'a> std::fmt::Debug for OwnedValueRef<'a> {
     }
 }
 
+#[allow(dead_code)]
 impl<'a> OwnedValueRef<'a> {
     pub fn new(context: &'a ContextWrapper, value: q::JSValue) -> Self {
         Self { context, value }
@@ -221,10 +274,12 @@ impl<'a> OwnedValueRef<'a> {
 
 /// Wraps an object from the quickjs runtime.
 /// Provides convenience property accessors.
+#[allow(dead_code)]
 pub struct OwnedObjectRef<'a> {
     value: OwnedValueRef<'a>,
 }
 
+#[allow(dead_code)]
 impl<'a> OwnedObjectRef<'a> {
     pub fn new(value: OwnedValueRef<'a>) -> Result<Self, ValueError> {
         if value.value.tag != TAG_OBJECT {
@@ -310,6 +365,237 @@ impl<'a> OwnedObjectRef<'a> {
     }
 }
 
+/// A Rust closure that backs JS functions.
+#[derive(Clone)]
+struct CallbackEntry {
+    closure: Rc<WrappedCallback>,
+    argcount: i32,
+}
+
+impl CallbackEntry {
+    /// Create a new JS function object backed by the closure.
+    ///
+    /// The function owns a reference to the closure, which is released when
+    /// the function is collected.
+    fn new_function(&self, context: *mut q::JSContext) -> q::JSValue {
+        unsafe {
+            let mut data = q::JS_NewObjectClass(context, callback_class_id() as i32);
+            if data.tag == TAG_EXCEPTION {
+                return data;
+            }
+            let closure = Box::new(self.closure.clone());
+            q::JS_SetOpaque(data, Box::into_raw(closure) as *mut c_void);
+
+            let function = q::JS_NewCFunctionData(
+                context,
+                Some(callback_trampoline),
+                self.argcount,
+                0,
+                1,
+                &mut data,
+            );
+            q::JS_FreeValue(context, data);
+            function
+        }
+    }
+}
+
+/// Javascript glue that installs a console forwarding to the native `write`
+/// function.
+///
+/// The native functions are only captured by the closures, so scripts can not
+/// reach or replace them.
+/// Format specifiers and assertions are handled here, while counters, timers
+/// and groups are kept by the native side. Objects are formatted by the
+/// native `inspect` function, like the arguments of a message.
+/// Logging never throws, even for values whose conversion to a string
+/// throws. Only captured builtins are called, and arguments are collected in
+/// array-likes without a prototype, so changes to the globals and prototypes
+/// by scripts do not affect the output.
+/// See the console standard: https://console.spec.whatwg.org
+const CONSOLE_SCRIPT: &str = r#"
+    (function ({ String, parseInt, parseFloat, isArray, apply, Error }, write, inspect) {
+        const string = value => {
+            try {
+                return String(value);
+            } catch (e) {
+                return inspect(value);
+            }
+        };
+
+        // Append the values from index `start` to the array-like `list`.
+        const append = (list, values, start) => {
+            for (let i = start; i < values.length; i++) {
+                list[list.length] = values[i];
+                list.length += 1;
+            }
+            return list;
+        };
+
+        const types = { __proto__: null, s: true, d: true, i: true, f: true, o: true, O: true, c: true };
+        const specifier = (type, value) => {
+            switch (type) {
+                case "s":
+                    return string(value);
+                case "d":
+                case "i":
+                    return typeof value === "symbol" ? "NaN" : String(parseInt(string(value), 10));
+                case "f":
+                    return typeof value === "symbol" ? "NaN" : String(parseFloat(string(value)));
+                case "o":
+                case "O":
+                    return inspect(value);
+                case "c":
+                    return "";
+            }
+        };
+
+        // Append the arguments of a console call to `list`, with the format
+        // specifiers of a leading string replaced.
+        const format = (list, args) => {
+            if (args.length < 2 || typeof args[0] !== "string") {
+                return append(list, args, 0);
+            }
+            const first = args[0];
+            let index = 1;
+            let text = "";
+            for (let i = 0; i < first.length; i++) {
+                const type = i + 1 < first.length ? first[i + 1] : "";
+                if (first[i] !== "%" || type === "") {
+                    text += first[i];
+                } else if (type === "%") {
+                    text += "%";
+                    i++;
+                } else if (!types[type]) {
+                    text += "%";
+                } else if (index >= args.length) {
+                    text += "%" + type;
+                    i++;
+                } else {
+                    text += specifier(type, args[index++]);
+                    i++;
+                }
+            }
+            list[list.length] = text;
+            list.length += 1;
+            return append(list, args, index);
+        };
+
+        // The stack trace line of the caller of a console function, which
+        // calls this function directly.
+        const caller = () => {
+            try {
+                const stack = new Error().stack;
+                if (typeof stack !== "string") {
+                    return undefined;
+                }
+                let line = 0;
+                let frame = "";
+                for (let i = 0; i < stack.length && line <= 2; i++) {
+                    if (stack[i] === "\n") {
+                        line++;
+                    } else if (line === 2) {
+                        frame += stack[i];
+                    }
+                }
+                return line >= 2 ? frame : undefined;
+            } catch (e) {
+                return undefined;
+            }
+        };
+
+        // Start the arguments of a call of `write`.
+        const message = (method, frame) => ({ __proto__: null, length: 2, 0: method, 1: frame });
+        const label = value => value === undefined ? "default" : string(value);
+        const logger = method => (...args) => {
+            apply(write, undefined, format(message(method, caller()), args));
+        };
+
+        globalThis.console = {
+            trace: logger("trace"),
+            debug: logger("debug"),
+            log: logger("log"),
+            info: logger("info"),
+            warn: logger("warn"),
+            error: logger("error"),
+            dirxml: logger("dirxml"),
+            assert: (condition, ...args) => {
+                if (condition) {
+                    return;
+                }
+                const list = message("assert", caller());
+                let values;
+                if (args.length > 0 && typeof args[0] === "string") {
+                    values = append({ __proto__: null, length: 0 }, args, 0);
+                    values[0] = `Assertion failed: ${args[0]}`;
+                } else {
+                    values = append({ __proto__: null, length: 1, 0: "Assertion failed" }, args, 0);
+                }
+                apply(write, undefined, format(list, values));
+            },
+            count: value => {
+                write("count", caller(), label(value));
+            },
+            countReset: value => {
+                write("countReset", caller(), label(value));
+            },
+            time: value => {
+                write("time", caller(), label(value));
+            },
+            timeLog: (value, ...args) => {
+                const list = message("timeLog", caller());
+                list[2] = label(value);
+                list.length = 3;
+                apply(write, undefined, append(list, args, 0));
+            },
+            timeEnd: value => {
+                write("timeEnd", caller(), label(value));
+            },
+            group: logger("group"),
+            groupCollapsed: logger("groupCollapsed"),
+            groupEnd: () => {
+                write("groupEnd", caller());
+            },
+            table: (data, columns) => {
+                write("table", caller(), data, isArray(columns) ? columns : null);
+            },
+            dir: item => {
+                write("dir", caller(), item);
+            },
+            clear: () => {
+                write("clear", caller());
+            },
+        };
+    })
+"#;
+
+/// Javascript glue that builds the function tracking the state of a promise,
+/// from the builtins captured when the context was created.
+///
+/// The function returns an object whose `done` property becomes true once
+/// the promise is settled, with `ok` and `value` describing the outcome.
+/// Other thenables are adopted by a promise first.
+const PROMISE_STATE_SCRIPT: &str = r#"
+    (function ({ apply, Promise, promiseThen, promiseResolve }) {
+        return value => {
+            const state = { __proto__: null, done: false, ok: false, value: undefined };
+            const settle = ok => result => {
+                state.done = true;
+                state.ok = ok;
+                state.value = result;
+            };
+            const callbacks = { __proto__: null, length: 2, 0: settle(true), 1: settle(false) };
+            try {
+                apply(promiseThen, value, callbacks);
+            } catch (e) {
+                // Not a promise.
+                apply(promiseThen, apply(promiseResolve, Promise, [value]), callbacks);
+            }
+            return state;
+        };
+    })
+"#;
+
 /*
 type ModuleInit = dyn Fn(*mut q::JSContext, *mut q::JSModuleDef);
 
@@ -329,21 +615,52 @@ unsafe extern "C" fn native_module_init(
 }
 */
 
+/// The callbacks, host objects and user data of a context, which can be
+/// restored when the context is reset.
+pub(crate) struct Snapshot {
+    callbacks: IndexMap<String, CallbackEntry>,
+    host_objects: Vec<HostObject>,
+    user_data: UserData,
+}
+
 /// Wraps a quickjs context.
 ///
 /// Cleanup of the context happens in drop.
 pub struct ContextWrapper {
     runtime: *mut q::JSRuntime,
     pub(crate) context: *mut q::JSContext,
-    /// Stores callback closures and quickjs data pointers.
-    /// This array is write-only and only exists to ensure the lifetime of
-    /// the closure.
+    /// Named callbacks added with `add_callback` by global name, installed
+    /// again on reset. Adding a callback with the same name replaces the
+    /// earlier one.
+    /// Anonymous callbacks are owned by their function objects.
     // A Mutex is used over a RefCell because it needs to be unwind-safe.
-    callbacks: Mutex<Vec<(Box<WrappedCallback>, Box<q::JSValue>)>>,
+    callbacks: Mutex<IndexMap<String, CallbackEntry>>,
+    /// The console installed with `set_console` and its native write and
+    /// inspect functions, installed again on reset.
+    console: Option<(Rc<Console>, CallbackEntry, CallbackEntry)>,
+    /// Bytecode of the preludes, evaluated again on reset.
+    preludes: Vec<Arc<[u8]>>,
+    /// Flag checked by the interrupt handler of the runtime, if one was set.
+    /// Must outlive the runtime, like the allocator.
+    interrupt: Option<Arc<AtomicBool>>,
+    /// Allocator used by the runtime, which also records failed allocations.
+    /// Must outlive the runtime, which is guaranteed since fields are dropped
+    /// after `Drop::drop` has freed the runtime.
+    allocator: Box<allocator::RuntimeAllocator>,
+    /// Limits for converting runtime values to `JsValue`s.
+    conversion_limits: ConversionLimits,
+    /// Host objects added with `add_host_object`, installed again on reset.
+    /// Each property is kept once per path, by the latest object defining it.
+    host_objects: Mutex<Vec<HostObject>>,
+    /// State reachable from the raw context, like `JsFunction` handles and
+    /// user data.
+    /// Boxed so the context opaque pointer stays valid when the wrapper moves.
+    pub(crate) state: Box<ContextState>,
 }
 
 impl Drop for ContextWrapper {
     fn drop(&mut self) {
+        self.state.clear(self.context);
         unsafe {
             q::JS_FreeContext(self.context);
             q::JS_FreeRuntime(self.runtime);
@@ -354,10 +671,29 @@ impl Drop for ContextWrapper {
 impl ContextWrapper {
     /// Initialize a wrapper by creating a JSRuntime and JSContext.
     pub fn new(memory_limit: Option<usize>) -> Result<Self, ContextError> {
-        let runtime = unsafe { q::JS_NewRuntime() };
+        Self::with_allocator(memory_limit, None)
+    }
+
+    /// Initialize a wrapper whose runtime allocates memory with the given
+    /// allocator, or the `SystemAllocator` if none is given.
+    pub fn with_allocator(
+        memory_limit: Option<usize>,
+        allocator: Option<Arc<dyn Allocator>>,
+    ) -> Result<Self, ContextError> {
+        let allocator = allocator::RuntimeAllocator::new(
+            allocator.unwrap_or_else(|| Arc::new(crate::allocator::SystemAllocator)),
+        );
+        let runtime = unsafe {
+            let functions = allocator::malloc_functions();
+            q::JS_NewRuntime2(&functions, allocator.opaque())
+        };
         if runtime.is_null() {
             return Err(ContextError::RuntimeCreationFailed);
         }
+        if !unsafe { register_callback_class(runtime) } {
+            unsafe { q::JS_FreeRuntime(runtime) };
+            return Err(ContextError::RuntimeCreationFailed);
+        }
 
         // Configure memory limit if specified.
         if let Some(limit) = memory_limit {
@@ -366,7 +702,7 @@ impl ContextWrapper {
             }
         }
 
-        let context = unsafe { q::JS_NewContext(runtime) };
+        let context = unsafe { new_context(runtime) };
         if context.is_null() {
             unsafe {
                 q::JS_FreeRuntime(runtime);
@@ -374,87 +710,192 @@ impl ContextWrapper {
             return Err(ContextError::ContextCreationFailed);
         }
 
-        // Initialize the promise resolver helper code.
-        // This code is needed by Self::resolve_value
         let wrapper = Self {
             runtime,
             context,
-            callbacks: Mutex::new(Vec::new()),
+            callbacks: Mutex::new(IndexMap::new()),
+            console: None,
+            preludes: Vec::new(),
+            interrupt: None,
+            allocator,
+            conversion_limits: ConversionLimits::default(),
+            host_objects: Mutex::new(Vec::new()),
+            state: Box::new(ContextState::new()),
         };
+        unsafe { wrapper.state.install(context) }?;
 
         Ok(wrapper)
     }
 
+    /// Set the limits for converting runtime values to `JsValue`s.
+    ///
+    /// Must be called before adding callbacks, which capture the limits.
+    pub fn set_conversion_limits(&mut self, limits: ConversionLimits) {
+        self.conversion_limits = limits;
+    }
+
     // See console standard: https://console.spec.whatwg.org
-    pub fn set_console(&self, backend: Box<dyn ConsoleBackend>) -> Result<(), ExecutionError> {
-        use crate::console::Level;
-
-        self.add_callback("__console_write", move |args: Arguments| {
-            let mut args = args.into_vec();
-
-            if args.len() > 1 {
-                let level_raw = args.remove(0);
-
-                let level_opt = level_raw.as_str().and_then(|v| match v {
-                    "trace" => Some(Level::Trace),
-                    "debug" => Some(Level::Debug),
-                    "log" => Some(Level::Log),
-                    "info" => Some(Level::Info),
-                    "warn" => Some(Level::Warn),
-                    "error" => Some(Level::Error),
-                    _ => None,
-                });
-
-                if let Some(level) = level_opt {
-                    backend.log(level, args);
-                }
-            }
-        })?;
-
-        self.eval(
-            r#"
-            globalThis.console = {
-                trace: (...args) => {
-                    globalThis.__console_write("trace", ...args);
-                },
-                debug: (...args) => {
-                    globalThis.__console_write("debug", ...args);
-                },
-                log: (...args) => {
-                    globalThis.__console_write("log", ...args);
-                },
-                info: (...args) => {
-                    globalThis.__console_write("info", ...args);
-                },
-                warn: (...args) => {
-                    globalThis.__console_write("warn", ...args);
-                },
-                error: (...args) => {
-                    globalThis.__console_write("error", ...args);
-                },
-            };
-        "#,
-        )?;
+    pub fn set_console(&mut self, backend: Arc<dyn ConsoleBackend>) -> Result<(), ExecutionError> {
+        let console = Rc::new(Console::new(backend));
+        // Logging must not fail, so the arguments are converted lossily.
+        let limits = ConversionLimits {
+            lossy: true,
+            ..self.conversion_limits
+        };
+
+        let c = console.clone();
+        let write = self.callback_entry_with_limits(
+            move |method: String, frame: Option<String>, args: Rest<JsValue>| {
+                c.write(&method, frame, args.into_vec());
+            },
+            limits,
+        );
+        let inspect = self.callback_entry_with_limits(
+            |value: JsValue| value.inspect(&InspectOptions::default()),
+            limits,
+        );
+
+        self.install_console(&write, &inspect)?;
+        self.console = Some((console, write, inspect));
 
         Ok(())
     }
 
+    /// Install a console that forwards to the given write and inspect
+    /// functions.
+    fn install_console(
+        &self,
+        write: &CallbackEntry,
+        inspect: &CallbackEntry,
+    ) -> Result<(), ExecutionError> {
+        let write = OwnedJsValue::new(self, write.new_function(self.context));
+        let inspect = OwnedJsValue::new(self, inspect.new_function(self.context));
+        let install = self.eval(CONSOLE_SCRIPT)?.try_into_function()?;
+        install.call(vec![self.intrinsics(), write, inspect])?;
+        Ok(())
+    }
+
     /// Reset the wrapper by creating a new context.
+    ///
+    /// Callbacks added with `add_callback` and the console are installed
+    /// again, so the new context is configured like the old one.
     pub fn reset(self) -> Result<Self, ContextError> {
-        unsafe {
-            q::JS_FreeContext(self.context);
-        };
-        self.callbacks.lock().unwrap().clear();
-        let context = unsafe { q::JS_NewContext(self.runtime) };
+        // The new context is created first, so the wrapper still owns a valid
+        // context that is freed on drop if the creation fails.
+        let context = unsafe { new_context(self.runtime) };
         if context.is_null() {
             return Err(ContextError::ContextCreationFailed);
         }
 
+        // Handles to functions of the old context become invalid.
+        self.state.clear(self.context);
+        unsafe {
+            q::JS_FreeContext(self.context);
+            // Collect cycles left over by the old context.
+            q::JS_RunGC(self.runtime);
+        };
+
         let mut s = self;
         s.context = context;
+        unsafe { s.state.install(context) }?;
+        s.reinstall().map_err(ContextError::Execution)?;
         Ok(s)
     }
 
+    /// Take a snapshot of the callbacks, host objects and user data.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            callbacks: self.callbacks.lock().unwrap().clone(),
+            host_objects: self.host_objects.lock().unwrap().clone(),
+            user_data: self.state.all_user_data(),
+        }
+    }
+
+    /// Reset the wrapper like `reset`, with the callbacks, host objects and
+    /// user data of a snapshot instead of the current ones.
+    pub fn reset_to(self, snapshot: &Snapshot) -> Result<Self, ContextError> {
+        *self.callbacks.lock().unwrap() = snapshot.callbacks.clone();
+        *self.host_objects.lock().unwrap() = snapshot.host_objects.clone();
+        self.state.set_all_user_data(snapshot.user_data.clone());
+        self.reset()
+    }
+
+    /// Install the named callbacks, host objects and the console in a fresh
+    /// context.
+    fn reinstall(&self) -> Result<(), ExecutionError> {
+        let global = self.global()?;
+        for (name, cb) in self.callbacks.lock().unwrap().iter() {
+            let func = OwnedJsValue::new(self, cb.new_function(self.context));
+            global.set_property(name, func)?;
+        }
+        for object in self.host_objects.lock().unwrap().iter() {
+            self.install_host_object(object)?;
+        }
+        if let Some((console, write, inspect)) = &self.console {
+            console.reset();
+            self.install_console(write, inspect)?;
+        }
+        for bytecode in &self.preludes {
+            self.eval_bytecode(bytecode)?;
+        }
+        Ok(())
+    }
+
+    /// Compile a script to bytecode.
+    pub fn compile_bytecode(&self, code: &str, filename: &str) -> Result<Vec<u8>, ExecutionError> {
+        let func = compile::compile(self, code, filename)?.try_into_compiled_function()?;
+        Ok(compile::to_bytecode(self, &func))
+    }
+
+    /// Evaluate bytecode created by `compile_bytecode`.
+    fn eval_bytecode(&self, bytecode: &[u8]) -> Result<(), ExecutionError> {
+        let func = compile::from_bytecode(self, bytecode)?.try_into_compiled_function()?;
+        compile::run_compiled_function(&func)?;
+        Ok(())
+    }
+
+    /// Evaluate a prelude and remember it, so it is evaluated again on reset.
+    pub fn add_prelude(&mut self, bytecode: Arc<[u8]>) -> Result<(), ExecutionError> {
+        self.eval_bytecode(&bytecode)?;
+        self.preludes.push(bytecode);
+        Ok(())
+    }
+
+    /// Interrupt running scripts once the flag is set.
+    ///
+    /// Interrupted scripts throw an uncatchable `InternalError`.
+    pub fn set_interrupt_flag(&mut self, flag: Arc<AtomicBool>) {
+        unsafe extern "C" fn handler(_rt: *mut q::JSRuntime, opaque: *mut c_void) -> c_int {
+            (*(opaque as *const AtomicBool)).load(Ordering::Relaxed) as c_int
+        }
+
+        unsafe {
+            q::JS_SetInterruptHandler(
+                self.runtime,
+                Some(handler),
+                Arc::as_ptr(&flag) as *mut c_void,
+            )
+        };
+        self.interrupt = Some(flag);
+    }
+
+    fn compute_memory_usage(&self) -> q::JSMemoryUsage {
+        let mut usage = std::mem::MaybeUninit::<q::JSMemoryUsage>::uninit();
+        unsafe {
+            q::JS_ComputeMemoryUsage(self.runtime, usage.as_mut_ptr());
+            usage.assume_init()
+        }
+    }
+
+    /// Get the number of bytes currently allocated by the runtime.
+    pub fn memory_usage(&self) -> usize {
+        self.compute_memory_usage().malloc_size as usize
+    }
+
+    pub fn allocation_stats(&self) -> AllocationStats {
+        self.allocator.stats(&self.compute_memory_usage())
+    }
+
     pub fn serialize_value(&self, value: JsValue) -> Result<OwnedJsValue<'_>, ExecutionError> {
         let serialized = convert::serialize_value(self.context, value)?;
         Ok(OwnedJsValue::new(self, serialized))
@@ -462,7 +903,25 @@ impl ContextWrapper {
 
     // Deserialize a quickjs runtime value into a Rust value.
     pub(crate) fn to_value(&self, value: &q::JSValue) -> Result<JsValue, ValueError> {
-        convert::deserialize_value(self.context, value)
+        convert::deserialize_value(self.context, value, self.conversion_limits)
+    }
+
+    /// Get the Javascript function referenced by a `JsFunction` handle.
+    pub(crate) fn function_value(
+        &self,
+        function: &crate::JsFunction,
+    ) -> Result<OwnedJsValue<'_>, ExecutionError> {
+        self.state.functions.free_released(self.context);
+        let value = self
+            .state
+            .functions
+            .get(self.context, function)
+            .ok_or_else(|| {
+                ExecutionError::Internal(
+                    "Function belongs to a different context or was released".into(),
+                )
+            })?;
+        Ok(OwnedJsValue::new(self, value))
     }
 
     /// Get the global object.
@@ -473,15 +932,33 @@ impl ContextWrapper {
         Ok(global)
     }
 
+    /// Get the builtins captured when the context was created.
+    ///
+    /// Javascript glue takes them as an argument instead of using globals,
+    /// which scripts can replace.
+    pub(crate) fn intrinsics(&self) -> OwnedJsValue<'_> {
+        OwnedJsValue::new(self, self.state.intrinsics.object(self.context))
+    }
+
     /// Get the last exception from the runtime, and if present, convert it to a ExceptionError.
+    ///
+    /// The exception is only reported as `ExecutionError::OutOfMemory` if an
+    /// allocation of the runtime actually failed, so scripts can not fake it
+    /// by throwing an error with the same message.
     pub(crate) fn get_exception(&self) -> Option<ExecutionError> {
         let value = unsafe {
             let raw = q::JS_GetException(self.context);
             OwnedJsValue::new(self, raw)
         };
+        let allocation_failed = self.allocator.take_failure();
 
         if value.is_null() {
-            None
+            // QuickJS throws null if it can not even allocate the error.
+            if allocation_failed {
+                Some(ExecutionError::OutOfMemory)
+            } else {
+                None
+            }
         } else if value.is_exception() {
             Some(ExecutionError::Internal(
                 "Could get exception from runtime".into(),
@@ -489,7 +966,7 @@ impl ContextWrapper {
         } else {
             match value.js_to_string() {
                 Ok(strval) => {
-                    if strval.contains("out of memory") {
+                    if allocation_failed && strval.contains("out of memory") {
                         Some(ExecutionError::OutOfMemory)
                     } else {
                         Some(ExecutionError::Exception(JsValue::String(strval)))
@@ -501,6 +978,7 @@ impl ContextWrapper {
     }
 
     /// Returns `Result::Err` when an error ocurred.
+    #[allow(dead_code)]
     pub(crate) fn ensure_no_excpetion(&self) -> Result<(), ExecutionError> {
         if let Some(e) = self.get_exception() {
             Err(e)
@@ -509,6 +987,21 @@ impl ContextWrapper {
         }
     }
 
+    /// Get the function tracking the state of promises, which is built on
+    /// first use and kept with the captured builtins.
+    fn promise_tracker(&self) -> Result<JsFunction<'_>, ExecutionError> {
+        let intrinsics = self.intrinsics().try_into_object()?;
+        if let Some(track) = intrinsics.property("trackPromise")? {
+            return Ok(track.try_into_function()?);
+        }
+        let build = self.eval(PROMISE_STATE_SCRIPT)?.try_into_function()?;
+        let track = build.call(vec![self.intrinsics()])?;
+        intrinsics.set_property("trackPromise", track)?;
+        Ok(intrinsics
+            .property_require("trackPromise")?
+            .try_into_function()?)
+    }
+
     /// If the given value is a promise, run the event loop until it is
     /// resolved, and return the final value.
     fn resolve_value<'a>(
@@ -523,44 +1016,24 @@ impl ContextWrapper {
         } else if value.is_object() {
             let obj = value.try_into_object()?;
             if obj.is_promise()? {
-                self.eval(
-                    r#"
-                    // Values:
-                    //   - undefined: promise not finished
-                    //   - false: error ocurred, __promiseError is set.
-                    //   - true: finished, __promiseSuccess is set.
-                    var __promiseResult = 0;
-                    var __promiseValue = 0;
-
-                    var __resolvePromise = function(p) {
-                        p
-                            .then(value => {
-                                __promiseResult = true;
-                                __promiseValue = value;
-                            })
-                            .catch(e => {
-                                __promiseResult = false;
-                                __promiseValue = e;
-                            });
-                    }
-                "#,
-                )?;
-
-                let global = self.global()?;
-                let resolver = global
-                    .property_require("__resolvePromise")?
-                    .try_into_function()?;
-
-                // Call the resolver code that sets the result values once
-                // the promise resolves.
-                resolver.call(vec![obj.into_value()])?;
+                // Track the outcome of the promise in a private state object.
+                let track = self.promise_tracker()?;
+                let state = track.call(vec![obj.into_value()])?.try_into_object()?;
 
                 loop {
-                    let flag = unsafe {
-                        let wrapper_mut = self as *const Self as *mut Self;
-                        let ctx_mut = &mut (*wrapper_mut).context;
-                        q::JS_ExecutePendingJob(self.runtime, ctx_mut)
-                    };
+                    // A running script would be interrupted by the handler,
+                    // but this loop only runs scripts while jobs are pending.
+                    if let Some(interrupt) = &self.interrupt {
+                        if interrupt.load(Ordering::Relaxed) {
+                            return Err(ExecutionError::Internal(
+                                "Interrupted while waiting for a promise".into(),
+                            ));
+                        }
+                    }
+                    // Receives the context of the job that ran, or null if no
+                    // job was pending.
+                    let mut job_context = std::ptr::null_mut();
+                    let flag = unsafe { q::JS_ExecutePendingJob(self.runtime, &mut job_context) };
                     if flag < 0 {
                         let e = self.get_exception().unwrap_or_else(|| {
                             ExecutionError::Exception("Unknown exception".into())
@@ -569,10 +1042,9 @@ impl ContextWrapper {
                     }
 
                     // Check if promise is finished.
-                    let res_val = global.property_require("__promiseResult")?;
-                    if res_val.is_bool() {
-                        let ok = res_val.to_bool()?;
-                        let value = global.property_require("__promiseValue")?;
+                    if state.property_require("done")?.to_bool()? {
+                        let ok = state.property_require("ok")?.to_bool()?;
+                        let value = state.property_require("value")?;
 
                         if ok {
                             return self.resolve_value(value);
@@ -581,6 +1053,12 @@ impl ContextWrapper {
                             return Err(ExecutionError::Exception(JsValue::String(err_msg)));
                         }
                     }
+                    if flag == 0 {
+                        // No job is left that could settle the promise.
+                        return Err(ExecutionError::Internal(
+                            "Promise never settles: no pending jobs left".into(),
+                        ));
+                    }
                 }
             } else {
                 Ok(obj.into_value())
@@ -592,6 +1070,9 @@ impl ContextWrapper {
 
     /// Evaluate javascript code.
     pub fn eval<'a>(&'a self, code: &str) -> Result<OwnedJsValue<'a>, ExecutionError> {
+        self.state.functions.free_released(self.context);
+        // Forget allocations that failed in earlier, caught errors.
+        self.allocator.take_failure();
         let filename = "script.js";
         let filename_c = make_cstring(filename)?;
         let code_c = make_cstring(code)?;
@@ -654,84 +1135,121 @@ impl ContextWrapper {
         argc: c_int,
         argv: *mut q::JSValue,
         callback: &impl Callback<F>,
-    ) -> Result<q::JSValue, ExecutionError> {
+        limits: ConversionLimits,
+    ) -> Result<q::JSValue, JsError> {
         let result = std::panic::catch_unwind(|| {
-            let arg_slice = unsafe { std::slice::from_raw_parts(argv, argc as usize) };
+            // Getters are called with a null `argv`.
+            let arg_slice = if argv.is_null() {
+                &[]
+            } else {
+                unsafe { std::slice::from_raw_parts(argv, argc as usize) }
+            };
 
             let args = arg_slice
                 .iter()
-                .map(|raw| convert::deserialize_value(context, raw))
-                .collect::<Result<Vec<_>, _>>()?;
+                .map(|raw| convert::deserialize_value(context, raw, limits))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(ExecutionError::from)?;
 
-            match callback.call(args) {
+            let state = unsafe { ContextState::from_context(context) }
+                .ok_or_else(|| ExecutionError::Internal("Context has no state".into()))?;
+            match callback.call(&CallContext::new(state), args) {
                 Ok(Ok(result)) => {
-                    let serialized = convert::serialize_value(context, result)?;
+                    let serialized =
+                        convert::serialize_value(context, result).map_err(ExecutionError::from)?;
                     Ok(serialized)
                 }
-                // TODO: better error reporting.
-                Ok(Err(e)) => Err(ExecutionError::Exception(JsValue::String(e))),
-                Err(e) => Err(e.into()),
+                Ok(Err(e)) => Err(e),
+                Err(e) => Err(ExecutionError::from(e).into()),
             }
         });
 
         match result {
             Ok(r) => r,
-            Err(_e) => Err(ExecutionError::Internal("Callback panicked!".to_string())),
+            Err(panic) => {
+                let message = if let Some(message) = panic.downcast_ref::<&str>() {
+                    message.to_string()
+                } else if let Some(message) = panic.downcast_ref::<String>() {
+                    message.clone()
+                } else {
+                    "Callback panicked".to_string()
+                };
+                Err(JsError::internal_error(message))
+            }
         }
     }
 
-    /// Add a global JS function that is backed by a Rust function or closure.
+    /// Create a JS function that is backed by a Rust function or closure.
+    #[allow(dead_code)]
     pub fn create_callback<'a, F>(
         &'a self,
         callback: impl Callback<F> + 'static,
     ) -> Result<JsFunction<'a>, ExecutionError> {
+        let entry = self.callback_entry(callback);
+        let obj = OwnedJsValue::new(self, entry.new_function(self.context));
+        // Anonymous callbacks are only kept alive by their function.
+        let f = obj.try_into_function()?;
+        Ok(f)
+    }
+
+    /// Wrap a callback in a closure that can back JS functions.
+    fn callback_entry<F>(&self, callback: impl Callback<F> + 'static) -> CallbackEntry {
+        self.callback_entry_with_limits(callback, self.conversion_limits)
+    }
+
+    /// Wrap a callback whose arguments are converted with the given limits.
+    fn callback_entry_with_limits<F>(
+        &self,
+        callback: impl Callback<F> + 'static,
+        limits: ConversionLimits,
+    ) -> CallbackEntry {
         let argcount = callback.argument_count() as i32;
 
-        let context = self.context;
-        let wrapper = move |argc: c_int, argv: *mut q::JSValue| -> q::JSValue {
-            match Self::exec_callback(context, argc, argv, &callback) {
-                Ok(value) => value,
-                // TODO: better error reporting.
-                Err(e) => {
-                    let js_exception_value = match e {
-                        ExecutionError::Exception(e) => e,
-                        other => other.to_string().into(),
-                    };
-                    let js_exception =
-                        convert::serialize_value(context, js_exception_value).unwrap();
-                    unsafe {
-                        q::JS_Throw(context, js_exception);
-                    }
+        let wrapper =
+            move |context: *mut q::JSContext, argc: c_int, argv: *mut q::JSValue| -> q::JSValue {
+                match Self::exec_callback(context, argc, argv, &callback, limits) {
+                    Ok(value) => value,
+                    Err(e) => {
+                        // Throw the conversion error if the error object
+                        // could not be created.
+                        let exception = convert::serialize_error(context, e).or_else(|err| {
+                            convert::serialize_value(context, err.to_string().into())
+                        });
+                        if let Ok(exception) = exception {
+                            unsafe {
+                                q::JS_Throw(context, exception);
+                            }
+                        }
 
-                    q::JSValue {
-                        u: q::JSValueUnion { int32: 0 },
-                        tag: TAG_EXCEPTION,
+                        q::JSValue {
+                            u: q::JSValueUnion { int32: 0 },
+                            tag: TAG_EXCEPTION,
+                        }
                     }
                 }
-            }
-        };
-
-        let (pair, trampoline) = unsafe { build_closure_trampoline(wrapper) };
-        let data = (&*pair.1) as *const q::JSValue as *mut q::JSValue;
-        self.callbacks.lock().unwrap().push(pair);
-
-        let obj = unsafe {
-            let f = q::JS_NewCFunctionData(self.context, trampoline, argcount, 0, 1, data);
-            OwnedJsValue::new(self, f)
-        };
+            };
 
-        let f = obj.try_into_function()?;
-        Ok(f)
+        CallbackEntry {
+            closure: Rc::new(wrapper),
+            argcount,
+        }
     }
 
-    pub fn add_callback<'a, F>(
-        &'a self,
+    /// Add a global JS function that is backed by a Rust function or closure.
+    ///
+    /// The function is installed again when the context is reset.
+    pub fn add_callback<F>(
+        &self,
         name: &str,
         callback: impl Callback<F> + 'static,
     ) -> Result<(), ExecutionError> {
-        let cfunc = self.create_callback(callback)?;
-        let global = self.global()?;
-        global.set_property(name, cfunc.into_value())?;
+        let entry = self.callback_entry(callback);
+        let func = OwnedJsValue::new(self, entry.new_function(self.context));
+        self.global()?.set_property(name, func)?;
+        self.callbacks
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), entry);
         Ok(())
     }
 }
@@ -0,0 +1,86 @@
+use std::{
+    any::{Any, TypeId},
+    cell::RefCell,
+    collections::HashMap,
+    os::raw::c_void,
+    rc::Rc,
+};
+
+use libquickjs_sys as q;
+
+use crate::ContextError;
+
+use super::{function::FunctionRegistry, intrinsics::Intrinsics};
+
+/// User data of a context, by type.
+pub(crate) type UserData = HashMap<TypeId, Rc<dyn Any>>;
+
+/// State of a context that must be reachable from the raw context, like in
+/// callbacks and value conversion.
+///
+/// Stored as the opaque pointer of the context.
+pub(crate) struct ContextState {
+    /// Functions referenced by `JsFunction` handles.
+    pub functions: FunctionRegistry,
+    /// Builtins captured when the context was created.
+    pub intrinsics: Intrinsics,
+    user_data: RefCell<UserData>,
+}
+
+impl ContextState {
+    pub fn new() -> Self {
+        Self {
+            functions: FunctionRegistry::new(),
+            intrinsics: Intrinsics::new(),
+            user_data: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Set the state as the opaque pointer of a fresh context and capture
+    /// its builtins.
+    ///
+    /// The state must outlive the context.
+    pub unsafe fn install(&self, context: *mut q::JSContext) -> Result<(), ContextError> {
+        q::JS_SetContextOpaque(context, self as *const Self as *mut c_void);
+        self.intrinsics
+            .capture(context)
+            .map_err(|_| ContextError::ContextCreationFailed)
+    }
+
+    /// Free the values of the context held by the state.
+    ///
+    /// Must be called before the context is freed.
+    pub fn clear(&self, context: *mut q::JSContext) {
+        self.functions.clear(context);
+        self.intrinsics.clear(context);
+    }
+
+    /// Get the state of a context.
+    pub unsafe fn from_context<'a>(context: *mut q::JSContext) -> Option<&'a Self> {
+        (q::JS_GetContextOpaque(context) as *const Self).as_ref()
+    }
+
+    /// Set the user data of type `T`, returning the previous value.
+    pub fn set_user_data<T: 'static>(&self, value: T) -> Option<Rc<T>> {
+        self.user_data
+            .borrow_mut()
+            .insert(TypeId::of::<T>(), Rc::new(value))
+            .and_then(|previous| previous.downcast().ok())
+    }
+
+    /// Get the user data of type `T`.
+    pub fn user_data<T: 'static>(&self) -> Option<Rc<T>> {
+        let value = self.user_data.borrow().get(&TypeId::of::<T>())?.clone();
+        value.downcast().ok()
+    }
+
+    /// Get the user data of all types.
+    pub fn all_user_data(&self) -> UserData {
+        self.user_data.borrow().clone()
+    }
+
+    /// Replace the user data of all types.
+    pub fn set_all_user_data(&self, user_data: UserData) {
+        *self.user_data.borrow_mut() = user_data;
+    }
+}
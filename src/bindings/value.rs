@@ -487,6 +487,76 @@ impl<'a> OwnedJsObject<'a> {
             }
         }
     }
+
+    /// Define a property with the given `JS_PROP_*` flags.
+    pub fn define_property(
+        &self,
+        name: &str,
+        value: OwnedJsValue<'a>,
+        flags: i32,
+    ) -> Result<(), ExecutionError> {
+        let cname = make_cstring(name)?;
+        // NOTE: DefinePropertyValueStr takes ownership of the value, even if
+        // it fails.
+        let ret = unsafe {
+            q::JS_DefinePropertyValueStr(
+                self.value.context.context,
+                self.value.value,
+                cname.as_ptr(),
+                value.extract(),
+                flags | q::JS_PROP_THROW as i32,
+            )
+        };
+        if ret < 0 {
+            Err(self
+                .value
+                .context
+                .get_exception()
+                .unwrap_or_else(|| ExecutionError::Exception("Could not define property".into())))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Define an accessor property with the given `JS_PROP_*` flags.
+    ///
+    /// A missing getter or setter is `undefined`.
+    pub fn define_accessor(
+        &self,
+        name: &str,
+        getter: Option<OwnedJsValue<'a>>,
+        setter: Option<OwnedJsValue<'a>>,
+        flags: i32,
+    ) -> Result<(), ExecutionError> {
+        let context = self.value.context;
+        let cname = make_cstring(name)?;
+        let atom = OwnedJsAtom::new(context, unsafe {
+            q::JS_NewAtom(context.context, cname.as_ptr())
+        });
+        let undefined = q::JSValue {
+            u: q::JSValueUnion { int32: 0 },
+            tag: q::JS_TAG_UNDEFINED as i64,
+        };
+        // NOTE: DefinePropertyGetSet takes ownership of the getter and the
+        // setter, even if it fails.
+        let ret = unsafe {
+            q::JS_DefinePropertyGetSet(
+                context.context,
+                self.value.value,
+                atom.value,
+                getter.map_or(undefined, |getter| getter.extract()),
+                setter.map_or(undefined, |setter| setter.extract()),
+                flags | q::JS_PROP_THROW as i32,
+            )
+        };
+        if ret < 0 {
+            Err(context
+                .get_exception()
+                .unwrap_or_else(|| ExecutionError::Exception("Could not define property".into())))
+        } else {
+            Ok(())
+        }
+    }
 }
 
 /// Wraps an object from the QuickJs runtime.
@@ -1,30 +1,275 @@
-use std::{convert::TryFrom, marker::PhantomData, panic::RefUnwindSafe};
+use std::{convert::TryFrom, fmt, marker::PhantomData, panic::RefUnwindSafe, rc::Rc};
 
-use crate::value::{JsValue, ValueError};
+use crate::{
+    bindings::ContextState,
+    value::{IndexMap, JsValue, ValueError},
+};
+
+/// The kind of a Javascript error, named after its constructor.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[allow(missing_docs)]
+pub enum JsErrorKind {
+    Error,
+    TypeError,
+    RangeError,
+    ReferenceError,
+    SyntaxError,
+    /// QuickJS specific error, also used for panics in callbacks.
+    InternalError,
+}
+
+impl JsErrorKind {
+    /// The name of the Javascript constructor, like `TypeError`.
+    pub fn constructor_name(self) -> &'static str {
+        match self {
+            JsErrorKind::Error => "Error",
+            JsErrorKind::TypeError => "TypeError",
+            JsErrorKind::RangeError => "RangeError",
+            JsErrorKind::ReferenceError => "ReferenceError",
+            JsErrorKind::SyntaxError => "SyntaxError",
+            JsErrorKind::InternalError => "InternalError",
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub(crate) enum JsErrorRepr {
+    Error {
+        kind: JsErrorKind,
+        message: String,
+        properties: IndexMap<String, JsValue>,
+    },
+    Value(JsValue),
+}
+
+/// An exception thrown by a callback.
+///
+/// Callbacks returning `Result<T, JsError>` throw a real Javascript error
+/// object, like `TypeError`, with additional properties.
+/// Callbacks can also fail with a `String`, a `&str` or a boxed
+/// `std::error::Error`, which are thrown as a string with the message, so `?`
+/// works for any error in callbacks returning
+/// `Result<T, Box<dyn Error + Send + Sync>>`.
+///
+/// ```rust
+/// use quick_js::{Context, JsError};
+///
+/// let context = Context::new().unwrap();
+/// context
+///     .add_callback("parsePort", |port: String| -> Result<i32, JsError> {
+///         let port: i32 = port
+///             .parse()
+///             .map_err(|_| JsError::type_error("not a number").code("ERR_PORT"))?;
+///         if !(0..65536).contains(&port) {
+///             return Err(JsError::range_error("out of range").property("port", port));
+///         }
+///         Ok(port)
+///     })
+///     .unwrap();
+///
+/// let code = context
+///     .eval_as::<String>(" try { parsePort('x') } catch (e) { e.name + ' ' + e.code } ")
+///     .unwrap();
+/// assert_eq!(code, "TypeError ERR_PORT");
+/// ```
+#[derive(PartialEq, Clone, Debug)]
+pub struct JsError {
+    pub(crate) repr: JsErrorRepr,
+}
+
+impl JsError {
+    /// Create an error with the given kind and message.
+    pub fn with_kind(kind: JsErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            repr: JsErrorRepr::Error {
+                kind,
+                message: message.into(),
+                properties: IndexMap::new(),
+            },
+        }
+    }
+
+    /// Create an `Error`.
+    pub fn new(message: impl Into<String>) -> Self {
+        Self::with_kind(JsErrorKind::Error, message)
+    }
+
+    /// Create a `TypeError`.
+    pub fn type_error(message: impl Into<String>) -> Self {
+        Self::with_kind(JsErrorKind::TypeError, message)
+    }
+
+    /// Create a `RangeError`.
+    pub fn range_error(message: impl Into<String>) -> Self {
+        Self::with_kind(JsErrorKind::RangeError, message)
+    }
+
+    /// Create a `ReferenceError`.
+    pub fn reference_error(message: impl Into<String>) -> Self {
+        Self::with_kind(JsErrorKind::ReferenceError, message)
+    }
+
+    /// Create a `SyntaxError`.
+    pub fn syntax_error(message: impl Into<String>) -> Self {
+        Self::with_kind(JsErrorKind::SyntaxError, message)
+    }
+
+    /// Create an `InternalError`.
+    pub fn internal_error(message: impl Into<String>) -> Self {
+        Self::with_kind(JsErrorKind::InternalError, message)
+    }
+
+    /// Throw an arbitrary value instead of an error object.
+    pub fn value(value: impl Into<JsValue>) -> Self {
+        Self {
+            repr: JsErrorRepr::Value(value.into()),
+        }
+    }
+
+    /// Set the `code` property of the error.
+    ///
+    /// Has no effect on errors created with [JsError::value].
+    pub fn code(self, code: impl Into<String>) -> Self {
+        self.property("code", code.into())
+    }
+
+    /// Set a property of the error.
+    ///
+    /// Has no effect on errors created with [JsError::value].
+    pub fn property(mut self, name: impl Into<String>, value: impl Into<JsValue>) -> Self {
+        if let JsErrorRepr::Error { properties, .. } = &mut self.repr {
+            properties.insert(name.into(), value.into());
+        }
+        self
+    }
+
+    /// Returns the kind of the error, or `None` for thrown values.
+    pub fn kind(&self) -> Option<JsErrorKind> {
+        match &self.repr {
+            JsErrorRepr::Error { kind, .. } => Some(*kind),
+            JsErrorRepr::Value(_) => None,
+        }
+    }
+}
+
+/// Formats errors like Javascript, as `TypeError: message`, and thrown
+/// values with their `Display` implementation.
+impl fmt::Display for JsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.repr {
+            JsErrorRepr::Error { kind, message, .. } if message.is_empty() => {
+                f.write_str(kind.constructor_name())
+            }
+            JsErrorRepr::Error { kind, message, .. } => {
+                write!(f, "{}: {}", kind.constructor_name(), message)
+            }
+            JsErrorRepr::Value(JsValue::String(value)) => f.write_str(value),
+            JsErrorRepr::Value(value) => value.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for JsError {}
+
+impl From<String> for JsError {
+    fn from(message: String) -> Self {
+        JsError::value(message)
+    }
+}
+
+impl From<&str> for JsError {
+    fn from(message: &str) -> Self {
+        JsError::value(message)
+    }
+}
+
+impl From<Box<dyn std::error::Error + Send + Sync>> for JsError {
+    fn from(e: Box<dyn std::error::Error + Send + Sync>) -> Self {
+        JsError::value(e.to_string())
+    }
+}
+
+impl From<ValueError> for JsError {
+    fn from(e: ValueError) -> Self {
+        JsError::value(e.to_string())
+    }
+}
+
+impl From<crate::ExecutionError> for JsError {
+    fn from(e: crate::ExecutionError) -> Self {
+        JsError::value(e.to_string())
+    }
+}
 
 pub trait IntoCallbackResult {
-    fn into_callback_res(self) -> Result<JsValue, String>;
+    fn into_callback_res(self) -> Result<JsValue, JsError>;
 }
 
 impl<T: Into<JsValue>> IntoCallbackResult for T {
-    fn into_callback_res(self) -> Result<JsValue, String> {
+    fn into_callback_res(self) -> Result<JsValue, JsError> {
         Ok(self.into())
     }
 }
 
-impl<T: Into<JsValue>, E: std::fmt::Display> IntoCallbackResult for Result<T, E> {
-    fn into_callback_res(self) -> Result<JsValue, String> {
+impl IntoCallbackResult for () {
+    fn into_callback_res(self) -> Result<JsValue, JsError> {
+        Ok(JsValue::Undefined)
+    }
+}
+
+impl<T: Into<JsValue>, E: Into<JsError>> IntoCallbackResult for Result<T, E> {
+    fn into_callback_res(self) -> Result<JsValue, JsError> {
         match self {
             Ok(v) => Ok(v.into()),
-            Err(e) => Err(e.to_string()),
+            Err(e) => Err(e.into()),
         }
     }
 }
 
+/// The context of a callback invocation.
+///
+/// Callbacks can take a `&CallContext` as their first parameter, before the
+/// Javascript arguments.
+///
+/// ```rust
+/// use quick_js::{CallContext, Context};
+///
+/// struct Tenant {
+///     name: String,
+/// }
+///
+/// let context = Context::new().unwrap();
+/// context.set_user_data(Tenant { name: "acme".into() });
+/// context
+///     .add_callback("tenantName", |ctx: &CallContext| {
+///         ctx.data::<Tenant>().map(|tenant| tenant.name.clone())
+///     })
+///     .unwrap();
+///
+/// let name = context.eval_as::<String>(" tenantName() ").unwrap();
+/// assert_eq!(name, "acme");
+/// ```
+pub struct CallContext<'a> {
+    state: &'a ContextState,
+}
+
+impl<'a> CallContext<'a> {
+    pub(crate) fn new(state: &'a ContextState) -> Self {
+        Self { state }
+    }
+
+    /// Get the user data of type `T` of the context.
+    ///
+    /// See [crate::Context::set_user_data].
+    pub fn data<T: 'static>(&self) -> Option<Rc<T>> {
+        self.state.user_data()
+    }
+}
+
 /// The Callback trait is implemented for functions/closures that can be
 /// used as callbacks in the JS runtime.
 pub trait Callback<F>: RefUnwindSafe {
-    /// Returns the number of required Javascript arguments.
+    /// Returns the number of declared Javascript arguments.
     fn argument_count(&self) -> usize;
 
     /// Execute the callback.
@@ -32,96 +277,255 @@ pub trait Callback<F>: RefUnwindSafe {
     /// Should return:
     ///   - Err(_) if the JS values could not be converted
     ///   - Ok(Err(_)) if an error ocurred while processing.
-    ///       The given error will be raised as a JS exception.
+    ///     The given error will be raised as a JS exception.
     ///   - Ok(Ok(result)) when execution succeeded.
-    fn call(&self, args: Vec<JsValue>) -> Result<Result<JsValue, String>, ValueError>;
+    fn call(
+        &self,
+        context: &CallContext,
+        args: Vec<JsValue>,
+    ) -> Result<Result<JsValue, JsError>, ValueError>;
+}
+
+/// A type erased callback, for storing callbacks before they are added to a
+/// context.
+pub(crate) struct BoxedCallback {
+    argument_count: usize,
+    #[allow(clippy::type_complexity)]
+    call: Box<
+        dyn Fn(&CallContext, Vec<JsValue>) -> Result<Result<JsValue, JsError>, ValueError>
+            + RefUnwindSafe,
+    >,
+}
+
+impl BoxedCallback {
+    pub fn new<F>(callback: impl Callback<F> + 'static) -> Self {
+        Self {
+            argument_count: callback.argument_count(),
+            call: Box::new(move |context, args| callback.call(context, args)),
+        }
+    }
+}
+
+impl Callback<PhantomData<BoxedCallback>> for BoxedCallback {
+    fn argument_count(&self) -> usize {
+        self.argument_count
+    }
+
+    fn call(
+        &self,
+        context: &CallContext,
+        args: Vec<JsValue>,
+    ) -> Result<Result<JsValue, JsError>, ValueError> {
+        (self.call)(context, args)
+    }
+}
+
+/// The name of a Rust type without module paths, like `Vec<String>`.
+fn short_type_name<T>() -> String {
+    let name = std::any::type_name::<T>();
+    let mut short = String::with_capacity(name.len());
+    let mut segment_start = 0;
+    for (i, c) in name.char_indices() {
+        if c.is_alphanumeric() || c == '_' || c == ':' {
+            if name[i..].starts_with("::") {
+                segment_start = i + 2;
+            }
+        } else {
+            short.push_str(&name[segment_start.min(i)..i]);
+            short.push(c);
+            segment_start = i + c.len_utf8();
+        }
+    }
+    short.push_str(&name[segment_start.min(name.len())..]);
+    short
+}
+
+/// The Javascript type of a value, for error messages.
+fn js_type_name(value: &JsValue) -> &'static str {
+    match value {
+        JsValue::Undefined => "undefined",
+        JsValue::Null => "null",
+        JsValue::Bool(_) => "boolean",
+        JsValue::Int(_) | JsValue::Float(_) => "number",
+        JsValue::String(_) => "string",
+        JsValue::Array(_) => "array",
+        JsValue::Object(_) => "object",
+        JsValue::Map(_) => "Map",
+        JsValue::Set(_) => "Set",
+        JsValue::Symbol(_) => "symbol",
+        JsValue::Function(_) => "function",
+        JsValue::Bytes(_) => "ArrayBuffer",
+        JsValue::TypedArray(array) => array.constructor_name(),
+        #[cfg(feature = "chrono")]
+        JsValue::Date(_) => "Date",
+        #[cfg(feature = "bigint")]
+        JsValue::BigInt(_) => "bigint",
+        #[cfg(feature = "bignum")]
+        JsValue::BigDecimal(_) => "bigdecimal",
+        #[cfg(feature = "bignum")]
+        JsValue::BigFloat(_) => "bigfloat",
+        JsValue::__NonExhaustive => unreachable!(),
+    }
+}
+
+/// Convert the next argument of a callback.
+///
+/// Missing arguments are converted from `undefined`, so they can be
+/// optional. Fails with a `TypeError` naming the argument and the expected
+/// type.
+fn next_argument<T: TryFrom<JsValue>>(
+    args: &mut std::vec::IntoIter<JsValue>,
+    index: &mut usize,
+) -> Result<T, JsError> {
+    *index += 1;
+    match args.next() {
+        Some(value) => {
+            let actual = js_type_name(&value);
+            T::try_from(value).map_err(|_| {
+                JsError::type_error(format!(
+                    "Invalid argument {}: expected {}, got {}",
+                    index,
+                    short_type_name::<T>(),
+                    actual
+                ))
+            })
+        }
+        None => T::try_from(JsValue::Undefined).map_err(|_| {
+            JsError::type_error(format!(
+                "Missing argument {}: expected {}",
+                index,
+                short_type_name::<T>()
+            ))
+        }),
+    }
+}
+
+macro_rules! try_argument {
+    ($arg:ident, $args:ident, $index:ident) => {
+        match next_argument::<$arg>(&mut $args, &mut $index) {
+            Ok(value) => value,
+            Err(e) => return Ok(Err(e)),
+        }
+    };
 }
 
 macro_rules! impl_callback {
-    (@call $len:literal $self:ident $args:ident ) => {
-        $self()
+    (@context $ctx:ty, $context:ident) => {
+        $context
     };
 
-    (@call $len:literal $self:ident $args:ident $( $arg:ident ),* ) => {
+    (@impl $len:literal ( $( $ctx:ty )? ) ( $( $arg:ident, )* )) => {
+        impl<
+            $( $arg, )*
+            R,
+            F,
+        > Callback<PhantomData<(
+            $( $ctx, )?
+            $( &$arg, )*
+            &R,
+            &F,
+        )>> for F
+        where
+            $( $arg: TryFrom<JsValue>, )*
+            R: IntoCallbackResult,
+            F: Fn( $( $ctx, )? $( $arg, )*  ) -> R + Sized + RefUnwindSafe,
+        {
+            fn argument_count(&self) -> usize {
+                $len
+            }
+
+            fn call(
+                &self,
+                #[allow(unused_variables)]
+                context: &CallContext,
+                args: Vec<JsValue>,
+            ) -> Result<Result<JsValue, JsError>, ValueError> {
+                if args.len() > $len {
+                    return Ok(Err(JsError::type_error(format!(
+                        "Invalid argument count: Expected at most {}, got {}",
+                        self.argument_count(),
+                        args.len()
+                    ))));
+                }
+
+                #[allow(unused_mut, unused_variables)]
+                let mut args = args.into_iter();
+                #[allow(unused_mut, unused_variables)]
+                let mut index = 0;
+                let res = self(
+                    $( impl_callback!(@context $ctx, context), )?
+                    $( try_argument!($arg, args, index), )*
+                );
+                Ok(res.into_callback_res())
+            }
+        }
+
+        impl<
+            $( $arg, )*
+            T,
+            R,
+            F,
+        > Callback<PhantomData<(
+            $( $ctx, )?
+            $( &$arg, )*
+            &Rest<T>,
+            &R,
+            &F,
+        )>> for F
+        where
+            $( $arg: TryFrom<JsValue>, )*
+            T: TryFrom<JsValue>,
+            R: IntoCallbackResult,
+            F: Fn( $( $ctx, )? $( $arg, )* Rest<T> ) -> R + Sized + RefUnwindSafe,
         {
-            let mut iter = $args.into_iter();
-            $self(
+            fn argument_count(&self) -> usize {
+                $len
+            }
+
+            fn call(
+                &self,
+                #[allow(unused_variables)]
+                context: &CallContext,
+                args: Vec<JsValue>,
+            ) -> Result<Result<JsValue, JsError>, ValueError> {
+                let mut args = args.into_iter();
+                let mut index = 0;
                 $(
-                    $arg::try_from(iter.next().unwrap())?,
+                    #[allow(non_snake_case)]
+                    let $arg = try_argument!($arg, args, index);
                 )*
-            )
+                let mut rest = Vec::with_capacity(args.len());
+                while args.len() > 0 {
+                    rest.push(try_argument!(T, args, index));
+                }
+                let res = self(
+                    $( impl_callback!(@context $ctx, context), )?
+                    $( $arg, )*
+                    Rest(rest)
+                );
+                Ok(res.into_callback_res())
+            }
         }
     };
 
     [ $(  $len:literal : ( $( $arg:ident, )* ), )* ] => {
         $(
-
-            impl<
-                $( $arg, )*
-                E,
-                R,
-                F,
-            > Callback<PhantomData<(
-                $( &$arg, )*
-                &E,
-                &R,
-                &F,
-            )>> for F
-            where
-                $( $arg: TryFrom<JsValue, Error = E>, )*
-                ValueError: From<E>,
-                R: IntoCallbackResult,
-                F: Fn( $( $arg, )*  ) -> R + Sized + RefUnwindSafe,
-            {
-                fn argument_count(&self) -> usize {
-                    $len
-                }
-
-                fn call(&self, args: Vec<JsValue>) -> Result<Result<JsValue, String>, ValueError> {
-                    if args.len() != $len {
-                        return Ok(Err(format!(
-                            "Invalid argument count: Expected {}, got {}",
-                            self.argument_count(),
-                            args.len()
-                        )));
-                    }
-
-                    let res = impl_callback!(@call $len self args $($arg),* );
-                    Ok(res.into_callback_res())
-                }
-            }
+            impl_callback!(@impl $len () ( $( $arg, )* ));
+            impl_callback!(@impl $len (&CallContext<'_>) ( $( $arg, )* ));
         )*
     };
 }
 
-impl<R, F> Callback<PhantomData<(&R, &F)>> for F
-where
-    R: IntoCallbackResult,
-    F: Fn() -> R + Sized + RefUnwindSafe,
-{
-    fn argument_count(&self) -> usize {
-        0
-    }
-
-    fn call(&self, args: Vec<JsValue>) -> Result<Result<JsValue, String>, ValueError> {
-        if args.len() != 0 {
-            return Ok(Err(format!(
-                "Invalid argument count: Expected 0, got {}",
-                args.len(),
-            )));
-        }
-
-        let res = self();
-        Ok(res.into_callback_res())
-    }
-}
-
 impl_callback![
+    0: (),
     1: (A1,),
     2: (A1, A2,),
     3: (A1, A2, A3,),
     4: (A1, A2, A3, A4,),
     5: (A1, A2, A3, A4, A5,),
+    6: (A1, A2, A3, A4, A5, A6,),
+    7: (A1, A2, A3, A4, A5, A6, A7,),
+    8: (A1, A2, A3, A4, A5, A6, A7, A8,),
 ];
 
 /// A wrapper around Vec<JsValue>, used for vararg callbacks.
@@ -137,31 +541,65 @@ impl Arguments {
     }
 }
 
-impl<F> Callback<PhantomData<(&Arguments, &F)>> for F
+impl<F, R> Callback<PhantomData<(&Arguments, &F, &R)>> for F
 where
-    F: Fn(Arguments) + Sized + RefUnwindSafe,
+    R: IntoCallbackResult,
+    F: Fn(Arguments) -> R + Sized + RefUnwindSafe,
 {
     fn argument_count(&self) -> usize {
         0
     }
 
-    fn call(&self, args: Vec<JsValue>) -> Result<Result<JsValue, String>, ValueError> {
-        (self)(Arguments(args));
-        Ok(Ok(JsValue::Undefined))
+    fn call(
+        &self,
+        _context: &CallContext,
+        args: Vec<JsValue>,
+    ) -> Result<Result<JsValue, JsError>, ValueError> {
+        let res = (self)(Arguments(args));
+        Ok(res.into_callback_res())
     }
 }
 
-impl<F, R> Callback<PhantomData<(&Arguments, &F, &R)>> for F
+impl<F, R> Callback<PhantomData<(&CallContext<'_>, &Arguments, &F, &R)>> for F
 where
     R: IntoCallbackResult,
-    F: Fn(Arguments) -> R + Sized + RefUnwindSafe,
+    F: Fn(&CallContext, Arguments) -> R + Sized + RefUnwindSafe,
 {
     fn argument_count(&self) -> usize {
         0
     }
 
-    fn call(&self, args: Vec<JsValue>) -> Result<Result<JsValue, String>, ValueError> {
-        let res = (self)(Arguments(args));
+    fn call(
+        &self,
+        context: &CallContext,
+        args: Vec<JsValue>,
+    ) -> Result<Result<JsValue, JsError>, ValueError> {
+        let res = (self)(context, Arguments(args));
         Ok(res.into_callback_res())
     }
 }
+
+/// The remaining arguments of a callback, converted to `T`.
+///
+/// Must be the last parameter of the callback closure. Parameters before it
+/// can be optional.
+///
+/// ```rust
+/// use quick_js::{Context, Rest};
+///
+/// let context = Context::new().unwrap();
+/// context
+///     .add_callback("sum", |first: i32, rest: Rest<i32>| {
+///         first + rest.into_vec().into_iter().sum::<i32>()
+///     })
+///     .unwrap();
+/// assert_eq!(context.eval_as::<i32>(" sum(1, 2, 3) ").unwrap(), 6);
+/// ```
+pub struct Rest<T>(Vec<T>);
+
+impl<T> Rest<T> {
+    /// Unpack the arguments into a Vec.
+    pub fn into_vec(self) -> Vec<T> {
+        self.0
+    }
+}
@@ -0,0 +1,243 @@
+use std::{convert::TryFrom, marker::PhantomData};
+
+use crate::{
+    bindings::{HostClass, HostObject},
+    Callback, Context, ExecutionError, JsValue,
+};
+
+/// Builder for a Javascript class of a Rust value type.
+///
+/// Created with [Context::class].
+///
+/// Instances are the objects that `T` converts to with `Into<JsValue>`, so
+/// `T` must convert to a [JsValue::Object]. Their prototype is the class
+/// prototype, which holds the methods and, with the `bignum` feature, the
+/// overloaded operators. Instances are converted back to `T` with
+/// `TryFrom<JsValue>`, from their own properties.
+///
+/// Values of `T` returned by other callbacks are plain objects, so scripts
+/// should create instances with the constructor.
+///
+/// ```rust
+/// # #[cfg(feature = "bignum")] {
+/// use std::{collections::HashMap, convert::TryFrom, ops::Add};
+/// use quick_js::{Context, JsValue, ValueError};
+///
+/// #[derive(Clone, Copy, PartialEq, Debug)]
+/// struct Money {
+///     cents: i32,
+/// }
+///
+/// impl Add for Money {
+///     type Output = Money;
+///
+///     fn add(self, other: Money) -> Money {
+///         Money { cents: self.cents + other.cents }
+///     }
+/// }
+///
+/// impl From<Money> for JsValue {
+///     fn from(money: Money) -> JsValue {
+///         let mut object = HashMap::new();
+///         object.insert("cents", money.cents);
+///         object.into()
+///     }
+/// }
+///
+/// impl TryFrom<JsValue> for Money {
+///     type Error = ValueError;
+///
+///     fn try_from(value: JsValue) -> Result<Money, ValueError> {
+///         let object = HashMap::<String, i32>::try_from(value)?;
+///         let cents = object.get("cents").ok_or(ValueError::UnexpectedType)?;
+///         Ok(Money { cents: *cents })
+///     }
+/// }
+///
+/// let context = Context::new().unwrap();
+/// context
+///     .class::<Money>("", "Money")
+///     .constructor(|cents: i32| Money { cents })
+///     .method("format", |money: Money| format!("${:.2}", money.cents as f64 / 100.0))
+///     .operator_add()
+///     .operator_eq()
+///     .install()
+///     .unwrap();
+///
+/// let total = context.eval_as::<Money>(" new Money(150) + new Money(275) ").unwrap();
+/// assert_eq!(total, Money { cents: 425 });
+/// let output = context.eval_as::<String>(" (new Money(1) + new Money(2)).format() ").unwrap();
+/// assert_eq!(output, "$0.03");
+/// # }
+/// ```
+#[must_use = "the class is only installed by calling `install`"]
+pub struct Class<'a, T> {
+    context: &'a Context,
+    object: HostObject,
+    name: String,
+    class: HostClass,
+    value_type: PhantomData<fn(T) -> T>,
+}
+
+impl<'a, T> Class<'a, T>
+where
+    T: TryFrom<JsValue> + Into<JsValue> + 'static,
+{
+    pub(crate) fn new(context: &'a Context, obj_path: &str, name: &str) -> Self {
+        Self {
+            context,
+            object: HostObject::new(obj_path),
+            name: name.to_string(),
+            class: HostClass::new(&context.wrapper, |value: T| value),
+            value_type: PhantomData,
+        }
+    }
+
+    /// Set the constructor, which is called with the arguments of `new` and
+    /// must return a `T`.
+    ///
+    /// By default, the constructor converts a single object argument to `T`.
+    /// See [Context::add_callback] for the requirements of the callback.
+    pub fn constructor<F>(mut self, callback: impl Callback<F> + 'static) -> Self {
+        self.class.set_constructor(&self.context.wrapper, callback);
+        self
+    }
+
+    /// Add a method to the prototype.
+    ///
+    /// The callback receives the instance as the first argument, followed by
+    /// the arguments of the call.
+    pub fn method<F>(mut self, name: &str, callback: impl Callback<F> + 'static) -> Self {
+        self.class.add_method(&self.context.wrapper, name, callback);
+        self
+    }
+
+    /// Install the class in the context.
+    ///
+    /// The class is installed again when the context is reset.
+    pub fn install(mut self) -> Result<(), ExecutionError> {
+        self.object.add_class(&self.name, self.class);
+        self.context.wrapper.add_host_object(self.object)
+    }
+}
+
+/// Operators are created with `Operators.create` of the QuickJS bignum
+/// extension, so both operands must be instances of the class, or a number
+/// for the `_number` variants.
+#[cfg(feature = "bignum")]
+impl<'a, T> Class<'a, T>
+where
+    T: TryFrom<JsValue> + Into<JsValue> + 'static,
+{
+    fn binary<R, F>(mut self, op: &'static str, f: F) -> Self
+    where
+        R: Into<JsValue> + 'static,
+        F: Fn(T, T) -> R + std::panic::RefUnwindSafe + 'static,
+    {
+        let wraps = op != "==" && op != "<";
+        self.class
+            .add_operator(&self.context.wrapper, op, None, wraps, move |a: T, b: T| {
+                f(a, b)
+            });
+        self
+    }
+
+    /// Implement `+` with [std::ops::Add].
+    pub fn operator_add(self) -> Self
+    where
+        T: std::ops::Add<Output = T>,
+    {
+        self.binary("+", |a, b| a + b)
+    }
+
+    /// Implement `-` with [std::ops::Sub].
+    pub fn operator_sub(self) -> Self
+    where
+        T: std::ops::Sub<Output = T>,
+    {
+        self.binary("-", |a, b| a - b)
+    }
+
+    /// Implement `*` with [std::ops::Mul].
+    pub fn operator_mul(self) -> Self
+    where
+        T: std::ops::Mul<Output = T>,
+    {
+        self.binary("*", |a, b| a * b)
+    }
+
+    /// Implement `/` with [std::ops::Div].
+    pub fn operator_div(self) -> Self
+    where
+        T: std::ops::Div<Output = T>,
+    {
+        self.binary("/", |a, b| a / b)
+    }
+
+    /// Implement `%` with [std::ops::Rem].
+    pub fn operator_rem(self) -> Self
+    where
+        T: std::ops::Rem<Output = T>,
+    {
+        self.binary("%", |a, b| a % b)
+    }
+
+    /// Implement unary `-` with [std::ops::Neg].
+    pub fn operator_neg(mut self) -> Self
+    where
+        T: std::ops::Neg<Output = T>,
+    {
+        self.class
+            .add_operator(&self.context.wrapper, "neg", None, true, |a: T| -a);
+        self
+    }
+
+    /// Implement `==` and `!=` with [PartialEq].
+    ///
+    /// Like for other objects, `===` still compares identity.
+    pub fn operator_eq(self) -> Self
+    where
+        T: PartialEq,
+    {
+        self.binary("==", |a, b| a == b)
+    }
+
+    /// Implement `<`, `<=`, `>` and `>=` with [PartialOrd].
+    pub fn operator_lt(self) -> Self
+    where
+        T: PartialOrd,
+    {
+        self.binary("<", |a, b| a < b)
+    }
+
+    /// Implement `*` with a number on either side with `Mul<f64>`, like
+    /// scaling a vector.
+    ///
+    /// `x * value` is computed as `value * x`.
+    pub fn operator_mul_number(mut self) -> Self
+    where
+        T: std::ops::Mul<f64, Output = T>,
+    {
+        let wrapper = &self.context.wrapper;
+        self.class
+            .add_operator(wrapper, "*", Some("left"), true, |x: f64, a: T| a * x);
+        self.class
+            .add_operator(wrapper, "*", Some("right"), true, |a: T, x: f64| a * x);
+        self
+    }
+
+    /// Implement `/` with a number on the right side with `Div<f64>`.
+    pub fn operator_div_number(mut self) -> Self
+    where
+        T: std::ops::Div<f64, Output = T>,
+    {
+        self.class.add_operator(
+            &self.context.wrapper,
+            "/",
+            Some("right"),
+            true,
+            |a: T, x: f64| a / x,
+        );
+        self
+    }
+}
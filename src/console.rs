@@ -1,7 +1,14 @@
 //! Javascript console integration.
 //! See the [ConsoleBackend] trait for more info.
 
-use super::JsValue;
+use std::{
+    collections::HashMap,
+    io::Write,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use super::{value::CIRCULAR, InspectOptions, JsValue};
 
 /// Log level of a log message sent via the console.
 /// These levels represent the different functions defined in the spec:
@@ -32,6 +39,167 @@ impl std::fmt::Display for Level {
     }
 }
 
+/// The console function that caused a [ConsoleMessage].
+#[derive(PartialEq, Clone, Debug)]
+#[non_exhaustive]
+pub enum MessageKind {
+    /// A message from `log`, `info`, `debug`, `warn`, `error`, `trace` or
+    /// `dirxml`, or a warning of the console itself, like for an unknown
+    /// timer.
+    Log,
+    /// A failed `assert`.
+    Assert,
+    /// A call to `count`.
+    Count {
+        /// The label of the counter.
+        label: String,
+        /// The new count of the label.
+        count: u64,
+    },
+    /// A call to `countReset`.
+    CountReset {
+        /// The label of the counter.
+        label: String,
+    },
+    /// A timer was started with `time`.
+    Time {
+        /// The label of the timer.
+        label: String,
+    },
+    /// A call to `timeLog`.
+    TimeLog {
+        /// The label of the timer.
+        label: String,
+        /// The time elapsed since the timer was started.
+        elapsed: Duration,
+    },
+    /// A timer was stopped with `timeEnd`.
+    TimeEnd {
+        /// The label of the timer.
+        label: String,
+        /// The time elapsed since the timer was started.
+        elapsed: Duration,
+    },
+    /// A group was started with `group` or `groupCollapsed`.
+    /// The values hold the group label.
+    Group {
+        /// Whether the group was started with `groupCollapsed`.
+        collapsed: bool,
+    },
+    /// The current group was ended with `groupEnd`.
+    GroupEnd,
+    /// A call to `table`.
+    /// The values hold the tabular data.
+    Table {
+        /// The columns to show, if given.
+        columns: Option<Vec<String>>,
+    },
+    /// A call to `dir`.
+    Dir,
+    /// A call to `clear`, which also ends all groups.
+    Clear,
+}
+
+/// A structured console message.
+///
+/// The values are the message as it would be printed: format specifiers
+/// like `%s` are already applied, and messages of `count` or `timeEnd` hold
+/// their formatted `label: value` text.
+///
+/// Values that refer to themselves are passed as strings, formatted with
+/// [JsValue::inspect] and the default options, which show the references as
+/// `[Circular]`. Values that can not be converted at all are passed as their
+/// error message.
+///
+/// The location of the console call is taken from the stack trace, so it is
+/// only known for script code. QuickJS only records the line for functions
+/// that span more than one line.
+#[derive(PartialEq, Clone, Debug)]
+#[non_exhaustive]
+pub struct ConsoleMessage {
+    /// The console function that caused the message.
+    pub kind: MessageKind,
+    /// The log level of the message.
+    pub level: Level,
+    /// The values to print, which can be empty, for example for `time`.
+    pub values: Vec<JsValue>,
+    /// The number of open groups the message belongs to.
+    pub group_depth: usize,
+    /// The filename of the script that called the console.
+    pub filename: Option<String>,
+    /// The line of the console call.
+    pub line: Option<u32>,
+    /// The name of the function that called the console, or `None` for
+    /// top-level code and anonymous functions.
+    pub function: Option<String>,
+}
+
+impl ConsoleMessage {
+    /// Create a log message without location.
+    pub fn new(level: Level, values: Vec<JsValue>) -> Self {
+        Self {
+            kind: MessageKind::Log,
+            level,
+            values,
+            group_depth: 0,
+            filename: None,
+            line: None,
+            function: None,
+        }
+    }
+
+    /// The values of the message, separated by spaces.
+    ///
+    /// Strings are printed as they are, and other values are formatted with
+    /// [JsValue::inspect] and the default options, like Node prints them.
+    pub fn text(&self) -> String {
+        self.text_with(&InspectOptions::default())
+    }
+
+    /// The values of the message, like [ConsoleMessage::text] but formatted
+    /// with the given options.
+    pub fn text_with(&self, options: &InspectOptions) -> String {
+        self.values
+            .iter()
+            .map(|value| match value {
+                JsValue::String(value) => value.clone(),
+                value => value.inspect(options),
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Set the location from a stack trace line like `at f (script.js:3)`.
+    fn set_location(&mut self, frame: &str) {
+        let frame = frame.trim();
+        let frame = frame.strip_prefix("at ").unwrap_or(frame);
+        let (function, location) = match frame.rfind(" (") {
+            Some(index) if frame.ends_with(')') => {
+                (&frame[..index], &frame[index + 2..frame.len() - 1])
+            }
+            _ => ("", frame),
+        };
+
+        if !matches!(function, "" | "<eval>" | "<anonymous>") {
+            self.function = Some(function.to_string());
+        }
+        if location == "native" {
+            return;
+        }
+        let (filename, line) = match location.rfind(':') {
+            Some(index) => match location[index + 1..].parse() {
+                Ok(line) => (&location[..index], Some(line)),
+                Err(_) => (location, None),
+            },
+            None => (location, None),
+        };
+        if !filename.is_empty() {
+            self.filename = Some(filename.to_string());
+        }
+        self.line = line;
+    }
+}
+
 /// A console backend that handles console messages sent from JS via
 /// console.{log,debug,trace,...} functions.
 ///
@@ -54,67 +222,301 @@ impl std::fmt::Display for Level {
 ///     # .unwrap();
 /// ```
 ///
-pub trait ConsoleBackend: std::panic::RefUnwindSafe + 'static {
+pub trait ConsoleBackend: std::panic::RefUnwindSafe + Send + Sync + 'static {
     /// Handle a log message.
     fn log(&self, level: Level, values: Vec<JsValue>);
+
+    /// Handle a structured message, with group depth, labels, timings and
+    /// the location of the console call.
+    ///
+    /// The default implementation forwards messages with values to
+    /// [ConsoleBackend::log].
+    fn message(&self, message: ConsoleMessage) {
+        if !message.values.is_empty() {
+            self.log(message.level, message.values);
+        }
+    }
 }
 
 impl<F> ConsoleBackend for F
 where
-    F: Fn(Level, Vec<JsValue>) + std::panic::RefUnwindSafe + 'static,
+    F: Fn(Level, Vec<JsValue>) + std::panic::RefUnwindSafe + Send + Sync + 'static,
 {
     fn log(&self, level: Level, values: Vec<JsValue>) {
         (self)(level, values);
     }
 }
 
-#[cfg(feature = "log")]
-mod log {
-    use super::{JsValue, Level};
+/// Counters, timers and groups of a console.
+#[derive(Default)]
+struct ConsoleState {
+    counts: HashMap<String, u64>,
+    timers: HashMap<String, Instant>,
+    group_depth: usize,
+}
 
-    /// A console implementation that logs messages via the `log` crate.
+/// Turns the calls of the Javascript console into messages for a backend.
+pub(crate) struct Console {
+    backend: Arc<dyn ConsoleBackend>,
+    // A Mutex is used over a RefCell because it needs to be unwind-safe.
+    state: Mutex<ConsoleState>,
+}
+
+impl Console {
+    pub fn new(backend: Arc<dyn ConsoleBackend>) -> Self {
+        Self {
+            backend,
+            state: Mutex::new(ConsoleState::default()),
+        }
+    }
+
+    /// Forget all counters, timers and groups.
+    pub fn reset(&self) {
+        *self.state.lock().unwrap() = ConsoleState::default();
+    }
+
+    /// Handle a call of the console function `method`.
     ///
-    /// Only available with the `log` feature.
-    pub struct LogConsole;
+    /// `frame` is the stack trace line of the caller. Labels are passed as
+    /// the first argument.
+    pub fn write(&self, method: &str, frame: Option<String>, args: Vec<JsValue>) {
+        let mut args = args
+            .into_iter()
+            .map(|value| match is_circular(&value) {
+                true => JsValue::String(value.inspect(&InspectOptions::default())),
+                false => value,
+            })
+            .collect::<Vec<_>>();
+        let mut state = self.state.lock().unwrap();
+        let mut group_depth = state.group_depth;
+
+        let label = |args: &mut Vec<JsValue>| match args.is_empty() {
+            true => "default".to_string(),
+            false => match args.remove(0) {
+                JsValue::String(label) => label,
+                _ => "default".to_string(),
+            },
+        };
+        let warning = |message: String| (Level::Warn, MessageKind::Log, vec![message.into()]);
 
-    fn print_value(value: JsValue) -> String {
-        match value {
-            JsValue::Undefined => "undefined".to_string(),
-            JsValue::Null => "null".to_string(),
-            JsValue::Bool(v) => v.to_string(),
-            JsValue::Int(v) => v.to_string(),
-            JsValue::Float(v) => v.to_string(),
-            JsValue::String(v) => v,
-            JsValue::Array(values) => {
-                let parts = values
-                    .into_iter()
-                    .map(print_value)
-                    .collect::<Vec<_>>()
-                    .join(", ");
-                format!("[{}]", parts)
+        let (level, kind, values) = match method {
+            "trace" => (Level::Trace, MessageKind::Log, args),
+            "debug" => (Level::Debug, MessageKind::Log, args),
+            "log" | "dirxml" => (Level::Log, MessageKind::Log, args),
+            "info" => (Level::Info, MessageKind::Log, args),
+            "warn" => (Level::Warn, MessageKind::Log, args),
+            "error" => (Level::Error, MessageKind::Log, args),
+            "assert" => (Level::Error, MessageKind::Assert, args),
+            "count" => {
+                let label = label(&mut args);
+                let count = state.counts.entry(label.clone()).or_insert(0);
+                *count += 1;
+                let values = vec![format!("{}: {}", label, count).into()];
+                let kind = MessageKind::Count {
+                    label,
+                    count: *count,
+                };
+                (Level::Info, kind, values)
             }
-            JsValue::Object(map) => {
-                let parts = map
-                    .into_iter()
-                    .map(|(key, value)| format!("{}: {}", key, print_value(value)))
-                    .collect::<Vec<_>>()
-                    .join(", ");
-                format!("{{{}}}", parts)
+            "countReset" => {
+                let label = label(&mut args);
+                match state.counts.get_mut(&label) {
+                    Some(count) => {
+                        *count = 0;
+                        (Level::Info, MessageKind::CountReset { label }, Vec::new())
+                    }
+                    None => warning(format!("Count for '{}' does not exist", label)),
+                }
             }
-            #[cfg(feature = "chrono")]
-            JsValue::Date(v) => v.to_string(),
-            #[cfg(feature = "bigint")]
-            JsValue::BigInt(v) => v.to_string(),
-            JsValue::__NonExhaustive => unreachable!(),
+            "time" => {
+                let label = label(&mut args);
+                if state.timers.contains_key(&label) {
+                    warning(format!("Timer '{}' already exists", label))
+                } else {
+                    state.timers.insert(label.clone(), Instant::now());
+                    (Level::Info, MessageKind::Time { label }, Vec::new())
+                }
+            }
+            "timeLog" | "timeEnd" => {
+                let label = label(&mut args);
+                let start = match method {
+                    "timeLog" => state.timers.get(&label).copied(),
+                    _ => state.timers.remove(&label),
+                };
+                match start {
+                    Some(start) => {
+                        let elapsed = start.elapsed();
+                        let mut values =
+                            vec![
+                                format!("{}: {:.3}ms", label, elapsed.as_secs_f64() * 1000.0)
+                                    .into(),
+                            ];
+                        let kind = if method == "timeLog" {
+                            values.extend(args);
+                            MessageKind::TimeLog { label, elapsed }
+                        } else {
+                            MessageKind::TimeEnd { label, elapsed }
+                        };
+                        (Level::Info, kind, values)
+                    }
+                    None => warning(format!("Timer '{}' does not exist", label)),
+                }
+            }
+            "group" | "groupCollapsed" => {
+                state.group_depth += 1;
+                let collapsed = method == "groupCollapsed";
+                (Level::Log, MessageKind::Group { collapsed }, args)
+            }
+            "groupEnd" => {
+                state.group_depth = state.group_depth.saturating_sub(1);
+                group_depth = state.group_depth;
+                (Level::Log, MessageKind::GroupEnd, Vec::new())
+            }
+            "table" => {
+                let columns = match args.pop() {
+                    Some(JsValue::Array(columns)) => Some(
+                        columns
+                            .into_iter()
+                            .filter_map(|column| column.into_string())
+                            .collect(),
+                    ),
+                    _ => None,
+                };
+                (Level::Log, MessageKind::Table { columns }, args)
+            }
+            "dir" => (Level::Log, MessageKind::Dir, args),
+            "clear" => {
+                state.group_depth = 0;
+                group_depth = 0;
+                (Level::Log, MessageKind::Clear, Vec::new())
+            }
+            _ => return,
+        };
+        drop(state);
+
+        let mut message = ConsoleMessage {
+            kind,
+            group_depth,
+            ..ConsoleMessage::new(level, values)
+        };
+        if let Some(frame) = frame {
+            message.set_location(&frame);
+        }
+        self.backend.message(message);
+    }
+}
+
+/// Whether a value of a lossy conversion contains a reference to an object
+/// from within itself.
+fn is_circular(value: &JsValue) -> bool {
+    match value {
+        JsValue::String(value) => value == CIRCULAR,
+        JsValue::Array(values) | JsValue::Set(values) => values.iter().any(is_circular),
+        JsValue::Object(map) => map.values().any(is_circular),
+        JsValue::Map(entries) => entries
+            .iter()
+            .any(|(key, value)| is_circular(key) || is_circular(value)),
+        _ => false,
+    }
+}
+
+/// A console backend that records messages, for example for assertions in
+/// tests.
+///
+/// Clones share the recorded messages.
+///
+/// ```rust
+/// use quick_js::{console::CaptureConsole, Context};
+///
+/// let console = CaptureConsole::new();
+/// let context = Context::builder().console(console.clone()).build().unwrap();
+///
+/// context.eval(" console.log('%s!', 'hi') ").unwrap();
+/// assert_eq!(console.messages()[0].text(), "hi!");
+/// ```
+#[derive(Clone, Default, Debug)]
+pub struct CaptureConsole {
+    messages: Arc<Mutex<Vec<ConsoleMessage>>>,
+}
+
+impl CaptureConsole {
+    /// Create a console without messages.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get a copy of the recorded messages.
+    pub fn messages(&self) -> Vec<ConsoleMessage> {
+        self.messages.lock().unwrap().clone()
+    }
+
+    /// Remove and return the recorded messages.
+    pub fn take(&self) -> Vec<ConsoleMessage> {
+        std::mem::take(&mut *self.messages.lock().unwrap())
+    }
+
+    /// Remove all recorded messages.
+    pub fn clear(&self) {
+        self.messages.lock().unwrap().clear();
+    }
+}
+
+impl ConsoleBackend for CaptureConsole {
+    fn log(&self, level: Level, values: Vec<JsValue>) {
+        self.message(ConsoleMessage::new(level, values));
+    }
+
+    fn message(&self, message: ConsoleMessage) {
+        self.messages.lock().unwrap().push(message);
+    }
+}
+
+/// A console backend that prints `warn`, `error` and `trace` messages to
+/// stderr and all other messages to stdout, like Node.
+///
+/// Messages inside groups are indented by two spaces per group.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct StdioConsole;
+
+impl ConsoleBackend for StdioConsole {
+    fn log(&self, level: Level, values: Vec<JsValue>) {
+        self.message(ConsoleMessage::new(level, values));
+    }
+
+    fn message(&self, message: ConsoleMessage) {
+        if message.values.is_empty() {
+            return;
         }
+        let text = format!("{}{}", "  ".repeat(message.group_depth), message.text());
+        // Errors are ignored, like for a closed stdout.
+        let _ = match message.level {
+            Level::Warn | Level::Error | Level::Trace => writeln!(std::io::stderr(), "{}", text),
+            _ => writeln!(std::io::stdout(), "{}", text),
+        };
     }
+}
+
+#[cfg(feature = "log")]
+mod log {
+    use super::{ConsoleMessage, JsValue, Level};
+
+    /// A console implementation that logs messages via the `log` crate.
+    ///
+    /// Messages inside groups are indented by two spaces per group.
+    ///
+    /// Only available with the `log` feature.
+    pub struct LogConsole;
 
     impl super::ConsoleBackend for LogConsole {
         fn log(&self, level: Level, values: Vec<JsValue>) {
-            if values.is_empty() {
+            self.message(ConsoleMessage::new(level, values));
+        }
+
+        fn message(&self, message: ConsoleMessage) {
+            if message.values.is_empty() {
                 return;
             }
-            let log_level = match level {
+            let log_level = match message.level {
                 Level::Trace => log::Level::Trace,
                 Level::Debug => log::Level::Debug,
                 Level::Log => log::Level::Info,
@@ -123,16 +525,62 @@ mod log {
                 Level::Error => log::Level::Error,
             };
 
-            let msg = values
-                .into_iter()
-                .map(print_value)
-                .collect::<Vec<_>>()
-                .join(" ");
-
-            log::log!(log_level, "{}", msg);
+            let indent = "  ".repeat(message.group_depth);
+            log::log!(log_level, "{}{}", indent, message.text());
         }
     }
 }
 
 #[cfg(feature = "log")]
 pub use self::log::LogConsole;
+
+#[cfg(feature = "tracing")]
+mod tracing {
+    use super::{ConsoleMessage, JsValue, Level};
+
+    /// A console implementation that emits messages as `tracing` events.
+    ///
+    /// Events have the target `quick_js::console` and carry the group depth
+    /// and the location of the console call as fields.
+    ///
+    /// Only available with the `tracing` feature.
+    pub struct TracingConsole;
+
+    impl super::ConsoleBackend for TracingConsole {
+        fn log(&self, level: Level, values: Vec<JsValue>) {
+            self.message(ConsoleMessage::new(level, values));
+        }
+
+        fn message(&self, message: ConsoleMessage) {
+            if message.values.is_empty() {
+                return;
+            }
+
+            macro_rules! event {
+                ($level:expr) => {
+                    tracing::event!(
+                        target: "quick_js::console",
+                        $level,
+                        group_depth = message.group_depth,
+                        filename = message.filename.as_deref(),
+                        line = message.line,
+                        function = message.function.as_deref(),
+                        "{}",
+                        message.text()
+                    )
+                };
+            }
+
+            match message.level {
+                Level::Trace => event!(tracing::Level::TRACE),
+                Level::Debug => event!(tracing::Level::DEBUG),
+                Level::Log | Level::Info => event!(tracing::Level::INFO),
+                Level::Warn => event!(tracing::Level::WARN),
+                Level::Error => event!(tracing::Level::ERROR),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+pub use self::tracing::TracingConsole;
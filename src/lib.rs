@@ -34,23 +34,40 @@
 
 #![deny(missing_docs)]
 
+pub mod allocator;
 mod bindings;
 mod callback;
+mod class;
 pub mod console;
+mod namespace;
+mod pool;
+mod threaded;
 mod value;
+mod worker;
 
 #[cfg(test)]
 mod tests;
 
-use std::{convert::TryFrom, error, fmt};
+use std::{
+    convert::TryFrom,
+    error, fmt,
+    rc::Rc,
+    sync::{Arc, Mutex},
+};
 
 pub use self::{
-    callback::{Arguments, Callback},
+    callback::{Arguments, CallContext, Callback, JsError, JsErrorKind, Rest},
+    class::Class,
+    namespace::{Accessor, Namespace, PropertyFlags},
+    pool::{ContextPool, ContextPoolBuilder, PooledContext},
+    threaded::{Reply, ThreadedContext, ThreadedError},
     value::*,
+    worker::{WorkerPool, WorkerPoolBuilder},
 };
 
 /// Error on Javascript execution.
 #[derive(PartialEq, Debug)]
+#[allow(clippy::manual_non_exhaustive)]
 pub enum ExecutionError {
     /// Code to be executed contained zero-bytes.
     InputWithZeroBytes,
@@ -90,6 +107,7 @@ impl From<ValueError> for ExecutionError {
 
 /// Error on context creation.
 #[derive(Debug)]
+#[allow(clippy::manual_non_exhaustive)]
 pub enum ContextError {
     /// Runtime could not be created.
     RuntimeCreationFailed,
@@ -115,12 +133,44 @@ impl fmt::Display for ContextError {
 
 impl error::Error for ContextError {}
 
+/// A script that is evaluated in every context built by a [ContextBuilder].
+struct Prelude {
+    code: String,
+    filename: String,
+    /// Bytecode compiled on the first build, shared by all builder clones.
+    bytecode: Mutex<Option<Arc<[u8]>>>,
+}
+
+impl Prelude {
+    /// Get the bytecode, compiling it with the given context if necessary.
+    fn bytecode(&self, wrapper: &bindings::ContextWrapper) -> Result<Arc<[u8]>, ExecutionError> {
+        let mut cached = self.bytecode.lock().unwrap();
+        if let Some(bytecode) = &*cached {
+            return Ok(bytecode.clone());
+        }
+        let bytecode: Arc<[u8]> = wrapper.compile_bytecode(&self.code, &self.filename)?.into();
+        *cached = Some(bytecode.clone());
+        Ok(bytecode)
+    }
+}
+
 /// A builder for [Context](Context).
 ///
 /// Create with [Context::builder](Context::builder).
+///
+/// A builder can be cloned to build multiple contexts with the same
+/// configuration. Clones share the console backend, the allocator and the
+/// compiled preludes.
+///
+/// A builder is `Send`, so it can be passed to the thread that will own the
+/// context.
+#[derive(Clone)]
 pub struct ContextBuilder {
     memory_limit: Option<usize>,
-    console_backend: Option<Box<dyn console::ConsoleBackend>>,
+    console_backend: Option<Arc<dyn console::ConsoleBackend>>,
+    allocator: Option<Arc<dyn allocator::Allocator>>,
+    preludes: Vec<Arc<Prelude>>,
+    conversion_limits: bindings::ConversionLimits,
 }
 
 impl ContextBuilder {
@@ -128,20 +178,63 @@ impl ContextBuilder {
         Self {
             memory_limit: None,
             console_backend: None,
+            allocator: None,
+            preludes: Vec::new(),
+            conversion_limits: bindings::ConversionLimits::default(),
         }
     }
 
     /// Sets the memory limit of the Javascript runtime (in bytes).
     ///
     /// If the limit is exceeded, methods like `eval` will return
-    /// a `Err(ExecutionError::Exception(JsValue::Null))`
-    // TODO: investigate why we don't get a proper exception message here.
+    /// `Err(ExecutionError::OutOfMemory)`.
+    ///
+    /// ```rust
+    /// use quick_js::{Context, ExecutionError};
+    ///
+    /// let context = Context::builder().memory_limit(1_000_000).build().unwrap();
+    /// let res = context.eval(" 'abc'.repeat(1_000_000) ");
+    /// assert_eq!(res, Err(ExecutionError::OutOfMemory));
+    /// ```
     pub fn memory_limit(self, max_bytes: usize) -> Self {
         let mut s = self;
         s.memory_limit = Some(max_bytes);
         s
     }
 
+    /// Sets the maximum nesting depth of values converted from Javascript to
+    /// [JsValue]. Defaults to 128.
+    ///
+    /// Deeper values fail to convert with [ValueError::DepthLimitExceeded].
+    /// Values that reference themselves always fail with
+    /// [ValueError::CyclicValue].
+    pub fn max_value_depth(mut self, max_depth: usize) -> Self {
+        self.conversion_limits.max_depth = max_depth;
+        self
+    }
+
+    /// Sets the maximum number of values, including all nested values, in a
+    /// value converted from Javascript to [JsValue]. Defaults to 1,000,000.
+    ///
+    /// Larger values fail to convert with [ValueError::SizeLimitExceeded].
+    /// Objects that are referenced more than once count towards the limit
+    /// for every reference, since each reference becomes a separate copy.
+    ///
+    /// ```rust
+    /// use quick_js::{Context, ExecutionError, ValueError};
+    ///
+    /// let context = Context::builder().max_value_size(100).build().unwrap();
+    /// let res = context.eval(" new Array(1000).fill(0) ");
+    /// assert_eq!(
+    ///     res,
+    ///     Err(ExecutionError::Conversion(ValueError::SizeLimitExceeded))
+    /// );
+    /// ```
+    pub fn max_value_size(mut self, max_size: usize) -> Self {
+        self.conversion_limits.max_size = Some(max_size);
+        self
+    }
+
     /// Set a console handler that will proxy `console.{log,trace,debug,...}`
     /// calls.
     ///
@@ -152,16 +245,88 @@ impl ContextBuilder {
     where
         B: console::ConsoleBackend,
     {
-        self.console_backend = Some(Box::new(backend));
+        self.console_backend = Some(Arc::new(backend));
+        self
+    }
+
+    /// Set a custom allocator that will be used for all memory allocations
+    /// of the Javascript runtime.
+    ///
+    /// Defaults to the [allocator::SystemAllocator].
+    /// See the [allocator] module for available allocators.
+    ///
+    /// ```rust
+    /// use quick_js::{allocator::SharedCountingAllocator, Context};
+    ///
+    /// let allocator = SharedCountingAllocator::new();
+    /// let context = Context::builder()
+    ///     .allocator(allocator.clone())
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let stats = allocator.stats();
+    /// assert!(stats.current_bytes > 0);
+    /// ```
+    pub fn allocator<A>(mut self, allocator: A) -> Self
+    where
+        A: allocator::Allocator,
+    {
+        self.allocator = Some(Arc::new(allocator));
+        self
+    }
+
+    /// Add a script that is evaluated in every built context, for example a
+    /// library bundle.
+    ///
+    /// The script is compiled to bytecode only once, on the first build.
+    /// Clones of this builder, like the template of a [ContextPool], share
+    /// the compiled bytecode.
+    /// Preludes are evaluated again when a context is reset.
+    ///
+    /// ```rust
+    /// let context = quick_js::Context::builder()
+    ///     .prelude("function greet(name) { return 'Hello ' + name; }", "lib.js")
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let value = context.eval_as::<String>(" greet('JS') ").unwrap();
+    /// assert_eq!(value, "Hello JS");
+    /// ```
+    pub fn prelude(mut self, code: impl Into<String>, filename: impl Into<String>) -> Self {
+        self.preludes.push(Arc::new(Prelude {
+            code: code.into(),
+            filename: filename.into(),
+            bytecode: Mutex::new(None),
+        }));
+        self
+    }
+
+    /// Add a prelude that was already compiled to bytecode.
+    fn prelude_bytecode(mut self, bytecode: Arc<[u8]>) -> Self {
+        self.preludes.push(Arc::new(Prelude {
+            code: String::new(),
+            filename: String::new(),
+            bytecode: Mutex::new(Some(bytecode)),
+        }));
         self
     }
 
     /// Finalize the builder and build a JS Context.
     pub fn build(self) -> Result<Context, ContextError> {
-        let wrapper = bindings::ContextWrapper::new(self.memory_limit)?;
+        let mut wrapper =
+            bindings::ContextWrapper::with_allocator(self.memory_limit, self.allocator)?;
+        wrapper.set_conversion_limits(self.conversion_limits);
         if let Some(be) = self.console_backend {
             wrapper.set_console(be).map_err(ContextError::Execution)?;
         }
+        for prelude in &self.preludes {
+            let bytecode = prelude
+                .bytecode(&wrapper)
+                .map_err(ContextError::Execution)?;
+            wrapper
+                .add_prelude(bytecode)
+                .map_err(ContextError::Execution)?;
+        }
         Ok(Context::from_wrapper(wrapper))
     }
 }
@@ -204,19 +369,75 @@ impl Context {
 
     /// Reset the Javascript engine.
     ///
-    /// All state and callbacks will be removed.
+    /// All Javascript state will be removed.
+    /// The console, callbacks added with [Context::add_callback] and preludes
+    /// are installed again, so the context behaves like a freshly built one.
+    ///
+    /// ```rust
+    /// use quick_js::Context;
+    /// let context = Context::new().unwrap();
+    /// context.add_callback("double", |x: i32| x * 2).unwrap();
+    /// context.eval(" var x = 1; ").unwrap();
+    ///
+    /// let context = context.reset().unwrap();
+    /// assert!(context.eval(" x ").is_err());
+    /// assert_eq!(context.eval_as::<i32>(" double(2) ").unwrap(), 4);
+    /// ```
     pub fn reset(self) -> Result<Self, ContextError> {
         let wrapper = self.wrapper.reset()?;
         Ok(Self { wrapper })
     }
 
+    /// Take a snapshot of the callbacks, host objects and user data, to
+    /// restore them with [Context::reset_to].
+    pub(crate) fn snapshot(&self) -> bindings::Snapshot {
+        self.wrapper.snapshot()
+    }
+
+    /// Reset the Javascript engine like [Context::reset], and restore the
+    /// callbacks, host objects and user data of a snapshot.
+    pub(crate) fn reset_to(self, snapshot: &bindings::Snapshot) -> Result<Self, ContextError> {
+        let wrapper = self.wrapper.reset_to(snapshot)?;
+        Ok(Self { wrapper })
+    }
+
+    /// Returns the number of bytes currently allocated by the Javascript
+    /// runtime.
+    ///
+    /// ```rust
+    /// let context = quick_js::Context::new().unwrap();
+    /// assert!(context.memory_usage() > 0);
+    /// ```
+    pub fn memory_usage(&self) -> usize {
+        self.wrapper.memory_usage()
+    }
+
+    /// Returns the current and peak memory usage of the Javascript runtime.
+    ///
+    /// Unlike the statistics of a [allocator::SharedCountingAllocator], these
+    /// only count the allocations of this context, whichever allocator is
+    /// used.
+    ///
+    /// ```rust
+    /// let context = quick_js::Context::new().unwrap();
+    /// context.eval(" 'abc'.repeat(10000) ").unwrap();
+    ///
+    /// let stats = context.allocation_stats();
+    /// assert!(stats.peak_bytes >= stats.current_bytes);
+    /// assert!(stats.peak_bytes > 30_000);
+    /// ```
+    pub fn allocation_stats(&self) -> allocator::AllocationStats {
+        self.wrapper.allocation_stats()
+    }
+
     /// Evaluates Javascript code and returns the value of the final expression.
     ///
     /// **Promises**:
     /// If the evaluated code returns a Promise, the event loop
     /// will be executed until the promise is finished. The final value of
     /// the promise will be returned, or a `ExecutionError::Exception` if the
-    /// promise failed.
+    /// promise failed. A promise that is still pending when no jobs are left
+    /// fails with `ExecutionError::Internal`.
     ///
     /// ```rust
     /// use quick_js::{Context, JsValue};
@@ -252,7 +473,8 @@ impl Context {
     /// If the evaluated code returns a Promise, the event loop
     /// will be executed until the promise is finished. The final value of
     /// the promise will be returned, or a `ExecutionError::Exception` if the
-    /// promise failed.
+    /// promise failed. A promise that is still pending when no jobs are left
+    /// fails with `ExecutionError::Internal`.
     ///
     /// ```rust
     /// use quick_js::{Context};
@@ -310,7 +532,8 @@ impl Context {
     /// If the evaluated code returns a Promise, the event loop
     /// will be executed until the promise is finished. The final value of
     /// the promise will be returned, or a `ExecutionError::Exception` if the
-    /// promise failed.
+    /// promise failed. A promise that is still pending when no jobs are left
+    /// fails with `ExecutionError::Internal`.
     ///
     /// ```rust
     /// use quick_js::{Context, JsValue};
@@ -343,8 +566,9 @@ impl Context {
     /// Add a global JS function that is backed by a Rust function or closure.
     ///
     /// The callback must satisfy several requirements:
-    /// * accepts 0 - 5 arguments
+    /// * accepts 0 - 8 arguments, optionally followed by a [Rest] argument
     /// * each argument must be convertible from a JsValue
+    /// * trailing `Option<T>` arguments can be omitted by the caller
     /// * must return a value
     /// * the return value must either:
     ///   - be convertible to JsValue
@@ -373,4 +597,129 @@ impl Context {
     ) -> Result<(), ExecutionError> {
         self.wrapper.add_callback(name, callback)
     }
+
+    /// Store a value of type `T` on the context, replacing and returning the
+    /// previous value of that type.
+    ///
+    /// Callbacks read the value with [CallContext::data].
+    /// User data is kept when the context is reset, but contexts returned to
+    /// a [ContextPool] get back the user data they had when they were built.
+    pub fn set_user_data<T: 'static>(&self, value: T) -> Option<Rc<T>> {
+        self.wrapper.state.set_user_data(value)
+    }
+
+    /// Get the value of type `T` stored with [Context::set_user_data].
+    pub fn user_data<T: 'static>(&self) -> Option<Rc<T>> {
+        self.wrapper.state.user_data()
+    }
+
+    /// Start building a namespace object at a dot separated path below the
+    /// global object, like `host.fs`.
+    ///
+    /// Objects on the path are created if they don't exist yet.
+    /// The namespace is installed with [Namespace::install], and installed
+    /// again when the context is reset.
+    ///
+    /// ```rust
+    /// use quick_js::Context;
+    /// let context = Context::new().unwrap();
+    ///
+    /// context
+    ///     .namespace("host.fs")
+    ///     .function("read", |path: String| format!("contents of {}", path))
+    ///     .constant("SEP", "/")
+    ///     .frozen()
+    ///     .install()
+    ///     .unwrap();
+    ///
+    /// let output = context.eval_as::<String>(" host.fs.read('a' + host.fs.SEP + 'b') ").unwrap();
+    /// assert_eq!(output, "contents of a/b");
+    /// ```
+    pub fn namespace(&self, path: &str) -> Namespace<'_> {
+        Namespace::new(self, path)
+    }
+
+    /// Define a property backed by a Rust getter and setter on the object at
+    /// a dot separated path. An empty path is the global object.
+    ///
+    /// Objects on the path are created if they don't exist yet.
+    /// The property is defined again when the context is reset.
+    /// See [Accessor] for an example.
+    pub fn define_property(
+        &self,
+        obj_path: &str,
+        name: &str,
+        accessor: Accessor,
+    ) -> Result<(), ExecutionError> {
+        let mut object = bindings::HostObject::new(obj_path);
+        accessor.add_to(self, &mut object, name);
+        self.wrapper.add_host_object(object)
+    }
+
+    /// Define a data property with the given flags on the object at a dot
+    /// separated path. An empty path is the global object.
+    ///
+    /// The property is defined again when the context is reset.
+    ///
+    /// ```rust
+    /// use quick_js::{Context, PropertyFlags};
+    /// let context = Context::new().unwrap();
+    ///
+    /// context.define_value("", "VERSION", "1.0", PropertyFlags::READ_ONLY).unwrap();
+    /// let output = context.eval_as::<String>(" VERSION = '2.0'; VERSION ").unwrap();
+    /// assert_eq!(output, "1.0");
+    /// ```
+    pub fn define_value(
+        &self,
+        obj_path: &str,
+        name: &str,
+        value: impl Into<JsValue>,
+        flags: PropertyFlags,
+    ) -> Result<(), ExecutionError> {
+        let mut object = bindings::HostObject::new(obj_path);
+        object.add_value(name, value.into(), Some(flags.to_raw()));
+        self.wrapper.add_host_object(object)
+    }
+
+    /// Define a property whose value is computed by a Rust function or
+    /// closure on first access.
+    ///
+    /// The property is then replaced by the computed value, so the function
+    /// is called at most once per context.
+    ///
+    /// ```rust
+    /// use quick_js::{Context, JsValue};
+    /// let context = Context::new().unwrap();
+    ///
+    /// context
+    ///     .define_lazy("", "config", || {
+    ///         let mut config = std::collections::HashMap::new();
+    ///         config.insert("debug", true);
+    ///         config
+    ///     })
+    ///     .unwrap();
+    /// assert!(context.eval_as::<bool>(" config === config && config.debug ").unwrap());
+    /// ```
+    pub fn define_lazy<F>(
+        &self,
+        obj_path: &str,
+        name: &str,
+        init: impl Callback<F> + 'static,
+    ) -> Result<(), ExecutionError> {
+        let mut object = bindings::HostObject::new(obj_path);
+        object.add_lazy(&self.wrapper, name, init);
+        self.wrapper.add_host_object(object)
+    }
+
+    /// Define a class of a Rust value type on the object at a dot separated
+    /// path. An empty path is the global object.
+    ///
+    /// The class is defined again when the context is reset.
+    /// See [Class] for an example.
+    pub fn class<T>(&self, obj_path: &str, name: &str) -> Class<'_, T>
+    where
+        T: TryFrom<JsValue> + Into<JsValue> + 'static,
+    {
+        Class::new(self, obj_path, name)
+    }
 }
@@ -0,0 +1,197 @@
+use libquickjs_sys as q;
+
+use crate::{
+    bindings::HostObject, callback::BoxedCallback, Callback, Context, ExecutionError, JsValue,
+};
+
+/// Builder for an object of host functions and constants.
+///
+/// Created with [Context::namespace].
+/// By default, properties are writable, enumerable and configurable, like
+/// globals added with [Context::add_callback].
+#[must_use = "the namespace is only installed by calling `install`"]
+pub struct Namespace<'a> {
+    context: &'a Context,
+    object: HostObject,
+}
+
+impl<'a> Namespace<'a> {
+    pub(crate) fn new(context: &'a Context, path: &str) -> Self {
+        Self {
+            context,
+            object: HostObject::new(path),
+        }
+    }
+
+    /// Add a function that is backed by a Rust function or closure.
+    ///
+    /// See [Context::add_callback] for the requirements of the callback.
+    pub fn function<F>(mut self, name: &str, callback: impl Callback<F> + 'static) -> Self {
+        self.object
+            .add_function(&self.context.wrapper, name, callback);
+        self
+    }
+
+    /// Add a constant value.
+    pub fn constant(mut self, name: &str, value: impl Into<JsValue>) -> Self {
+        self.object.add_value(name, value.into(), None);
+        self
+    }
+
+    /// Make the properties read-only, so they can not be assigned or deleted.
+    pub fn read_only(mut self) -> Self {
+        self.object.read_only = true;
+        self
+    }
+
+    /// Hide the properties from enumeration, like `Object.keys`.
+    pub fn non_enumerable(mut self) -> Self {
+        self.object.enumerable = false;
+        self
+    }
+
+    /// Freeze the namespace object with `Object.freeze`, so no properties
+    /// can be added, changed or removed.
+    pub fn frozen(mut self) -> Self {
+        self.object.frozen = true;
+        self
+    }
+
+    /// Install the namespace in the context.
+    ///
+    /// Fails if the path is invalid or a property could not be defined, for
+    /// example because the object on the path is frozen.
+    pub fn install(self) -> Result<(), ExecutionError> {
+        self.context.wrapper.add_host_object(self.object)
+    }
+}
+
+/// Attributes of a data property defined with [Context::define_value].
+///
+/// The default flags are all `true`, like for a property created by
+/// assignment.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct PropertyFlags {
+    /// The value can be changed by assignment.
+    pub writable: bool,
+    /// The property shows up in enumeration, like `Object.keys`.
+    pub enumerable: bool,
+    /// The property can be deleted and its attributes changed.
+    pub configurable: bool,
+}
+
+impl PropertyFlags {
+    /// A constant that can not be changed or deleted, but is enumerable.
+    pub const READ_ONLY: Self = Self {
+        writable: false,
+        enumerable: true,
+        configurable: false,
+    };
+
+    pub(crate) fn to_raw(self) -> i32 {
+        let mut flags = 0;
+        if self.writable {
+            flags |= q::JS_PROP_WRITABLE;
+        }
+        if self.enumerable {
+            flags |= q::JS_PROP_ENUMERABLE;
+        }
+        if self.configurable {
+            flags |= q::JS_PROP_CONFIGURABLE;
+        }
+        flags as i32
+    }
+}
+
+impl Default for PropertyFlags {
+    fn default() -> Self {
+        Self {
+            writable: true,
+            enumerable: true,
+            configurable: true,
+        }
+    }
+}
+
+/// A property backed by a Rust getter and setter, defined with
+/// [Context::define_property].
+///
+/// The getter is called without arguments, and the setter with the assigned
+/// value. Without a setter, assignments are ignored, or throw in strict mode.
+/// The property is enumerable and configurable by default.
+///
+/// ```rust
+/// use std::sync::{Arc, Mutex};
+/// use quick_js::{Accessor, Context};
+///
+/// let context = Context::new().unwrap();
+/// let level = Arc::new(Mutex::new(1));
+///
+/// let (get_level, set_level) = (level.clone(), level.clone());
+/// let accessor = Accessor::new()
+///     .get(move || *get_level.lock().unwrap())
+///     .set(move |value: i32| *set_level.lock().unwrap() = value);
+/// context.define_property("", "level", accessor).unwrap();
+///
+/// context.eval(" level = level + 2 ").unwrap();
+/// assert_eq!(*level.lock().unwrap(), 3);
+/// ```
+pub struct Accessor {
+    get: Option<BoxedCallback>,
+    set: Option<BoxedCallback>,
+    enumerable: bool,
+    configurable: bool,
+}
+
+impl Accessor {
+    /// Create an accessor without getter and setter.
+    pub fn new() -> Self {
+        Self {
+            get: None,
+            set: None,
+            enumerable: true,
+            configurable: true,
+        }
+    }
+
+    /// Set the getter.
+    pub fn get<F>(mut self, getter: impl Callback<F> + 'static) -> Self {
+        self.get = Some(BoxedCallback::new(getter));
+        self
+    }
+
+    /// Set the setter.
+    pub fn set<F>(mut self, setter: impl Callback<F> + 'static) -> Self {
+        self.set = Some(BoxedCallback::new(setter));
+        self
+    }
+
+    /// Set whether the property shows up in enumeration.
+    pub fn enumerable(mut self, enumerable: bool) -> Self {
+        self.enumerable = enumerable;
+        self
+    }
+
+    /// Set whether the property can be deleted and redefined.
+    pub fn configurable(mut self, configurable: bool) -> Self {
+        self.configurable = configurable;
+        self
+    }
+
+    pub(crate) fn add_to(self, context: &Context, object: &mut HostObject, name: &str) {
+        let mut flags = 0;
+        if self.enumerable {
+            flags |= q::JS_PROP_ENUMERABLE;
+        }
+        if self.configurable {
+            flags |= q::JS_PROP_CONFIGURABLE;
+        }
+        object.add_accessor(&context.wrapper, name, self.get, self.set, flags as i32);
+    }
+}
+
+impl Default for Accessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
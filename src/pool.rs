@@ -0,0 +1,382 @@
+use std::{cell::RefCell, ops::Deref};
+
+use crate::{bindings::Snapshot, Context, ContextBuilder, ContextError, ExecutionError};
+
+type InitFn = dyn Fn(&Context) -> Result<(), ExecutionError>;
+
+struct PoolEntry {
+    context: Context,
+    /// The state of the context after it was built and initialized.
+    snapshot: Snapshot,
+    uses: usize,
+}
+
+/// A pool of pre-built contexts.
+///
+/// Building a context with a console and many callbacks is relatively
+/// expensive. A pool builds contexts from a [ContextBuilder] template ahead
+/// of time and hands out clean contexts on checkout.
+/// When a [PooledContext] is dropped, the context is reset like with
+/// [Context::reset] and returned to the pool. Callbacks, host objects and
+/// user data added while the context was checked out are dropped, so every
+/// checkout starts with the state of a freshly built context.
+///
+/// Like [Context], a pool must only be used from a single thread.
+///
+/// ```rust
+/// use quick_js::{Context, ContextPool};
+///
+/// let pool = ContextPool::builder(Context::builder())
+///     .size(2)
+///     .max_uses(100)
+///     .init(|ctx| ctx.add_callback("double", |x: i32| x * 2))
+///     .build()
+///     .unwrap();
+///
+/// {
+///     let ctx = pool.checkout().unwrap();
+///     ctx.eval(" var leaked = 1; ").unwrap();
+///     assert_eq!(ctx.eval_as::<i32>(" double(21) ").unwrap(), 42);
+/// }
+///
+/// // Returned contexts are reset.
+/// let ctx = pool.checkout().unwrap();
+/// assert!(ctx.eval(" leaked ").is_err());
+/// ```
+pub struct ContextPool {
+    template: ContextBuilder,
+    init: Option<Box<InitFn>>,
+    size: usize,
+    max_uses: Option<usize>,
+    max_memory: Option<usize>,
+    idle: RefCell<Vec<PoolEntry>>,
+}
+
+impl ContextPool {
+    /// Create a builder for a pool that builds its contexts from the given
+    /// template.
+    pub fn builder(template: ContextBuilder) -> ContextPoolBuilder {
+        ContextPoolBuilder {
+            template,
+            init: None,
+            size: 4,
+            max_uses: None,
+            max_memory: None,
+        }
+    }
+
+    /// Create a pool with `size` pre-built contexts and default settings.
+    pub fn new(template: ContextBuilder, size: usize) -> Result<Self, ContextError> {
+        Self::builder(template).size(size).build()
+    }
+
+    /// Take a clean context from the pool.
+    ///
+    /// If no idle context is available, a new one is built.
+    pub fn checkout(&self) -> Result<PooledContext<'_>, ContextError> {
+        let entry = self.idle.borrow_mut().pop();
+        let entry = match entry {
+            Some(entry) => entry,
+            None => self.build_entry()?,
+        };
+        Ok(PooledContext {
+            pool: self,
+            entry: Some(entry),
+        })
+    }
+
+    /// Returns the number of contexts that are ready for checkout.
+    pub fn idle_count(&self) -> usize {
+        self.idle.borrow().len()
+    }
+
+    fn build_entry(&self) -> Result<PoolEntry, ContextError> {
+        let context = self.template.clone().build()?;
+        if let Some(init) = &self.init {
+            init(&context).map_err(ContextError::Execution)?;
+        }
+        let snapshot = context.snapshot();
+        Ok(PoolEntry {
+            context,
+            snapshot,
+            uses: 0,
+        })
+    }
+
+    /// Reset a returned context and keep it, unless it should be evicted.
+    ///
+    /// An evicted context is replaced with a new one from the template, so
+    /// the pool keeps `size` contexts ready for checkout.
+    fn checkin(&self, entry: PoolEntry) {
+        if self.idle.borrow().len() >= self.size {
+            return;
+        }
+
+        let uses = entry.uses + 1;
+        let evict = self.max_uses.map(|max| uses >= max).unwrap_or(false)
+            || self
+                .max_memory
+                .map(|max| entry.context.memory_usage() > max)
+                .unwrap_or(false);
+        let PoolEntry {
+            context, snapshot, ..
+        } = entry;
+        let entry = if evict {
+            // Free the evicted runtime before building its replacement.
+            drop(context);
+            None
+        } else {
+            // A context that can not be reset is replaced as well.
+            context.reset_to(&snapshot).ok().map(|context| PoolEntry {
+                context,
+                snapshot,
+                uses,
+            })
+        };
+
+        // If building the replacement fails, the next checkout builds a
+        // context on demand.
+        if let Some(entry) = entry.or_else(|| self.build_entry().ok()) {
+            self.idle.borrow_mut().push(entry);
+        }
+    }
+}
+
+/// A builder for [ContextPool].
+///
+/// Create with [ContextPool::builder].
+pub struct ContextPoolBuilder {
+    template: ContextBuilder,
+    init: Option<Box<InitFn>>,
+    size: usize,
+    max_uses: Option<usize>,
+    max_memory: Option<usize>,
+}
+
+impl ContextPoolBuilder {
+    /// Set the number of contexts kept in the pool. Defaults to 4.
+    ///
+    /// All contexts are built when the pool is created.
+    /// If more contexts are checked out at the same time, additional contexts
+    /// are built on demand and dropped when returned.
+    pub fn size(mut self, size: usize) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// Replace a context with a new one after it was checked out `max_uses`
+    /// times.
+    pub fn max_uses(mut self, max_uses: usize) -> Self {
+        self.max_uses = Some(max_uses);
+        self
+    }
+
+    /// Replace a context with a new one if its runtime uses more than
+    /// `max_bytes` when it is returned to the pool.
+    ///
+    /// See [Context::memory_usage].
+    pub fn max_memory(mut self, max_bytes: usize) -> Self {
+        self.max_memory = Some(max_bytes);
+        self
+    }
+
+    /// Set a function that initializes every newly built context, for
+    /// example by adding callbacks.
+    ///
+    /// Callbacks added with [Context::add_callback] survive a reset, so the
+    /// function only runs once per context.
+    pub fn init<F>(mut self, init: F) -> Self
+    where
+        F: Fn(&Context) -> Result<(), ExecutionError> + 'static,
+    {
+        self.init = Some(Box::new(init));
+        self
+    }
+
+    /// Finalize the builder and pre-build the contexts.
+    pub fn build(self) -> Result<ContextPool, ContextError> {
+        let pool = ContextPool {
+            template: self.template,
+            init: self.init,
+            size: self.size,
+            max_uses: self.max_uses,
+            max_memory: self.max_memory,
+            idle: RefCell::new(Vec::with_capacity(self.size)),
+        };
+        for _ in 0..pool.size {
+            let entry = pool.build_entry()?;
+            pool.idle.borrow_mut().push(entry);
+        }
+        Ok(pool)
+    }
+}
+
+/// A context checked out from a [ContextPool].
+///
+/// Dereferences to [Context]. The context is reset and returned to the pool
+/// when this value is dropped.
+pub struct PooledContext<'a> {
+    pool: &'a ContextPool,
+    entry: Option<PoolEntry>,
+}
+
+impl<'a> Deref for PooledContext<'a> {
+    type Target = Context;
+
+    fn deref(&self) -> &Context {
+        &self.entry.as_ref().unwrap().context
+    }
+}
+
+impl<'a> Drop for PooledContext<'a> {
+    fn drop(&mut self) {
+        if let Some(entry) = self.entry.take() {
+            self.pool.checkin(entry);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use super::*;
+
+    #[test]
+    fn test_pool_prebuilds_contexts() {
+        let pool = ContextPool::new(Context::builder(), 3).unwrap();
+        assert_eq!(pool.idle_count(), 3);
+
+        let a = pool.checkout().unwrap();
+        let b = pool.checkout().unwrap();
+        assert_eq!(pool.idle_count(), 1);
+        drop(a);
+        drop(b);
+        assert_eq!(pool.idle_count(), 3);
+    }
+
+    #[test]
+    fn test_pool_overflow_contexts_are_dropped() {
+        let pool = ContextPool::new(Context::builder(), 1).unwrap();
+        let a = pool.checkout().unwrap();
+        let b = pool.checkout().unwrap();
+        assert_eq!(pool.idle_count(), 0);
+        drop(a);
+        drop(b);
+        assert_eq!(pool.idle_count(), 1);
+    }
+
+    #[test]
+    fn test_pool_resets_and_keeps_callbacks() {
+        let pool = ContextPool::builder(Context::builder())
+            .size(1)
+            .init(|ctx| ctx.add_callback("answer", || 42))
+            .build()
+            .unwrap();
+
+        pool.checkout().unwrap().eval(" var x = 1; ").unwrap();
+
+        let ctx = pool.checkout().unwrap();
+        assert!(ctx.eval(" x ").is_err());
+        assert_eq!(ctx.eval_as::<i32>(" answer() ").unwrap(), 42);
+    }
+
+    #[test]
+    fn test_pool_isolates_checkouts() {
+        let pool = ContextPool::builder(Context::builder())
+            .size(1)
+            .init(|ctx| {
+                ctx.set_user_data(String::from("init"));
+                ctx.namespace("api").constant("version", 1).install()?;
+                ctx.add_callback("answer", || 42)
+            })
+            .build()
+            .unwrap();
+
+        {
+            let ctx = pool.checkout().unwrap();
+            ctx.set_user_data(String::from("tenant"));
+            ctx.set_user_data(7_i32);
+            ctx.add_callback("answer", || 0).unwrap();
+            ctx.add_callback("leaked", || true).unwrap();
+            ctx.namespace("api")
+                .constant("version", 2)
+                .install()
+                .unwrap();
+            ctx.namespace("secret")
+                .constant("key", 1)
+                .install()
+                .unwrap();
+        }
+
+        let ctx = pool.checkout().unwrap();
+        assert_eq!(ctx.user_data::<String>().unwrap().as_str(), "init");
+        assert!(ctx.user_data::<i32>().is_none());
+        assert_eq!(ctx.eval_as::<i32>(" answer() ").unwrap(), 42);
+        assert_eq!(ctx.eval_as::<i32>(" api.version ").unwrap(), 1);
+        assert!(ctx
+            .eval_as::<bool>(" typeof leaked === 'undefined' && typeof secret === 'undefined' ")
+            .unwrap());
+    }
+
+    /// A pool whose init function counts the built contexts.
+    fn counting_pool(builder: ContextPoolBuilder) -> (ContextPool, Rc<Cell<usize>>) {
+        let builds = Rc::new(Cell::new(0));
+        let counter = builds.clone();
+        let pool = builder
+            .size(1)
+            .init(move |_| {
+                counter.set(counter.get() + 1);
+                Ok(())
+            })
+            .build()
+            .unwrap();
+        (pool, builds)
+    }
+
+    #[test]
+    fn test_pool_max_uses() {
+        let (pool, builds) = counting_pool(ContextPool::builder(Context::builder()).max_uses(2));
+        assert_eq!(builds.get(), 1);
+
+        drop(pool.checkout().unwrap());
+        assert_eq!(pool.idle_count(), 1);
+        assert_eq!(builds.get(), 1);
+
+        // The evicted context is replaced.
+        drop(pool.checkout().unwrap());
+        assert_eq!(pool.idle_count(), 1);
+        assert_eq!(builds.get(), 2);
+    }
+
+    #[test]
+    fn test_pool_max_memory() {
+        let fresh = Context::new().unwrap().memory_usage();
+        let (pool, builds) =
+            counting_pool(ContextPool::builder(Context::builder()).max_memory(fresh + 1_000_000));
+
+        drop(pool.checkout().unwrap());
+        assert_eq!(builds.get(), 1);
+
+        // The memory is checked before the reset frees the garbage.
+        {
+            let ctx = pool.checkout().unwrap();
+            ctx.eval(" var big = 'abc'.repeat(1_000_000); ").unwrap();
+        }
+        assert_eq!(pool.idle_count(), 1);
+        assert_eq!(builds.get(), 2);
+        assert!(pool.checkout().unwrap().eval(" big ").is_err());
+    }
+
+    #[test]
+    fn test_pool_overflow_contexts_are_not_replaced() {
+        let (pool, builds) = counting_pool(ContextPool::builder(Context::builder()).max_uses(1));
+        let a = pool.checkout().unwrap();
+        let b = pool.checkout().unwrap();
+        assert_eq!(builds.get(), 2);
+        drop(a);
+        drop(b);
+        assert_eq!(pool.idle_count(), 1);
+        assert_eq!(builds.get(), 3);
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::convert::TryFrom;
 
 use super::*;
 
@@ -49,19 +49,19 @@ fn test_eval_pass() {
     let obj_cases = vec![
         (
             r#" {"a": null, "b": undefined} "#,
-            Ok(JsValue::Object(HashMap::from_iter(vec![
+            Ok(JsValue::Object(IndexMap::from_iter(vec![
                 ("a".to_string(), JsValue::Null),
                 ("b".to_string(), JsValue::Undefined),
             ]))),
         ),
         (
             r#" {a: 1, b: true, c: {c1: false}} "#,
-            Ok(JsValue::Object(HashMap::from_iter(vec![
+            Ok(JsValue::Object(IndexMap::from_iter(vec![
                 ("a".to_string(), JsValue::Int(1)),
                 ("b".to_string(), JsValue::Bool(true)),
                 (
                     "c".to_string(),
-                    JsValue::Object(HashMap::from_iter(vec![(
+                    JsValue::Object(IndexMap::from_iter(vec![(
                         "c1".to_string(),
                         JsValue::Bool(false),
                     )])),
@@ -79,7 +79,7 @@ fn test_eval_pass() {
         assert_eq!(c.eval(&full_code), res,);
     }
 
-    assert_eq!(c.eval_as::<bool>("true").unwrap(), true,);
+    assert!(c.eval_as::<bool>("true").unwrap());
     assert_eq!(c.eval_as::<i32>("1 + 2").unwrap(), 3,);
 
     let value: String = c.eval_as("var x = 44; x.toString()").unwrap();
@@ -276,7 +276,7 @@ fn test_callback() {
     let c = Context::new().unwrap();
 
     c.add_callback("no_arguments", || true).unwrap();
-    assert_eq!(c.eval_as::<bool>("no_arguments()").unwrap(), true);
+    assert!(c.eval_as::<bool>("no_arguments()").unwrap());
 
     c.add_callback("cb1", |flag: bool| !flag).unwrap();
     assert_eq!(c.eval("cb1(true)").unwrap(), JsValue::Bool(false),);
@@ -371,11 +371,9 @@ fn test_callback_varargs() {
         );
     })
     .unwrap();
-    assert_eq!(
-        c.eval_as::<bool>("cb('hello', true, 100) === undefined")
-            .unwrap(),
-        true
-    );
+    assert!(c
+        .eval_as::<bool>("cb('hello', true, 100) === undefined")
+        .unwrap());
 
     // With return.
     c.add_callback("cb2", |args: Arguments| -> u32 {
@@ -407,63 +405,1426 @@ fn test_callback_invalid_argcount() {
     assert_eq!(
         c.eval(" cb(5) "),
         Err(ExecutionError::Exception(
-            "Invalid argument count: Expected 2, got 1".into()
+            "TypeError: Missing argument 2: expected i32".into()
+        )),
+    );
+    assert_eq!(
+        c.eval(" cb(5, 6, 7) "),
+        Err(ExecutionError::Exception(
+            "TypeError: Invalid argument count: Expected at most 2, got 3".into()
         )),
     );
 }
 
 #[test]
-fn memory_limit_exceeded() {
-    let c = Context::builder().memory_limit(100_000).build().unwrap();
+fn test_callback_integer_arguments() {
+    let c = Context::new().unwrap();
+
+    c.add_callback("id", |id: u64| id.to_string()).unwrap();
+    c.add_callback("half", |n: i32| n / 2).unwrap();
+
     assert_eq!(
-        c.eval("  'abc'.repeat(200_000) "),
-        Err(ExecutionError::OutOfMemory),
+        c.eval_as::<String>(" id(2 ** 53 - 1) ").unwrap(),
+        "9007199254740991"
+    );
+    assert_eq!(c.eval_as::<i32>(" half(8 / 2) ").unwrap(), 2);
+    assert_eq!(
+        c.eval(" id(2 ** 53) "),
+        Err(ExecutionError::Exception(
+            "TypeError: Invalid argument 1: expected u64, got number".into()
+        )),
+    );
+    assert_eq!(
+        c.eval(" id(-1) "),
+        Err(ExecutionError::Exception(
+            "TypeError: Invalid argument 1: expected u64, got number".into()
+        )),
     );
+    assert!(c.eval_as::<u64>(" 2 ** 60 ").is_err());
+    assert_eq!(c.eval_as::<u64>(" 2 ** 40 ").unwrap(), 1 << 40);
 }
 
 #[test]
-fn context_reset() {
+fn test_callback_invalid_argument_type() {
     let c = Context::new().unwrap();
-    c.eval(" var x = 123; ").unwrap();
-    c.add_callback("myCallback", || true).unwrap();
 
-    let c2 = c.reset().unwrap();
+    c.add_callback("cb", |a: i32, b: Vec<String>| a + b.len() as i32)
+        .unwrap();
 
-    // Check it still works.
     assert_eq!(
-        c2.eval_as::<String>(" 'abc'.repeat(2) ").unwrap(),
-        "abcabc".to_string(),
+        c.eval(" cb(1, 'x') "),
+        Err(ExecutionError::Exception(
+            "TypeError: Invalid argument 2: expected Vec<String>, got string".into()
+        )),
+    );
+    assert!(c
+        .eval_as::<bool>(" try { cb('x', []) } catch (e) { e instanceof TypeError } ")
+        .unwrap());
+}
+
+#[test]
+fn test_callback_optional_arguments() {
+    let c = Context::new().unwrap();
+
+    c.add_callback("greet", |name: String, greeting: Option<String>| {
+        format!("{} {}", greeting.unwrap_or_else(|| "Hello".into()), name)
+    })
+    .unwrap();
+
+    assert_eq!(c.eval_as::<String>(" greet('a') ").unwrap(), "Hello a");
+    assert_eq!(
+        c.eval_as::<String>(" greet('a', undefined) ").unwrap(),
+        "Hello a"
+    );
+    assert_eq!(c.eval_as::<String>(" greet('a', 'Hi') ").unwrap(), "Hi a");
+}
+
+#[test]
+fn test_callback_rest_arguments() {
+    let c = Context::new().unwrap();
+
+    c.add_callback("join", |sep: String, parts: Rest<String>| {
+        parts.into_vec().join(&sep)
+    })
+    .unwrap();
+    c.add_callback("count", |values: Rest<JsValue>| {
+        values.into_vec().len() as i32
+    })
+    .unwrap();
+
+    assert_eq!(c.eval_as::<String>(" join('-', 'a', 'b') ").unwrap(), "a-b");
+    assert_eq!(c.eval_as::<String>(" join('-') ").unwrap(), "");
+    assert_eq!(c.eval_as::<i32>(" count(1, 'a', null) ").unwrap(), 3);
+    assert_eq!(
+        c.eval(" join('-', 'a', 2) "),
+        Err(ExecutionError::Exception(
+            "TypeError: Invalid argument 3: expected String, got number".into()
+        )),
+    );
+}
+
+#[test]
+fn test_callback_many_arguments() {
+    let c = Context::new().unwrap();
+
+    c.add_callback(
+        "sum8",
+        |a: i32, b: i32, c: i32, d: i32, e: i32, f: i32, g: i32, h: i32| {
+            a + b + c + d + e + f + g + h
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        c.eval_as::<i32>(" sum8(1, 2, 3, 4, 5, 6, 7, 8) ").unwrap(),
+        36
+    );
+}
+
+#[test]
+fn test_callback_error_object() {
+    let c = Context::new().unwrap();
+
+    c.add_callback("fail", |kind: String| -> Result<i32, JsError> {
+        let err = match kind.as_str() {
+            "type" => JsError::type_error("bad type"),
+            "range" => JsError::range_error("bad range").property("max", 10),
+            _ => JsError::new("failed").code("E_FAIL"),
+        };
+        Err(err)
+    })
+    .unwrap();
+
+    let res = c
+        .eval_as::<Vec<String>>(
+            r#"
+        ['type', 'range', 'other'].map(kind => {
+            try {
+                fail(kind);
+            } catch (e) {
+                return [e instanceof Error, e.name, e.message, e.code, e.max].join(' ');
+            }
+        })
+    "#,
+        )
+        .unwrap();
+    assert_eq!(
+        res,
+        vec![
+            "true TypeError bad type  ",
+            "true RangeError bad range  10",
+            "true Error failed E_FAIL ",
+        ]
+    );
+
+    assert_eq!(
+        c.eval(" fail('type') "),
+        Err(ExecutionError::Exception("TypeError: bad type".into()))
+    );
+
+    assert_eq!(
+        JsError::type_error("bad type").to_string(),
+        "TypeError: bad type"
+    );
+    assert_eq!(JsError::new("").to_string(), "Error");
+    assert_eq!(JsError::from("thrown").to_string(), "thrown");
+}
+
+#[test]
+fn test_callback_error_value() {
+    let c = Context::new().unwrap();
+
+    c.add_callback("fail", || -> Result<i32, JsError> {
+        Err(JsError::value(vec![1, 2]))
+    })
+    .unwrap();
+    assert!(c
+        .eval_as::<bool>(" try { fail() } catch (e) { Array.isArray(e) } ")
+        .unwrap());
+
+    // Other errors are thrown as strings.
+    c.add_callback(
+        "fail_str",
+        || -> Result<i32, Box<dyn std::error::Error + Send + Sync>> {
+            "abc".parse::<i32>()?;
+            Ok(0)
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        c.eval(" fail_str() "),
+        Err(ExecutionError::Exception(
+            "invalid digit found in string".into()
+        ))
+    );
+}
+
+#[test]
+fn test_callback_panic() {
+    let c = Context::new().unwrap();
+
+    c.add_callback("boom", || -> i32 { panic!("boom {}", 1) })
+        .unwrap();
+    let res = c
+        .eval_as::<String>(" try { boom() } catch (e) { e.name + ': ' + e.message } ")
+        .unwrap();
+    assert_eq!(res, "InternalError: boom 1");
+
+    // The context is still usable.
+    assert_eq!(c.eval_as::<i32>(" 1 + 1 ").unwrap(), 2);
+}
+
+#[test]
+fn test_namespace() {
+    let c = Context::new().unwrap();
+
+    c.namespace("host.fs")
+        .function("read", |path: String| format!("read {}", path))
+        .constant("SEP", "/")
+        .install()
+        .unwrap();
+    c.namespace("host.net")
+        .function("fetch", |url: String| url.len() as i32)
+        .install()
+        .unwrap();
+
+    assert_eq!(
+        c.eval_as::<String>(" host.fs.read('a' + host.fs.SEP) ")
+            .unwrap(),
+        "read a/"
+    );
+    assert_eq!(c.eval_as::<i32>(" host.net.fetch('abc') ").unwrap(), 3);
+    assert_eq!(
+        c.eval_as::<Vec<String>>(" Object.keys(host) ").unwrap(),
+        vec!["fs".to_string(), "net".to_string()]
+    );
+    assert!(c.eval_as::<bool>(" typeof read === 'undefined' ").unwrap());
+
+    // Namespaces are installed again on reset.
+    let c = c.reset().unwrap();
+    assert_eq!(c.eval_as::<i32>(" host.net.fetch('ab') ").unwrap(), 2);
+}
+
+#[test]
+fn test_namespace_options() {
+    let c = Context::new().unwrap();
+
+    c.namespace("api")
+        .constant("VERSION", 2)
+        .read_only()
+        .non_enumerable()
+        .install()
+        .unwrap();
+    assert_eq!(
+        c.eval_as::<i32>(" api.VERSION = 3; delete api.VERSION; api.VERSION ")
+            .unwrap(),
+        2
+    );
+    assert!(c
+        .eval_as::<bool>(
+            " Object.keys(api).length === 0 && !Object.keys(globalThis).includes('api') "
+        )
+        .unwrap());
+
+    c.namespace("frozen")
+        .constant("a", 1)
+        .frozen()
+        .install()
+        .unwrap();
+    assert!(c
+        .eval_as::<bool>(" frozen.b = 1; Object.isFrozen(frozen) && frozen.b === undefined ")
+        .unwrap());
+    assert!(c.namespace("frozen").constant("c", 1).install().is_err());
+
+    assert!(c.namespace("a..b").install().is_err());
+}
+
+#[test]
+fn test_define_property_accessor() {
+    use std::sync::{Arc, Mutex};
+
+    let c = Context::new().unwrap();
+    let volume = Arc::new(Mutex::new(5));
+
+    let (get_volume, set_volume) = (volume.clone(), volume.clone());
+    c.define_property(
+        "settings",
+        "volume",
+        Accessor::new()
+            .get(move || *get_volume.lock().unwrap())
+            .set(move |value: i32| *set_volume.lock().unwrap() = value),
+    )
+    .unwrap();
+    c.define_property(
+        "settings",
+        "hidden",
+        Accessor::new().get(|| "secret").enumerable(false),
+    )
+    .unwrap();
+
+    assert_eq!(c.eval_as::<i32>(" settings.volume ").unwrap(), 5);
+    *volume.lock().unwrap() = 7;
+    assert_eq!(c.eval_as::<i32>(" settings.volume ").unwrap(), 7);
+    c.eval(" settings.volume = 9 ").unwrap();
+    assert_eq!(*volume.lock().unwrap(), 9);
+
+    assert_eq!(
+        c.eval_as::<Vec<String>>(" Object.keys(settings) ").unwrap(),
+        vec!["volume".to_string()]
+    );
+    assert_eq!(
+        c.eval_as::<String>(" settings.hidden = 'x'; settings.hidden ")
+            .unwrap(),
+        "secret"
+    );
+}
+
+#[test]
+fn test_define_value() {
+    let c = Context::new().unwrap();
+
+    c.define_value("", "VERSION", 2, PropertyFlags::READ_ONLY)
+        .unwrap();
+    c.define_value(
+        "app",
+        "internal",
+        true,
+        PropertyFlags {
+            enumerable: false,
+            ..PropertyFlags::default()
+        },
+    )
+    .unwrap();
+
+    assert_eq!(
+        c.eval_as::<i32>(" VERSION = 3; delete globalThis.VERSION; VERSION ")
+            .unwrap(),
+        2
+    );
+    assert!(c.eval(" 'use strict'; VERSION = 3; ").is_err());
+    assert!(c
+        .eval_as::<bool>(" app.internal && Object.keys(app).length === 0 ")
+        .unwrap());
+}
+
+#[test]
+fn test_define_lazy() {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    let c = Context::new().unwrap();
+    let calls = Arc::new(AtomicUsize::new(0));
+
+    let init_calls = calls.clone();
+    c.define_lazy("", "config", move || {
+        init_calls.fetch_add(1, Ordering::SeqCst);
+        let mut config = std::collections::HashMap::new();
+        config.insert("name", "app");
+        config
+    })
+    .unwrap();
+
+    assert_eq!(calls.load(Ordering::SeqCst), 0);
+    assert!(c
+        .eval_as::<bool>(" config === config && config.name === 'app' ")
+        .unwrap());
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    // The property is defined again on reset.
+    let c = c.reset().unwrap();
+    c.eval(" config = 1 ").unwrap();
+    assert_eq!(c.eval_as::<i32>(" config ").unwrap(), 1);
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[derive(Clone, Copy, PartialEq, PartialOrd, Debug)]
+struct Vector3 {
+    x: f64,
+    y: f64,
+    z: f64,
+}
+
+impl Vector3 {
+    fn new(x: f64, y: f64, z: f64) -> Self {
+        Self { x, y, z }
+    }
+}
+
+impl From<Vector3> for JsValue {
+    fn from(v: Vector3) -> Self {
+        let mut object = IndexMap::new();
+        object.insert("x".to_string(), JsValue::Float(v.x));
+        object.insert("y".to_string(), JsValue::Float(v.y));
+        object.insert("z".to_string(), JsValue::Float(v.z));
+        JsValue::Object(object)
+    }
+}
+
+impl TryFrom<JsValue> for Vector3 {
+    type Error = ValueError;
+
+    fn try_from(value: JsValue) -> Result<Self, ValueError> {
+        let object = std::collections::HashMap::<String, f64>::try_from(value)?;
+        let get = |name| object.get(name).copied().ok_or(ValueError::UnexpectedType);
+        Ok(Vector3::new(get("x")?, get("y")?, get("z")?))
+    }
+}
+
+impl std::ops::Add for Vector3 {
+    type Output = Vector3;
+
+    fn add(self, o: Vector3) -> Vector3 {
+        Vector3::new(self.x + o.x, self.y + o.y, self.z + o.z)
+    }
+}
+
+impl std::ops::Sub for Vector3 {
+    type Output = Vector3;
+
+    fn sub(self, o: Vector3) -> Vector3 {
+        Vector3::new(self.x - o.x, self.y - o.y, self.z - o.z)
+    }
+}
+
+impl std::ops::Mul<f64> for Vector3 {
+    type Output = Vector3;
+
+    fn mul(self, f: f64) -> Vector3 {
+        Vector3::new(self.x * f, self.y * f, self.z * f)
+    }
+}
+
+impl std::ops::Div<f64> for Vector3 {
+    type Output = Vector3;
+
+    fn div(self, f: f64) -> Vector3 {
+        Vector3::new(self.x / f, self.y / f, self.z / f)
+    }
+}
+
+impl std::ops::Neg for Vector3 {
+    type Output = Vector3;
+
+    fn neg(self) -> Vector3 {
+        Vector3::new(-self.x, -self.y, -self.z)
+    }
+}
+
+#[test]
+fn test_class() {
+    let c = Context::new().unwrap();
+    c.class::<Vector3>("geometry", "Vector3")
+        .constructor(|x: f64, y: f64, z: f64| Vector3::new(x, y, z))
+        .method("length", |v: Vector3| {
+            (v.x * v.x + v.y * v.y + v.z * v.z).sqrt()
+        })
+        .method("scale", |v: Vector3, f: f64| v * f)
+        .install()
+        .unwrap();
+
+    assert_eq!(
+        c.eval_as::<Vector3>(" new geometry.Vector3(1, 2, 3) ")
+            .unwrap(),
+        Vector3::new(1.0, 2.0, 3.0)
+    );
+    assert_eq!(
+        c.eval_as::<f64>(" new geometry.Vector3(3, 4, 0).length() ")
+            .unwrap(),
+        5.0
+    );
+    assert!(c
+        .eval_as::<bool>(
+            r#"
+            const v = new geometry.Vector3(1, 0, 0);
+            v instanceof geometry.Vector3
+                && geometry.Vector3.name === "Vector3"
+                && Object.keys(v).join() === "x,y,z"
+        "#
+        )
+        .unwrap());
+    assert_eq!(
+        c.eval(" geometry.Vector3(1, 2, 3) "),
+        Err(ExecutionError::Exception(
+            "TypeError: Class constructor Vector3 cannot be invoked without 'new'".into()
+        ))
+    );
+    assert_eq!(
+        c.eval(" new geometry.Vector3(1, 2, 'z') "),
+        Err(ExecutionError::Exception(
+            "TypeError: Invalid argument 3: expected f64, got string".into()
+        ))
+    );
+
+    // The class is defined again on reset.
+    let c = c.reset().unwrap();
+    assert_eq!(
+        c.eval_as::<Vector3>(" new geometry.Vector3(1, 1, 1).scale(2) ")
+            .unwrap(),
+        Vector3::new(2.0, 2.0, 2.0)
+    );
+}
+
+#[test]
+fn test_class_default_constructor() {
+    let c = Context::new().unwrap();
+    c.class::<Vector3>("", "Vector3").install().unwrap();
+    assert!(c
+        .eval_as::<bool>(" new Vector3({ x: 1, y: 2, z: 3 }) instanceof Vector3 ")
+        .unwrap());
+    assert!(c.eval(" new Vector3(1) ").is_err());
+}
+
+#[cfg(feature = "bignum")]
+#[test]
+fn test_class_operators() {
+    let c = Context::new().unwrap();
+    c.class::<Vector3>("", "Vector3")
+        .constructor(|x: f64, y: f64, z: f64| Vector3::new(x, y, z))
+        .operator_add()
+        .operator_sub()
+        .operator_neg()
+        .operator_eq()
+        .operator_lt()
+        .operator_mul_number()
+        .operator_div_number()
+        .install()
+        .unwrap();
+    c.eval(" var a = new Vector3(1, 2, 3), b = new Vector3(4, 5, 6); ")
+        .unwrap();
+
+    for (code, expected) in [
+        ("a + b", Vector3::new(5.0, 7.0, 9.0)),
+        ("b - a", Vector3::new(3.0, 3.0, 3.0)),
+        ("-a", Vector3::new(-1.0, -2.0, -3.0)),
+        ("a * 2", Vector3::new(2.0, 4.0, 6.0)),
+        ("2 * a", Vector3::new(2.0, 4.0, 6.0)),
+        ("b / 2", Vector3::new(2.0, 2.5, 3.0)),
+        ("(a + b) * 2 - a", Vector3::new(9.0, 12.0, 15.0)),
+    ] {
+        assert_eq!(c.eval_as::<Vector3>(code).unwrap(), expected, "{}", code);
+    }
+
+    assert!(c.eval_as::<bool>(" a == new Vector3(1, 2, 3) ").unwrap());
+    assert!(c
+        .eval_as::<bool>(" a != b && a !== new Vector3(1, 2, 3) ")
+        .unwrap());
+    assert!(c
+        .eval_as::<bool>(" a < b && b > a && a <= a && !(b <= a) ")
+        .unwrap());
+    assert!(c.eval_as::<bool>(" (a + b) instanceof Vector3 ").unwrap());
+    assert!(c.eval(" a + 1 ").is_err());
+
+    let c = c.reset().unwrap();
+    assert_eq!(
+        c.eval_as::<Vector3>(" new Vector3(1, 1, 1) + new Vector3(1, 1, 1) ")
+            .unwrap(),
+        Vector3::new(2.0, 2.0, 2.0)
     );
+}
+
+#[test]
+fn glue_builtins_replaced_by_script() {
+    let c = Context::new().unwrap();
+    c.eval(
+        r#"
+        Object.freeze = () => { throw new Error("freeze"); };
+        Object.defineProperty = () => { throw new Error("defineProperty"); };
+        Object.setPrototypeOf = () => { throw new Error("setPrototypeOf"); };
+        Object.keys = () => { throw new Error("keys"); };
+        Reflect.apply = () => { throw new Error("apply"); };
+        Function.prototype.bind = () => { throw new Error("bind"); };
+        if (typeof Operators !== "undefined") {
+            Operators.create = () => { throw new Error("create"); };
+        }
+    "#,
+    )
+    .unwrap();
+
+    c.namespace("frozen")
+        .constant("a", 1)
+        .frozen()
+        .install()
+        .unwrap();
+    c.define_lazy("lazy", "value", || 42).unwrap();
+    let class = c
+        .class::<Vector3>("", "Vector3")
+        .constructor(|x: f64, y: f64, z: f64| Vector3::new(x, y, z))
+        .method("scale", |v: Vector3, f: f64| v * f);
+    #[cfg(feature = "bignum")]
+    let class = class.operator_add();
+    class.install().unwrap();
+
+    assert!(c.eval_as::<bool>(" Object.isFrozen(frozen) ").unwrap());
+    assert_eq!(c.eval_as::<i32>(" lazy.value ").unwrap(), 42);
+    assert_eq!(
+        c.eval_as::<Vector3>(" new Vector3(1, 2, 3).scale(2) ")
+            .unwrap(),
+        Vector3::new(2.0, 4.0, 6.0)
+    );
+    #[cfg(feature = "bignum")]
+    assert_eq!(
+        c.eval_as::<Vector3>(" new Vector3(1, 2, 3) + new Vector3(1, 1, 1) ")
+            .unwrap(),
+        Vector3::new(2.0, 3.0, 4.0)
+    );
+}
+
+#[test]
+fn test_user_data() {
+    use std::cell::RefCell;
+
+    struct Counter(RefCell<i32>);
+
+    let c = Context::new().unwrap();
+    assert!(c.user_data::<Counter>().is_none());
+    assert!(c.set_user_data(Counter(RefCell::new(0))).is_none());
+    c.set_user_data("tenant-a".to_string());
+
+    c.add_callback("increment", |ctx: &CallContext, by: i32| {
+        let counter = ctx.data::<Counter>().unwrap();
+        *counter.0.borrow_mut() += by;
+        let value = *counter.0.borrow();
+        value
+    })
+    .unwrap();
+    c.add_callback("tenant", |ctx: &CallContext, args: Arguments| {
+        let tenant = ctx.data::<String>().unwrap();
+        format!("{}:{}", tenant, args.into_vec().len())
+    })
+    .unwrap();
+    c.add_callback("missing", |ctx: &CallContext, _rest: Rest<JsValue>| {
+        ctx.data::<u64>().is_none()
+    })
+    .unwrap();
+
+    assert_eq!(c.eval_as::<i32>(" increment(2); increment(3) ").unwrap(), 5);
+    assert_eq!(c.eval_as::<String>(" tenant(1, 2) ").unwrap(), "tenant-a:2");
+    assert!(c.eval_as::<bool>(" missing(1) ").unwrap());
+
+    // User data is kept on reset.
+    let c = c.reset().unwrap();
+    assert_eq!(c.eval_as::<i32>(" increment(1) ").unwrap(), 6);
+
+    let previous = c.set_user_data("tenant-b".to_string()).unwrap();
+    assert_eq!(*previous, "tenant-a");
+    assert_eq!(c.eval_as::<String>(" tenant() ").unwrap(), "tenant-b:0");
+}
+
+#[test]
+fn anonymous_callbacks_are_freed() {
+    use std::rc::Rc;
+
+    let c = Context::new().unwrap();
+    let captured = Rc::new(());
+
+    for _ in 0..100 {
+        let captured = captured.clone();
+        let f = c
+            .wrapper
+            .create_callback(move || Rc::strong_count(&captured) as i32)
+            .unwrap();
+        drop(f);
+    }
+    assert_eq!(Rc::strong_count(&captured), 1);
+
+    // A callback referenced from Javascript lives until the reference is gone.
+    let cb_captured = captured.clone();
+    let f = c
+        .wrapper
+        .create_callback(move || Rc::strong_count(&cb_captured) as i32)
+        .unwrap();
+    c.wrapper
+        .global()
+        .unwrap()
+        .set_property("f", f.into_value())
+        .unwrap();
+    assert_eq!(c.eval_as::<i32>(" f() ").unwrap(), 2);
+
+    c.eval(" delete globalThis.f ").unwrap();
+    assert_eq!(Rc::strong_count(&captured), 1);
+}
+
+#[test]
+fn named_callbacks_are_freed_with_context() {
+    use std::rc::Rc;
+
+    let captured = Rc::new(());
+    let c = Context::new().unwrap();
+    let cb_captured = captured.clone();
+    c.add_callback("cb", move || Rc::strong_count(&cb_captured) as i32)
+        .unwrap();
+
+    let c = c.reset().unwrap();
+    assert_eq!(c.eval_as::<i32>(" cb() ").unwrap(), 2);
+    drop(c);
+    assert_eq!(Rc::strong_count(&captured), 1);
+}
+
+#[test]
+fn memory_limit_exceeded() {
+    let c = Context::builder().memory_limit(100_000).build().unwrap();
+    assert_eq!(
+        c.eval("  'abc'.repeat(200_000) "),
+        Err(ExecutionError::OutOfMemory),
+    );
+}
+
+#[test]
+fn custom_allocator_counts_runtime_memory() {
+    use allocator::SharedCountingAllocator;
+
+    let alloc = SharedCountingAllocator::new();
+    let c = Context::builder().allocator(alloc.clone()).build().unwrap();
+
+    let initial = alloc.stats();
+    assert!(initial.current_bytes > 0);
+    assert!(initial.allocations > 0);
+
+    c.eval(" var s = 'abc'.repeat(100_000); ").unwrap();
+    let stats = alloc.stats();
+    assert!(stats.peak_bytes >= 300_000);
+    assert!(stats.peak_bytes >= stats.current_bytes);
+
+    drop(c);
+    let stats = alloc.stats();
+    assert_eq!(stats.current_bytes, 0);
+    assert_eq!(stats.allocations, 0);
+}
+
+#[test]
+fn allocation_stats_per_runtime() {
+    use allocator::SharedCountingAllocator;
+
+    let alloc = SharedCountingAllocator::new();
+    let builder = Context::builder().allocator(alloc.clone());
+    let a = builder.clone().build().unwrap();
+    let b = builder.build().unwrap();
+
+    a.eval(" var s = 'abc'.repeat(100_000); ").unwrap();
+    let stats_a = a.allocation_stats();
+    let stats_b = b.allocation_stats();
+    assert!(stats_a.peak_bytes >= 300_000);
+    assert!(stats_b.peak_bytes < 300_000);
+    assert_eq!(stats_a.current_bytes, a.memory_usage());
+    assert_eq!(
+        alloc.stats().current_bytes,
+        stats_a.current_bytes + stats_b.current_bytes
+    );
+    assert_eq!(
+        alloc.stats().allocations,
+        stats_a.allocations + stats_b.allocations
+    );
+}
+
+#[test]
+fn context_builder_is_send() {
+    let builder = Context::builder()
+        .allocator(allocator::SharedCountingAllocator::new())
+        .console(console::CaptureConsole::new())
+        .prelude("var x = 1;", "x.js");
+    let handle = std::thread::spawn(move || {
+        let c = builder.build().unwrap();
+        c.eval_as::<i32>("x").unwrap()
+    });
+    assert_eq!(handle.join().unwrap(), 1);
+}
+
+#[test]
+fn custom_allocator_memory_limit_exceeded() {
+    let c = Context::builder()
+        .allocator(allocator::SystemAllocator)
+        .memory_limit(100_000)
+        .build()
+        .unwrap();
+    assert_eq!(
+        c.eval("  'abc'.repeat(200_000) "),
+        Err(ExecutionError::OutOfMemory),
+    );
+}
+
+#[test]
+fn context_reset() {
+    let c = Context::new().unwrap();
+    c.eval(" var x = 123; ").unwrap();
+    c.add_callback("myCallback", || true).unwrap();
+
+    let c2 = c.reset().unwrap();
+
+    // Check it still works.
+    assert_eq!(
+        c2.eval_as::<String>(" 'abc'.repeat(2) ").unwrap(),
+        "abcabc".to_string(),
+    );
+
+    // Check old state is gone.
+    let err_msg = c2.eval(" x ").unwrap_err().to_string();
+    assert!(err_msg.contains("ReferenceError"));
+
+    // Check callback is still installed.
+    assert!(c2.eval_as::<bool>(" myCallback() ").unwrap());
+}
+
+#[test]
+fn context_reset_installs_replaced_definitions_once() {
+    use std::sync::Arc;
+
+    let c = Context::new().unwrap();
+    let first = Arc::new(());
+    let f = first.clone();
+    c.add_callback("value", move || Arc::strong_count(&f) as i32)
+        .unwrap();
+    c.add_callback("value", || 2).unwrap();
+
+    c.namespace("api")
+        .constant("a", 1)
+        .constant("b", 1)
+        .install()
+        .unwrap();
+    c.namespace("api").constant("a", 2).install().unwrap();
+    c.define_property("settings", "mode", Accessor::new().get(|| "old"))
+        .unwrap();
+    c.define_property("settings", "mode", Accessor::new().get(|| "new"))
+        .unwrap();
+
+    // The replaced callback is released with the old context.
+    let c = c.reset().unwrap();
+    assert_eq!(Arc::strong_count(&first), 1);
+    assert_eq!(
+        c.eval(" [value(), api.a, api.b, settings.mode] ").unwrap(),
+        JsValue::Array(vec![
+            JsValue::Int(2),
+            JsValue::Int(2),
+            JsValue::Int(1),
+            JsValue::from("new"),
+        ])
+    );
+}
+
+#[test]
+fn context_reset_preserves_console() {
+    use console::Level;
+    use std::sync::{Arc, Mutex};
+
+    let messages = Arc::new(Mutex::new(Vec::<(Level, Vec<JsValue>)>::new()));
+
+    let m = messages.clone();
+    let c = Context::builder()
+        .console(move |level: Level, args: Vec<JsValue>| {
+            m.lock().unwrap().push((level, args));
+        })
+        .build()
+        .unwrap();
+    c.add_callback("double", |x: i32| x * 2).unwrap();
+
+    let c = c.reset().unwrap().reset().unwrap();
+    c.eval(" console.log(double(21)); ").unwrap();
+
+    assert_eq!(
+        *messages.lock().unwrap(),
+        vec![(Level::Log, vec![JsValue::Int(42)])],
+    );
+}
+
+#[test]
+fn prelude_evaluated_on_build_and_reset() {
+    let builder = Context::builder().prelude(
+        "var counter = 0; function next() { return ++counter; }",
+        "prelude.js",
+    );
+
+    let c = builder.clone().build().unwrap();
+    // The bytecode is compiled once and shared with the template.
+    assert!(builder.preludes[0].bytecode.lock().unwrap().is_some());
+
+    assert_eq!(c.eval_as::<i32>(" next() + next() ").unwrap(), 3);
+    let c = c.reset().unwrap();
+    assert_eq!(c.eval_as::<i32>(" next() ").unwrap(), 1);
+
+    let c2 = builder.build().unwrap();
+    assert_eq!(c2.eval_as::<i32>(" next() ").unwrap(), 1);
+}
+
+#[test]
+fn prelude_syntax_error() {
+    let res = Context::builder().prelude(" !!!! ", "broken.js").build();
+    assert!(matches!(res, Err(ContextError::Execution(_))));
+}
+
+#[inline(never)]
+fn build_context() -> Context {
+    let ctx = Context::new().unwrap();
+    let name = "cb".to_string();
+    ctx.add_callback(&name, |a: String| a.repeat(2)).unwrap();
+
+    let code = " function f(value) { return cb(value); } ".to_string();
+    ctx.eval(&code).unwrap();
+
+    ctx
+}
+
+#[test]
+fn moved_context() {
+    let c = build_context();
+    let v = c.call_function("f", vec!["test"]).unwrap();
+    assert_eq!(v, "testtest".into());
+
+    let v = c.eval(" f('la') ").unwrap();
+    assert_eq!(v, "lala".into());
+}
+
+#[test]
+fn array_buffer_serialize() {
+    let c = Context::new().unwrap();
+
+    c.set_global("buffer", JsValue::Bytes(vec![1, 2, 3]))
+        .unwrap();
+    assert_eq!(
+        c.eval(" buffer instanceof ArrayBuffer && buffer.byteLength "),
+        Ok(JsValue::Int(3))
+    );
+    assert_eq!(c.eval(" new Uint8Array(buffer)[2] "), Ok(JsValue::Int(3)));
+}
+
+#[test]
+fn array_buffer_deserialize() {
+    let c = Context::new().unwrap();
+
+    let value = c.eval(" new Uint8Array([4, 5, 6]).buffer ").unwrap();
+    assert_eq!(value, JsValue::Bytes(vec![4, 5, 6]));
+}
+
+#[test]
+fn typed_array_roundtrip() {
+    let c = Context::new().unwrap();
+    c.eval(" function identity(x) { return x; } ").unwrap();
+
+    let arrays = vec![
+        TypedArray::Int8(vec![-1, 2]),
+        TypedArray::Uint8(vec![1, 255]),
+        TypedArray::Uint8Clamped(vec![0, 255]),
+        TypedArray::Int16(vec![-300, 300]),
+        TypedArray::Uint16(vec![60000]),
+        TypedArray::Int32(vec![i32::MIN, i32::MAX]),
+        TypedArray::Uint32(vec![u32::MAX]),
+        TypedArray::Float32(vec![1.5, -2.25]),
+        TypedArray::Float64(vec![std::f64::consts::PI]),
+        TypedArray::BigInt64(vec![i64::MIN]),
+        TypedArray::BigUint64(vec![u64::MAX]),
+        TypedArray::Float64(vec![]),
+    ];
+    for array in arrays {
+        let value = c
+            .call_function("identity", vec![JsValue::TypedArray(array.clone())])
+            .unwrap();
+        assert_eq!(value, JsValue::TypedArray(array));
+    }
+}
+
+#[test]
+fn typed_array_deserialize_view() {
+    let c = Context::new().unwrap();
+
+    // Only the viewed part of the buffer is returned.
+    let value = c
+        .eval(" new Int16Array(new Int16Array([1, 2, 3, 4]).buffer, 2, 2) ")
+        .unwrap();
+    assert_eq!(value, JsValue::TypedArray(TypedArray::Int16(vec![2, 3])));
+
+    let value = c.eval(" new Float32Array([0.5]) ").unwrap();
+    assert_eq!(
+        Vec::<f32>::try_from(TypedArray::try_from(value).unwrap()),
+        Ok(vec![0.5])
+    );
+}
+
+#[test]
+fn conversion_without_globals() {
+    let c = Context::new().unwrap();
+    c.eval(
+        r#"
+        var keep = { Map, Uint8Array, Symbol };
+        for (const name of ["Map", "Set", "ArrayBuffer", "Uint8Array", "Symbol", "Date"]) {
+            delete globalThis[name];
+        }
+    "#,
+    )
+    .unwrap();
+
+    let mut object = IndexMap::new();
+    object.insert("a".to_string(), JsValue::Int(1));
+    assert_eq!(c.eval(" ({a: 1}) "), Ok(JsValue::Object(object)));
+    assert_eq!(
+        c.eval(" new keep.Map([[1, 2]]) "),
+        Ok(JsValue::Map(vec![(JsValue::Int(1), JsValue::Int(2))]))
+    );
+    assert_eq!(
+        c.eval(" new keep.Uint8Array([1]) "),
+        Ok(JsValue::TypedArray(TypedArray::Uint8(vec![1])))
+    );
+    assert_eq!(
+        c.eval(" keep.Symbol.iterator "),
+        Ok(JsValue::Symbol(Symbol::WellKnown("iterator".into())))
+    );
+
+    let value = JsValue::TypedArray(TypedArray::Int16(vec![-1]));
+    c.set_global("x", value.clone()).unwrap();
+    assert_eq!(c.eval(" x "), Ok(value));
+}
+
+#[test]
+fn typed_array_modified_in_js() {
+    let c = Context::new().unwrap();
+    c.eval(" function double(a) { return a.map(x => x * 2); } ")
+        .unwrap();
+
+    let value = c
+        .call_function("double", vec![TypedArray::from(vec![1.5f64, 2.0])])
+        .unwrap();
+    assert_eq!(value, TypedArray::Float64(vec![3.0, 4.0]).into());
+}
+
+#[test]
+fn array_buffer_large_payload() {
+    let c = Context::new().unwrap();
+    c.eval(" function sum(b) { return new Uint8Array(b).reduce((a, x) => a + x, 0); } ")
+        .unwrap();
+
+    let bytes = vec![1u8; 4_000_000];
+    let value = c.call_function("sum", vec![JsValue::Bytes(bytes)]).unwrap();
+    assert_eq!(value, JsValue::Int(4_000_000));
+}
+
+#[test]
+fn object_preserves_property_order() {
+    let c = Context::new().unwrap();
+
+    let value = c.eval(" ({ z: 1, a: 2, 10: 3, m: 4 }) ").unwrap();
+    let keys = match value {
+        JsValue::Object(object) => object.keys().cloned().collect::<Vec<_>>(),
+        _ => panic!("expected object"),
+    };
+    // Integer keys come first, as in Javascript.
+    assert_eq!(keys, vec!["10", "z", "a", "m"]);
+
+    let mut object = IndexMap::new();
+    object.insert("z".to_string(), JsValue::Int(1));
+    object.insert("a".to_string(), JsValue::Int(2));
+    object.insert("m".to_string(), JsValue::Int(3));
+    c.set_global("o", JsValue::Object(object)).unwrap();
+    assert_eq!(
+        c.eval(" Object.keys(o).join(',') "),
+        Ok(JsValue::String("z,a,m".into()))
+    );
+}
+
+#[test]
+fn cyclic_value() {
+    let c = Context::new().unwrap();
+
+    let cyclic = Err(ExecutionError::Conversion(ValueError::CyclicValue));
+    assert_eq!(c.eval(" var a = {}; a.self = a; a "), cyclic);
+    assert_eq!(c.eval(" var b = [1]; b.push({ nested: [b] }); b "), cyclic);
+    assert_eq!(c.eval(" var m = new Map(); m.set('m', m); m "), cyclic);
+}
+
+#[test]
+fn shared_references_are_not_cyclic() {
+    let c = Context::new().unwrap();
+
+    let value = c.eval(" var shared = { x: 1 }; [shared, shared] ").unwrap();
+    let mut shared = IndexMap::new();
+    shared.insert("x".to_string(), JsValue::Int(1));
+    assert_eq!(
+        value,
+        JsValue::Array(vec![
+            JsValue::Object(shared.clone()),
+            JsValue::Object(shared)
+        ])
+    );
+}
+
+#[test]
+fn value_depth_limit() {
+    let nested = |depth: usize| {
+        format!(
+            " var v = 1; for (var i = 0; i < {}; i++) {{ v = [v]; }} v ",
+            depth
+        )
+    };
+    let exceeded = Err(ExecutionError::Conversion(ValueError::DepthLimitExceeded));
+
+    let c = Context::new().unwrap();
+    assert!(c.eval(&nested(128)).is_ok());
+    assert_eq!(c.eval(&nested(129)), exceeded);
+    // Hostile values fail without exhausting the stack.
+    assert_eq!(c.eval(&nested(1_000_000)), exceeded);
+
+    let c = Context::builder().max_value_depth(2).build().unwrap();
+    assert!(c.eval(" [[1]] ").is_ok());
+    assert_eq!(c.eval(" [[[1]]] "), exceeded);
+}
+
+#[test]
+fn value_size_limit() {
+    let c = Context::builder().max_value_size(4).build().unwrap();
+
+    assert_eq!(
+        c.eval(" [1, 2, 3] "),
+        Ok(JsValue::Array(vec![
+            JsValue::Int(1),
+            JsValue::Int(2),
+            JsValue::Int(3)
+        ]))
+    );
+    assert_eq!(
+        c.eval(" [1, [2, 3]] "),
+        Err(ExecutionError::Conversion(ValueError::SizeLimitExceeded))
+    );
+}
+
+#[test]
+fn value_size_limit_default() {
+    let exceeded = Err(ExecutionError::Conversion(ValueError::SizeLimitExceeded));
+    let c = Context::new().unwrap();
+
+    // Sparse arrays fail before iterating over their length.
+    assert_eq!(c.eval(" var a = []; a[2e9] = 1; a "), exceeded);
+    assert_eq!(c.eval(" var a = []; a[4e9] = 1; a "), exceeded);
+
+    // Shared references are copied for every reference.
+    let dag = " var x = [1]; for (var i = 0; i < 40; i++) { x = [x, x]; } x ";
+    assert_eq!(c.eval(dag), exceeded);
+}
+
+#[test]
+fn shared_values_converted_once() {
+    let c = Context::new().unwrap();
+    let value = c
+        .eval(
+            r#"
+            var calls = 0;
+            var shared = { get x() { calls++; return 1; } };
+            [shared, shared, shared, [shared]]
+        "#,
+        )
+        .unwrap();
+
+    let mut object = IndexMap::new();
+    object.insert("x".to_string(), JsValue::Int(1));
+    let object = JsValue::Object(object);
+    assert_eq!(
+        value,
+        JsValue::Array(vec![
+            object.clone(),
+            object.clone(),
+            object.clone(),
+            JsValue::Array(vec![object]),
+        ])
+    );
+    assert_eq!(c.eval_as::<i32>(" calls "), Ok(2));
+
+    let c = Context::builder().max_value_size(6).build().unwrap();
+    assert!(c.eval(" var s = [1]; [s, s] ").is_ok());
+    assert_eq!(
+        c.eval(" var s = [1]; [s, s, s] "),
+        Err(ExecutionError::Conversion(ValueError::SizeLimitExceeded))
+    );
+}
+
+#[test]
+fn cyclic_callback_argument() {
+    let c = Context::new().unwrap();
+    c.add_callback("inspect", |_value: JsValue| true).unwrap();
+
+    let res = c
+        .eval(
+            r#"
+            var a = {};
+            a.self = a;
+            try { inspect(a); 'ok' } catch (e) { 'error' }
+        "#,
+        )
+        .unwrap();
+    assert_eq!(res, JsValue::String("error".into()));
+}
+
+#[test]
+fn map_roundtrip() {
+    let c = Context::new().unwrap();
+    c.eval(" function identity(x) { return x; } ").unwrap();
+
+    let value = c
+        .eval(" new Map([[1, 'a'], ['b', [2]], [{}, null]]) ")
+        .unwrap();
+    let expected = JsValue::Map(vec![
+        (JsValue::Int(1), "a".into()),
+        ("b".into(), JsValue::Array(vec![JsValue::Int(2)])),
+        (JsValue::Object(IndexMap::new()), JsValue::Null),
+    ]);
+    assert_eq!(value, expected);
+
+    let value = c.call_function("identity", vec![expected.clone()]).unwrap();
+    assert_eq!(value, expected);
+
+    c.set_global("m", expected).unwrap();
+    assert_eq!(
+        c.eval(" m instanceof Map && m.get('b')[0] "),
+        Ok(JsValue::Int(2))
+    );
+}
+
+#[test]
+fn set_roundtrip() {
+    let c = Context::new().unwrap();
+
+    let value = c.eval(" new Set([3, 'x', 3]) ").unwrap();
+    assert_eq!(value, JsValue::Set(vec![JsValue::Int(3), "x".into()]));
+
+    c.set_global("s", value).unwrap();
+    assert_eq!(
+        c.eval(" s instanceof Set && s.has('x') && s.size "),
+        Ok(JsValue::Int(2))
+    );
+}
+
+#[test]
+fn collection_builtins_replaced_by_script() {
+    let c = Context::new().unwrap();
+    c.eval(
+        r#"
+        Array.from = () => 1;
+        Map.prototype.entries = Set.prototype.values = () => 1;
+        Map.prototype[Symbol.iterator] = Set.prototype[Symbol.iterator] = () => 1;
+        Object.getPrototypeOf(new Map().entries()).next = () => 1;
+    "#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        c.eval(" new Map([[1, 2]]) "),
+        Ok(JsValue::Map(vec![(JsValue::Int(1), JsValue::Int(2))]))
+    );
+    assert_eq!(
+        c.eval(" new Set([1]) "),
+        Ok(JsValue::Set(vec![JsValue::Int(1)]))
+    );
+}
+
+#[test]
+fn symbol_description_replaced_by_script() {
+    let c = Context::new().unwrap();
+    c.eval(
+        r#"
+        Object.defineProperty(Symbol.prototype, "description", { get: () => "fake" });
+    "#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        c.eval(" Symbol('desc') "),
+        Ok(JsValue::Symbol(Symbol::Local(Some("desc".into()))))
+    );
+    assert_eq!(
+        c.eval(" Symbol() "),
+        Ok(JsValue::Symbol(Symbol::Local(None)))
+    );
+}
+
+#[test]
+fn collection_conversions() {
+    use std::collections::{BTreeMap, HashSet};
+
+    let c = Context::new().unwrap();
+
+    let map: BTreeMap<String, i32> = c.eval_as(" new Map([['a', 1], ['b', 2]]) ").unwrap();
+    assert_eq!(map.get("b"), Some(&2));
+
+    let set: HashSet<i32> = c.eval_as(" new Set([1, 2, 2]) ").unwrap();
+    assert_eq!(set.len(), 2);
+}
+
+#[test]
+fn symbol_roundtrip() {
+    let c = Context::new().unwrap();
+    c.eval(" function identity(x) { return x; } ").unwrap();
+
+    assert_eq!(
+        c.eval(" Symbol('desc') "),
+        Ok(JsValue::Symbol(Symbol::Local(Some("desc".into()))))
+    );
+    assert_eq!(
+        c.eval(" Symbol() "),
+        Ok(JsValue::Symbol(Symbol::Local(None)))
+    );
+    assert_eq!(
+        c.eval(" Symbol.iterator "),
+        Ok(JsValue::Symbol(Symbol::WellKnown("iterator".into())))
+    );
+    assert_eq!(
+        c.eval(" Symbol.for('app.key') "),
+        Ok(JsValue::Symbol(Symbol::Registered("app.key".into())))
+    );
+
+    for symbol in [
+        Symbol::Local(Some("desc".into())),
+        Symbol::Registered("app.key".into()),
+        Symbol::WellKnown("asyncIterator".into()),
+    ] {
+        let value = c
+            .call_function("identity", vec![JsValue::Symbol(symbol.clone())])
+            .unwrap();
+        assert_eq!(value, JsValue::Symbol(symbol));
+    }
+
+    c.set_global("iter", Symbol::WellKnown("iterator".to_string()))
+        .unwrap();
+    c.set_global("key", Symbol::Registered("app.key".to_string()))
+        .unwrap();
+    assert_eq!(
+        c.eval(" iter === Symbol.iterator && key === Symbol.for('app.key') "),
+        Ok(JsValue::Bool(true))
+    );
+}
+
+#[test]
+fn object_symbol_keys_are_skipped() {
+    let c = Context::new().unwrap();
+
+    let value = c
+        .eval(" ({ a: 1, [Symbol('s')]: 2, [Symbol.for('r')]: 3, [Symbol.iterator]: 4 }) ")
+        .unwrap();
+    let mut expected = IndexMap::new();
+    expected.insert("a".to_string(), JsValue::Int(1));
+    assert_eq!(value, JsValue::Object(expected));
+}
+
+#[test]
+fn function_handle_called_after_callback() {
+    use std::sync::{Arc, Mutex};
+
+    let c = Context::new().unwrap();
+    let listeners = Arc::new(Mutex::new(Vec::<JsFunction>::new()));
+
+    let l = listeners.clone();
+    c.add_callback("subscribe", move |listener: JsFunction| {
+        l.lock().unwrap().push(listener);
+        JsValue::Undefined
+    })
+    .unwrap();
+    c.eval(
+        r#"
+        var events = [];
+        subscribe(e => { events.push(e); return events.length; });
+    "#,
+    )
+    .unwrap();
+
+    let listener = listeners.lock().unwrap().pop().unwrap();
+    assert_eq!(listener.call(&c, vec!["a"]), Ok(JsValue::Int(1)));
+    assert_eq!(listener.call(&c, vec!["b"]), Ok(JsValue::Int(2)));
+    assert_eq!(
+        c.eval_as::<Vec<String>>("events").unwrap(),
+        vec!["a".to_string(), "b".to_string()]
+    );
+}
+
+#[test]
+fn function_roundtrip() {
+    let c = Context::new().unwrap();
+    c.eval(" function apply(f, x) { return f(x); } ").unwrap();
+
+    let double = c.eval(" x => x * 2 ").unwrap();
+    let double = JsFunction::try_from(double).unwrap();
+    assert_eq!(double.clone(), double);
 
-    // Check old state is gone.
-    let err_msg = c2.eval(" x ").unwrap_err().to_string();
-    assert!(err_msg.contains("ReferenceError"));
+    assert_eq!(
+        c.call_function("apply", vec![JsValue::from(double.clone()), 21.into()]),
+        Ok(JsValue::Int(42))
+    );
 
-    // Check callback is gone.
-    let err_msg = c2.eval(" myCallback() ").unwrap_err().to_string();
-    assert!(err_msg.contains("ReferenceError"));
+    c.eval(" function identity(x) { return x; } ").unwrap();
+    let value = c.call_function("identity", vec![double.clone()]).unwrap();
+    let value = JsFunction::try_from(value).unwrap();
+    assert_eq!(value.call(&c, vec![4]), Ok(JsValue::Int(8)));
 }
 
-#[inline(never)]
-fn build_context() -> Context {
-    let ctx = Context::new().unwrap();
-    let name = "cb".to_string();
-    ctx.add_callback(&name, |a: String| a.repeat(2)).unwrap();
+#[test]
+fn function_released_when_handle_dropped() {
+    let c = Context::new().unwrap();
 
-    let code = " function f(value) { return cb(value); } ".to_string();
-    ctx.eval(&code).unwrap();
+    let f = c
+        .eval(" (() => { const big = new Array(1000000).fill(1); return () => big.length; })() ")
+        .unwrap();
+    let f = JsFunction::try_from(f).unwrap();
+    assert_eq!(
+        f.call(&c, Vec::<JsValue>::new()),
+        Ok(JsValue::Int(1_000_000))
+    );
+    let usage = c.memory_usage();
 
-    ctx
+    drop(f);
+    c.eval("1").unwrap();
+    assert!(c.memory_usage() + 4_000_000 < usage);
 }
 
 #[test]
-fn moved_context() {
-    let c = build_context();
-    let v = c.call_function("f", vec!["test"]).unwrap();
-    assert_eq!(v, "testtest".into());
-
-    let v = c.eval(" f('la') ").unwrap();
-    assert_eq!(v, "lala".into());
+fn function_invalid_in_other_context() {
+    let c = Context::new().unwrap();
+    let f = JsFunction::try_from(c.eval(" () => 1 ").unwrap()).unwrap();
+
+    let other = Context::new().unwrap();
+    assert!(matches!(
+        f.call(&other, Vec::<JsValue>::new()),
+        Err(ExecutionError::Internal(_))
+    ));
+    assert!(other.set_global("f", f.clone()).is_err());
+
+    let c = c.reset().unwrap();
+    assert!(matches!(
+        f.call(&c, Vec::<JsValue>::new()),
+        Err(ExecutionError::Internal(_))
+    ));
 }
 
 #[cfg(feature = "chrono")]
@@ -484,12 +1845,31 @@ fn chrono_serialize() {
     let now_millis = now.timestamp_millis();
 
     let timestamp = c
-        .call_function("dateToTimestamp", vec![JsValue::Date(now.clone())])
+        .call_function("dateToTimestamp", vec![JsValue::Date(now)])
         .unwrap();
 
     assert_eq!(timestamp, JsValue::Float(now_millis as f64));
 }
 
+#[cfg(feature = "chrono")]
+#[test]
+fn chrono_deserialize_builtins() {
+    use chrono::offset::TimeZone;
+
+    let c = build_context();
+    c.eval(" var OriginalDate = Date; Date = function () {}; delete Date.prototype.getTime; ")
+        .unwrap();
+    c.eval(" delete OriginalDate.prototype.getTime; ").unwrap();
+
+    let epoch = chrono::Utc.timestamp_millis_opt(0).unwrap();
+    assert_eq!(c.eval(" new OriginalDate(0) "), Ok(JsValue::Date(epoch)));
+    // Objects that only inherit from the Date prototype are not dates.
+    assert_eq!(
+        c.eval(" Object.create(OriginalDate.prototype) "),
+        Ok(JsValue::Object(IndexMap::new()))
+    );
+}
+
 #[cfg(feature = "chrono")]
 #[test]
 fn chrono_deserialize() {
@@ -498,7 +1878,7 @@ fn chrono_deserialize() {
     let c = build_context();
 
     let value = c.eval(" new Date(1234567555) ").unwrap();
-    let datetime = chrono::Utc.timestamp_millis(1234567555);
+    let datetime = chrono::Utc.timestamp_millis_opt(1234567555).unwrap();
 
     assert_eq!(value, JsValue::Date(datetime));
 }
@@ -510,7 +1890,7 @@ fn chrono_roundtrip() {
 
     c.eval(" function identity(x) { return x; } ").unwrap();
     let d = chrono::Utc::now();
-    let td = JsValue::Date(d.clone());
+    let td = JsValue::Date(d);
     let td2 = c.call_function("identity", vec![td.clone()]).unwrap();
     let d2 = if let JsValue::Date(x) = td2 {
         x
@@ -524,7 +1904,7 @@ fn chrono_roundtrip() {
 #[cfg(feature = "bigint")]
 #[test]
 fn test_bigint_deserialize_i64() {
-    for i in vec![0, std::i64::MAX, std::i64::MIN] {
+    for i in [0, i64::MAX, i64::MIN] {
         let c = Context::new().unwrap();
         let value = c.eval(&format!("{}n", i)).unwrap();
         assert_eq!(value, JsValue::BigInt(i.into()));
@@ -534,11 +1914,11 @@ fn test_bigint_deserialize_i64() {
 #[cfg(feature = "bigint")]
 #[test]
 fn test_bigint_deserialize_bigint() {
-    for i in vec![
-        std::i64::MAX as i128 + 1,
-        std::i64::MIN as i128 - 1,
-        std::i128::MAX,
-        std::i128::MIN,
+    for i in [
+        i64::MAX as i128 + 1,
+        i64::MIN as i128 - 1,
+        i128::MAX,
+        i128::MIN,
     ] {
         let c = Context::new().unwrap();
         let value = c.eval(&format!("{}n", i)).unwrap();
@@ -550,7 +1930,7 @@ fn test_bigint_deserialize_bigint() {
 #[cfg(feature = "bigint")]
 #[test]
 fn test_bigint_serialize_i64() {
-    for i in vec![0, std::i64::MAX, std::i64::MIN] {
+    for i in [0, i64::MAX, i64::MIN] {
         let c = Context::new().unwrap();
         c.eval(&format!(" function isEqual(x) {{ return x === {}n }} ", i))
             .unwrap();
@@ -565,11 +1945,11 @@ fn test_bigint_serialize_i64() {
 #[cfg(feature = "bigint")]
 #[test]
 fn test_bigint_serialize_bigint() {
-    for i in vec![
-        std::i64::MAX as i128 + 1,
-        std::i64::MIN as i128 - 1,
-        std::i128::MAX,
-        std::i128::MIN,
+    for i in [
+        i64::MAX as i128 + 1,
+        i64::MIN as i128 - 1,
+        i128::MAX,
+        i128::MIN,
     ] {
         let c = Context::new().unwrap();
         c.eval(&format!(" function isEqual(x) {{ return x === {}n }} ", i))
@@ -582,6 +1962,104 @@ fn test_bigint_serialize_bigint() {
     }
 }
 
+#[cfg(feature = "bigint")]
+#[test]
+fn test_bigint_no_wrap_around() {
+    let c = Context::new().unwrap();
+    // An unpatched `JS_ToBigInt64` would wrap these to 5 and -1.
+    let value: crate::BigInt = c.eval_as("2n ** 64n + 5n").unwrap();
+    assert_eq!(value.as_u128(), Some((1u128 << 64) + 5));
+    assert_eq!(value.as_i64(), None);
+    let value: crate::BigInt = c.eval_as("-(2n ** 64n) - 1n").unwrap();
+    assert_eq!(value.as_i128(), Some(-(1i128 << 64) - 1));
+
+    c.add_callback("double", |x: crate::BigInt| x.clone() + x)
+        .unwrap();
+    let value: crate::BigInt = c.eval_as("double(2n ** 100n)").unwrap();
+    assert_eq!(value, crate::BigInt::from(1u128 << 101));
+    assert!(c
+        .eval_as::<bool>("double(9007199254740993n) === 18014398509481986n")
+        .unwrap());
+}
+
+#[cfg(feature = "bignum")]
+#[test]
+fn test_bigdecimal_round_trip() {
+    use bigdecimal::BigDecimal;
+    use std::str::FromStr;
+
+    let c = Context::new().unwrap();
+    assert_eq!(
+        c.eval("0.1m + 0.2m").unwrap(),
+        JsValue::BigDecimal(BigDecimal::from_str("0.3").unwrap())
+    );
+    assert_eq!(
+        c.eval_as::<BigDecimal>("1e-30m").unwrap(),
+        BigDecimal::from_str("1e-30").unwrap()
+    );
+
+    c.eval("function identity(x) { return x }").unwrap();
+    for s in [
+        "0",
+        "0.1",
+        "-12.50",
+        "123456789012345678901234567890.123456789",
+        "1e40",
+    ] {
+        let value = JsValue::BigDecimal(BigDecimal::from_str(s).unwrap());
+        assert_eq!(
+            c.call_function("identity", vec![value.clone()]).unwrap(),
+            value
+        );
+    }
+
+    c.add_callback("total", |prices: Vec<BigDecimal>| {
+        prices.into_iter().fold(BigDecimal::from(0), |a, b| a + b)
+    })
+    .unwrap();
+    assert!(c
+        .eval_as::<bool>("total([0.1m, 0.2m, 3]) === 3.3m && typeof total([]) === 'bigdecimal'")
+        .unwrap());
+    assert_eq!(
+        JsValue::BigDecimal(BigDecimal::from_str("-0.1").unwrap()).to_string(),
+        "-0.1m"
+    );
+}
+
+#[cfg(feature = "bignum")]
+#[test]
+fn test_bigfloat_round_trip() {
+    use crate::BigFloat;
+
+    let c = Context::new().unwrap();
+    assert_eq!(c.eval("0.5l").unwrap(), JsValue::BigFloat(0.5.into()));
+    assert_eq!(
+        c.eval("-1l / 0l").unwrap(),
+        JsValue::BigFloat(BigFloat::infinity(true))
+    );
+    assert!(c.eval_as::<BigFloat>("0l / 0l").unwrap().is_nan());
+
+    c.eval("function identity(x) { return x }").unwrap();
+    for code in ["0.1l", "1e100l", "-1e-100l", "BigFloat.PI"] {
+        let value = c.eval(code).unwrap();
+        assert_eq!(
+            c.call_function("identity", vec![value.clone()]).unwrap(),
+            value
+        );
+        c.set_global("value", value).unwrap();
+        assert!(c.eval_as::<bool>(&format!("value === {}", code)).unwrap());
+    }
+
+    c.set_global("value", BigFloat::from(0.1)).unwrap();
+    assert!(c.eval_as::<bool>("value === BigFloat(0.1)").unwrap());
+    c.set_global("value", BigFloat::nan()).unwrap();
+    assert!(c.eval_as::<bool>("Number.isNaN(Number(value))").unwrap());
+    assert_eq!(
+        JsValue::BigFloat(BigFloat::infinity(false)).to_string(),
+        "BigFloat(\"Infinity\")"
+    );
+}
+
 #[test]
 fn test_console() {
     use console::Level;
@@ -616,6 +2094,434 @@ fn test_console() {
     );
 }
 
+#[test]
+fn test_console_format_and_assert() {
+    use console::Level;
+    use std::sync::{Arc, Mutex};
+
+    let messages = Arc::new(Mutex::new(Vec::<(Level, Vec<JsValue>)>::new()));
+
+    let m = messages.clone();
+    let c = Context::builder()
+        .console(move |level: Level, args: Vec<JsValue>| {
+            m.lock().unwrap().push((level, args));
+        })
+        .build()
+        .unwrap();
+
+    c.eval(
+        r#"
+        console.log("%s is %d years and %f%% done", "Bob", "42.9", 0.5, "extra");
+        console.info("%o %O %c|%x", "s", { a: [1] }, "color: red");
+        console.log("%s and %d", "only one");
+        console.warn({ a: 1 }, "%s");
+        console.assert(true, "not shown");
+        console.assert(false, "%s failed", "check");
+        console.assert(false, { a: 1 });
+        console.assert(false);
+    "#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        *messages.lock().unwrap(),
+        vec![
+            (
+                Level::Log,
+                vec!["Bob is 42 years and 0.5% done".into(), "extra".into()]
+            ),
+            (Level::Info, vec!["'s' { a: [ 1 ] } |%x".into()]),
+            (Level::Log, vec!["only one and %d".into()]),
+            (
+                Level::Warn,
+                vec![
+                    JsValue::Object(
+                        vec![("a".to_string(), JsValue::Int(1))]
+                            .into_iter()
+                            .collect()
+                    ),
+                    "%s".into()
+                ]
+            ),
+            (Level::Error, vec!["Assertion failed: check failed".into()]),
+            (
+                Level::Error,
+                vec![
+                    "Assertion failed".into(),
+                    JsValue::Object(
+                        vec![("a".to_string(), JsValue::Int(1))]
+                            .into_iter()
+                            .collect()
+                    ),
+                ]
+            ),
+            (Level::Error, vec!["Assertion failed".into()]),
+        ]
+    );
+}
+
+#[test]
+fn test_console_events() {
+    use console::{ConsoleBackend, ConsoleMessage, Level, MessageKind};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct Events(Arc<Mutex<Vec<ConsoleMessage>>>);
+
+    impl ConsoleBackend for Events {
+        fn log(&self, _level: Level, _values: Vec<JsValue>) {
+            unreachable!()
+        }
+
+        fn message(&self, message: ConsoleMessage) {
+            self.0.lock().unwrap().push(message);
+        }
+    }
+
+    let events = Events::default();
+    let c = Context::builder().console(events.clone()).build().unwrap();
+
+    c.eval(
+        r#"
+        console.count();
+        console.count("a");
+        console.count();
+        console.countReset();
+        console.countReset("missing");
+        console.group("outer");
+        console.groupCollapsed();
+        console.log("inner");
+        console.groupEnd();
+        console.table([{ a: 1 }], ["a", 1]);
+        console.groupEnd();
+        console.groupEnd();
+        console.dir("item");
+        console.time("t");
+        console.time("t");
+        console.timeLog("t", 1);
+        console.timeEnd("t");
+        console.timeEnd("t");
+        console.group();
+        console.clear();
+    "#,
+    )
+    .unwrap();
+
+    let events = events.0.lock().unwrap();
+    let summary = events
+        .iter()
+        .map(|event| (event.level, event.group_depth, event.values.clone()))
+        .collect::<Vec<_>>();
+
+    let warn = |message: &str| (Level::Warn, 0, vec![message.into()]);
+    assert_eq!(
+        summary[..13],
+        [
+            (Level::Info, 0, vec!["default: 1".into()]),
+            (Level::Info, 0, vec!["a: 1".into()]),
+            (Level::Info, 0, vec!["default: 2".into()]),
+            (Level::Info, 0, vec![]),
+            warn("Count for 'missing' does not exist"),
+            (Level::Log, 0, vec!["outer".into()]),
+            (Level::Log, 1, vec![]),
+            (Level::Log, 2, vec!["inner".into()]),
+            (Level::Log, 1, vec![]),
+            (
+                Level::Log,
+                1,
+                vec![JsValue::Array(vec![JsValue::Object(
+                    vec![("a".to_string(), JsValue::Int(1))]
+                        .into_iter()
+                        .collect()
+                )])]
+            ),
+            (Level::Log, 0, vec![]),
+            (Level::Log, 0, vec![]),
+            (Level::Log, 0, vec!["item".into()]),
+        ]
+    );
+
+    assert_eq!(
+        events[2].kind,
+        MessageKind::Count {
+            label: "default".into(),
+            count: 2
+        }
+    );
+    assert_eq!(
+        events[3].kind,
+        MessageKind::CountReset {
+            label: "default".into()
+        }
+    );
+    assert_eq!(events[6].kind, MessageKind::Group { collapsed: true });
+    assert_eq!(
+        events[9].kind,
+        MessageKind::Table {
+            columns: Some(vec!["a".into()])
+        }
+    );
+    assert_eq!(events[12].kind, MessageKind::Dir);
+
+    assert_eq!(events[13].kind, MessageKind::Time { label: "t".into() });
+    assert_eq!(summary[14], warn("Timer 't' already exists"));
+    match &events[15].kind {
+        MessageKind::TimeLog { label, .. } => assert_eq!(label, "t"),
+        other => panic!("expected timeLog, got {:?}", other),
+    }
+    assert_eq!(events[15].values[1], JsValue::Int(1));
+    assert!(events[15].values[0].as_str().unwrap().starts_with("t: "));
+    match &events[16].kind {
+        MessageKind::TimeEnd { label, elapsed } => {
+            assert_eq!(label, "t");
+            let text = format!("t: {:.3}ms", elapsed.as_secs_f64() * 1000.0);
+            assert_eq!(events[16].values, vec![JsValue::from(text)]);
+        }
+        other => panic!("expected timeEnd, got {:?}", other),
+    }
+    assert_eq!(summary[17], warn("Timer 't' does not exist"));
+    assert_eq!(events[18].group_depth, 0);
+    assert_eq!(events[19].kind, MessageKind::Clear);
+    assert_eq!(events[19].group_depth, 0);
+    assert_eq!(events.len(), 20);
+}
+
+#[test]
+fn test_console_capture_location() {
+    use console::{CaptureConsole, Level, MessageKind};
+
+    let console = CaptureConsole::new();
+    let c = Context::builder().console(console.clone()).build().unwrap();
+
+    c.eval(
+        r#"
+        console.log("top");
+        function check(value) {
+            console.assert(value, "%d is falsy", value);
+        }
+        check(0);
+        const inline = () => console.warn("inline");
+        inline();
+        [1].forEach(console.info);
+    "#,
+    )
+    .unwrap();
+
+    let messages = console.take();
+    assert!(console.messages().is_empty());
+    let locations = messages
+        .iter()
+        .map(|m| (m.filename.as_deref(), m.line, m.function.as_deref()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        locations,
+        vec![
+            (Some("script.js"), Some(2), None),
+            (Some("script.js"), Some(4), Some("check")),
+            // Single line functions have no line information.
+            (Some("script.js"), None, Some("inline")),
+            (None, None, Some("forEach")),
+        ]
+    );
+
+    assert_eq!(messages[1].kind, MessageKind::Assert);
+    assert_eq!(messages[1].level, Level::Error);
+    assert_eq!(messages[1].text(), "Assertion failed: 0 is falsy");
+    assert_eq!(messages[3].text(), "1 0 [ 1 ]");
+}
+
+#[test]
+fn test_console_never_throws() {
+    use console::CaptureConsole;
+
+    let console = CaptureConsole::new();
+    let c = Context::builder().console(console.clone()).build().unwrap();
+
+    c.eval(
+        r#"
+        const cyclic = { a: 1, list: [] };
+        cyclic.self = cyclic;
+        cyclic.list.push(cyclic);
+        const throwing = { toString() { throw new Error("toString"); } };
+
+        console.log(cyclic);
+        console.log("%o", cyclic);
+        console.log("%O|%s|%d", new Map([[1, cyclic]]), throwing, throwing);
+        console.count(throwing);
+        console.log("shared", [cyclic.list, cyclic.list]);
+    "#,
+    )
+    .unwrap();
+
+    let texts = console
+        .take()
+        .iter()
+        .map(|message| message.text())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        texts,
+        vec![
+            "{ a: 1, list: [ [Circular] ], self: [Circular] }",
+            "{ a: 1, list: [ [Circular] ], self: [Circular] }",
+            "Map(1) { 1 => { a: 1, list: [ [Circular] ], self: [Circular] } }|{ toString: [Function] }|NaN",
+            "{ toString: [Function] }: 1",
+            "shared [\n  [ { a: 1, list: [Circular], self: [Circular] } ],\n  [ { a: 1, list: [Circular], self: [Circular] } ]\n]",
+        ]
+    );
+
+    // Values without cycles are passed unchanged.
+    c.eval(" console.log({ a: [1] }) ").unwrap();
+    assert_eq!(
+        console.take()[0].values,
+        vec![JsValue::Object(
+            vec![("a".to_string(), JsValue::Array(vec![JsValue::Int(1)]))]
+                .into_iter()
+                .collect()
+        )]
+    );
+}
+
+#[test]
+fn test_console_helpers_are_hidden() {
+    use console::Level;
+    use std::sync::{Arc, Mutex};
+
+    let messages = Arc::new(Mutex::new(Vec::<(Level, Vec<JsValue>)>::new()));
+
+    let m = messages.clone();
+    let c = Context::builder()
+        .console(move |level: Level, args: Vec<JsValue>| {
+            m.lock().unwrap().push((level, args));
+        })
+        .build()
+        .unwrap();
+
+    assert_eq!(
+        c.eval(" typeof __console_write ").unwrap(),
+        JsValue::from("undefined")
+    );
+    c.eval(
+        r#"
+        globalThis.__console_write = () => { throw new Error("tampered"); };
+        console.log("hi");
+    "#,
+    )
+    .unwrap();
+    assert_eq!(
+        *messages.lock().unwrap(),
+        vec![(Level::Log, vec![JsValue::from("hi")])]
+    );
+
+    // The console is installed again on reset.
+    let c = c.reset().unwrap();
+    c.eval(" console.info(1) ").unwrap();
+    assert_eq!(
+        messages.lock().unwrap().last(),
+        Some(&(Level::Info, vec![JsValue::from(1)]))
+    );
+}
+
+#[test]
+fn test_promise_helpers_are_hidden() {
+    let c = Context::new().unwrap();
+
+    assert_eq!(c.eval_as::<i32>(" Promise.resolve(1) ").unwrap(), 1);
+    assert_eq!(
+        c.eval(" [typeof __resolvePromise, typeof __promiseResult, typeof __promiseValue] ")
+            .unwrap(),
+        JsValue::Array(vec!["undefined".into(); 3])
+    );
+
+    c.eval(
+        r#"
+        var __resolvePromise = () => {};
+        var __promiseResult = false;
+        var __promiseValue = "tampered";
+    "#,
+    )
+    .unwrap();
+    assert_eq!(c.eval_as::<i32>(" Promise.resolve(2) ").unwrap(), 2);
+    assert_eq!(
+        c.eval(" Promise.reject('failed') "),
+        Err(ExecutionError::Exception("failed".into()))
+    );
+}
+
+#[test]
+fn test_promise_never_settles() {
+    let c = Context::new().unwrap();
+    assert!(matches!(
+        c.eval(" new Promise(() => {}) "),
+        Err(ExecutionError::Internal(_))
+    ));
+    // Settled by a later job.
+    assert_eq!(
+        c.eval_as::<i32>(" new Promise(resolve => Promise.resolve().then(() => resolve(1))) ")
+            .unwrap(),
+        1
+    );
+}
+
+#[test]
+fn test_promise_then_replaced_by_script() {
+    let c = Context::new().unwrap();
+
+    c.eval(
+        r#"
+        Promise.prototype.then = () => {};
+        var own = Promise.resolve(3);
+        own.then = () => {};
+    "#,
+    )
+    .unwrap();
+    assert_eq!(c.eval_as::<i32>(" Promise.resolve(1) ").unwrap(), 1);
+    assert_eq!(c.eval_as::<i32>(" own ").unwrap(), 3);
+    assert_eq!(
+        c.eval(" Promise.reject('failed') "),
+        Err(ExecutionError::Exception("failed".into()))
+    );
+}
+
+#[test]
+fn test_console_builtins_replaced_by_script() {
+    use console::{CaptureConsole, Level, MessageKind};
+
+    let console = CaptureConsole::new();
+    let c = Context::builder().console(console.clone()).build().unwrap();
+
+    c.eval(
+        r#"
+        String.prototype.replace = () => "tampered";
+        String.prototype.split = () => [];
+        String = () => "tampered";
+        parseInt = () => 0;
+        Array.isArray = () => false;
+        Array.prototype[Symbol.iterator] = function* () {};
+        Object.defineProperty(Array.prototype, "0", { set() { throw new Error("tampered"); } });
+        console.log("%s is %d", "Bob", "42.9", "extra");
+        console.assert(false, "%s failed", "check");
+        console.table([1], ["a"]);
+    "#,
+    )
+    .unwrap();
+
+    let messages = console.take();
+    assert_eq!(messages.len(), 3);
+    assert_eq!(
+        messages[0].values,
+        vec![JsValue::from("Bob is 42"), JsValue::from("extra")]
+    );
+    assert_eq!(messages[0].line, Some(9));
+    assert_eq!(messages[1].level, Level::Error);
+    assert_eq!(messages[1].text(), "Assertion failed: check failed");
+    assert_eq!(
+        messages[2].kind,
+        MessageKind::Table {
+            columns: Some(vec!["a".into()])
+        }
+    );
+}
+
 #[test]
 fn test_global_setter() {
     let ctx = Context::new().unwrap();
@@ -0,0 +1,684 @@
+use std::{
+    error, fmt,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, SyncSender, TrySendError},
+        Arc, Condvar, Mutex, Weak,
+    },
+    task::{Context as TaskContext, Poll, Waker},
+    thread::JoinHandle,
+};
+
+use crate::{Context, ContextError, ExecutionError, JsValue};
+
+/// Error returned by [ThreadedContext] and [WorkerPool](crate::WorkerPool)
+/// methods.
+#[derive(PartialEq, Debug)]
+#[allow(clippy::manual_non_exhaustive)]
+pub enum ThreadedError {
+    /// Javascript execution failed.
+    Execution(ExecutionError),
+    /// The context thread has shut down or panicked.
+    Shutdown,
+    /// The command queue is full.
+    /// Only returned by [WorkerPool::try_submit](crate::WorkerPool::try_submit).
+    QueueFull,
+    #[doc(hidden)]
+    __NonExhaustive,
+}
+
+impl fmt::Display for ThreadedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use ThreadedError::*;
+        match self {
+            Execution(e) => e.fmt(f),
+            Shutdown => write!(f, "Context thread has shut down"),
+            QueueFull => write!(f, "Context command queue is full"),
+            __NonExhaustive => unreachable!(),
+        }
+    }
+}
+
+impl error::Error for ThreadedError {}
+
+impl From<ExecutionError> for ThreadedError {
+    fn from(e: ExecutionError) -> Self {
+        ThreadedError::Execution(e)
+    }
+}
+
+struct ReplyState<T> {
+    value: Option<Result<T, ThreadedError>>,
+    waker: Option<Waker>,
+}
+
+struct ReplyInner<T> {
+    state: Mutex<ReplyState<T>>,
+    ready: Condvar,
+}
+
+impl<T> ReplyInner<T> {
+    fn complete(&self, value: Result<T, ThreadedError>) {
+        let mut state = self.state.lock().unwrap();
+        state.value = Some(value);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+        self.ready.notify_all();
+    }
+}
+
+/// The sending half of a [Reply].
+///
+/// Completes the reply with [ThreadedError::Shutdown] if dropped without
+/// sending a value, for example when the context thread panics.
+pub(crate) struct ReplySender<T> {
+    inner: Option<Arc<ReplyInner<T>>>,
+}
+
+impl<T> ReplySender<T> {
+    pub(crate) fn send(mut self, value: Result<T, ThreadedError>) {
+        if let Some(inner) = self.inner.take() {
+            inner.complete(value);
+        }
+    }
+}
+
+impl<T> Drop for ReplySender<T> {
+    fn drop(&mut self) {
+        if let Some(inner) = self.inner.take() {
+            inner.complete(Err(ThreadedError::Shutdown));
+        }
+    }
+}
+
+/// The pending result of a command sent to a [ThreadedContext] or a
+/// [WorkerPool](crate::WorkerPool).
+///
+/// Can be awaited, or waited on with [Reply::wait].
+/// Dropping a reply whose command still waits for space in the queue
+/// cancels the command.
+pub struct Reply<T> {
+    inner: Arc<ReplyInner<T>>,
+    /// A command that did not fit into the full queue yet.
+    pending: Mutex<Option<PendingSend>>,
+}
+
+/// A command waiting for space in the queue of a [ThreadedContext].
+struct PendingSend {
+    /// Weak, so pending replies do not keep the context thread alive.
+    sender: Weak<SyncSender<Command>>,
+    waiters: Arc<QueueWaiters>,
+    command: Command,
+}
+
+impl PendingSend {
+    /// Try to queue the command, registering the waker if the queue is full.
+    ///
+    /// Returns the command if it has to wait.
+    fn poll_send(self, waker: &Waker) -> Option<Self> {
+        // If the context was dropped, dropping the command fails the reply
+        // with `Shutdown`.
+        let sender = self.sender.upgrade()?;
+        // Registering under the lock ensures the wake up for the next free
+        // slot is not missed.
+        let mut wakers = self.waiters.0.lock().unwrap();
+        match sender.try_send(self.command) {
+            Ok(()) | Err(TrySendError::Disconnected(_)) => None,
+            Err(TrySendError::Full(command)) => {
+                wakers.push(waker.clone());
+                drop(wakers);
+                Some(Self { command, ..self })
+            }
+        }
+    }
+
+    /// Queue the command, blocking while the queue is full.
+    fn send(self) {
+        if let Some(sender) = self.sender.upgrade() {
+            let _ = sender.send(self.command);
+        }
+    }
+}
+
+/// Wakers of replies waiting for space in the queue of a [ThreadedContext].
+#[derive(Default)]
+struct QueueWaiters(Mutex<Vec<Waker>>);
+
+impl QueueWaiters {
+    /// Wake all waiting replies after a command was taken from the queue.
+    fn wake(&self) {
+        for waker in self.0.lock().unwrap().drain(..) {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> Reply<T> {
+    pub(crate) fn channel() -> (ReplySender<T>, Self) {
+        let inner = Arc::new(ReplyInner {
+            state: Mutex::new(ReplyState {
+                value: None,
+                waker: None,
+            }),
+            ready: Condvar::new(),
+        });
+        let sender = ReplySender {
+            inner: Some(inner.clone()),
+        };
+        let reply = Self {
+            inner,
+            pending: Mutex::new(None),
+        };
+        (sender, reply)
+    }
+
+    pub(crate) fn ready(value: Result<T, ThreadedError>) -> Self {
+        let (sender, reply) = Self::channel();
+        sender.send(value);
+        reply
+    }
+
+    /// Block the current thread until the result is available.
+    pub fn wait(self) -> Result<T, ThreadedError> {
+        if let Some(pending) = self.pending.lock().unwrap().take() {
+            pending.send();
+        }
+        let mut state = self.inner.state.lock().unwrap();
+        loop {
+            if let Some(value) = state.value.take() {
+                return value;
+            }
+            state = self.inner.ready.wait(state).unwrap();
+        }
+    }
+}
+
+impl<T> Future for Reply<T> {
+    type Output = Result<T, ThreadedError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Self::Output> {
+        {
+            let mut pending = self.pending.lock().unwrap();
+            if let Some(send) = pending.take() {
+                *pending = send.poll_send(cx.waker());
+                if pending.is_some() {
+                    return Poll::Pending;
+                }
+            }
+        }
+        let mut state = self.inner.state.lock().unwrap();
+        match state.value.take() {
+            Some(value) => Poll::Ready(value),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+pub(crate) enum Command {
+    Eval(String, ReplySender<JsValue>),
+    CallFunction(String, Vec<JsValue>, ReplySender<JsValue>),
+    SetGlobal(String, JsValue, ReplySender<()>),
+}
+
+impl Command {
+    /// Run the command and send the result.
+    ///
+    /// Returns `false` if the context ran out of memory.
+    pub(crate) fn run(self, context: &Context) -> bool {
+        match self {
+            Command::Eval(code, reply) => send_result(reply, context.eval(&code)),
+            Command::CallFunction(name, args, reply) => {
+                send_result(reply, context.call_function(&name, args))
+            }
+            Command::SetGlobal(name, value, reply) => {
+                send_result(reply, context.set_global(&name, value))
+            }
+        }
+    }
+}
+
+impl Command {
+    /// Fail the command without running it.
+    pub(crate) fn fail(self, error: ThreadedError) {
+        match self {
+            Command::Eval(_, reply) | Command::CallFunction(_, _, reply) => reply.send(Err(error)),
+            Command::SetGlobal(_, _, reply) => reply.send(Err(error)),
+        }
+    }
+}
+
+fn send_result<T>(reply: ReplySender<T>, result: Result<T, ExecutionError>) -> bool {
+    let healthy = !matches!(result, Err(ExecutionError::OutOfMemory));
+    reply.send(result.map_err(Into::into));
+    healthy
+}
+
+/// A [Context] that lives on a dedicated thread.
+///
+/// `Context` is bound to the thread it was created on. A `ThreadedContext`
+/// owns a context on its own thread and forwards commands to it over a
+/// bounded queue, so it is `Send + Sync` and can be shared between threads
+/// and async tasks.
+///
+/// Each method has a blocking variant, which waits while the queue is full,
+/// and an `*_async` variant, which returns a [Reply] future without
+/// blocking. If the queue is full, the future waits for space before the
+/// command is queued.
+///
+/// When the `ThreadedContext` is dropped, the running script is interrupted,
+/// queued commands fail with [ThreadedError::Shutdown] and the context
+/// thread is shut down. A callback that blocks is not interrupted.
+/// Use [ThreadedContext::shutdown] to let queued commands finish instead.
+///
+/// ```rust
+/// use quick_js::{Context, JsValue, ThreadedContext};
+///
+/// let context = ThreadedContext::new(|| {
+///     let context = Context::new()?;
+///     context.add_callback("double", |x: i32| x * 2).unwrap();
+///     Ok(context)
+/// })
+/// .unwrap();
+///
+/// let handle = std::thread::spawn(move || context.eval(" double(21) "));
+/// assert_eq!(handle.join().unwrap(), Ok(JsValue::Int(42)));
+/// ```
+pub struct ThreadedContext {
+    sender: Option<Arc<SyncSender<Command>>>,
+    waiters: Arc<QueueWaiters>,
+    /// Set on drop to interrupt the running script and skip queued commands.
+    interrupt: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ThreadedContext {
+    /// The default capacity of the command queue.
+    pub const DEFAULT_QUEUE_CAPACITY: usize = 32;
+
+    /// Spawn a context thread.
+    ///
+    /// The context is created on the new thread with the given function,
+    /// which allows adding callbacks and other setup.
+    pub fn new<F>(factory: F) -> Result<Self, ContextError>
+    where
+        F: FnOnce() -> Result<Context, ContextError> + Send + 'static,
+    {
+        Self::with_queue_capacity(Self::DEFAULT_QUEUE_CAPACITY, factory)
+    }
+
+    /// Spawn a context thread with a custom command queue capacity.
+    ///
+    /// See [ThreadedContext::new].
+    pub fn with_queue_capacity<F>(capacity: usize, factory: F) -> Result<Self, ContextError>
+    where
+        F: FnOnce() -> Result<Context, ContextError> + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::sync_channel::<Command>(capacity);
+        let (init_sender, init_receiver) = mpsc::channel();
+        let waiters = Arc::new(QueueWaiters::default());
+        let interrupt = Arc::new(AtomicBool::new(false));
+
+        let thread_waiters = waiters.clone();
+        let thread_interrupt = interrupt.clone();
+        let thread = std::thread::Builder::new()
+            .name("quick-js-context".to_string())
+            .spawn(move || {
+                let mut context = match factory() {
+                    Ok(context) => {
+                        let _ = init_sender.send(Ok(()));
+                        context
+                    }
+                    Err(e) => {
+                        let _ = init_sender.send(Err(e));
+                        return;
+                    }
+                };
+                context.wrapper.set_interrupt_flag(thread_interrupt.clone());
+                for command in receiver {
+                    thread_waiters.wake();
+                    if thread_interrupt.load(Ordering::Relaxed) {
+                        command.fail(ThreadedError::Shutdown);
+                    } else {
+                        command.run(&context);
+                    }
+                }
+            })
+            .map_err(|_| ContextError::RuntimeCreationFailed)?;
+
+        match init_receiver.recv() {
+            Ok(Ok(())) => Ok(Self {
+                sender: Some(Arc::new(sender)),
+                waiters,
+                interrupt,
+                thread: Some(thread),
+            }),
+            Ok(Err(e)) => {
+                let _ = thread.join();
+                Err(e)
+            }
+            Err(_) => {
+                let _ = thread.join();
+                Err(ContextError::ContextCreationFailed)
+            }
+        }
+    }
+
+    fn sender(&self) -> &Arc<SyncSender<Command>> {
+        // Only taken in drop.
+        self.sender.as_ref().unwrap()
+    }
+
+    /// Send a command, blocking while the queue is full.
+    fn send<T>(&self, command: Command, reply: Reply<T>) -> Result<T, ThreadedError> {
+        self.sender()
+            .send(command)
+            .map_err(|_| ThreadedError::Shutdown)?;
+        reply.wait()
+    }
+
+    /// Send a command without blocking.
+    ///
+    /// If the queue is full, the reply queues the command once there is
+    /// space.
+    fn send_async<T>(&self, command: Command, reply: Reply<T>) -> Reply<T> {
+        match self.sender().try_send(command) {
+            Ok(()) => reply,
+            Err(TrySendError::Full(command)) => {
+                *reply.pending.lock().unwrap() = Some(PendingSend {
+                    sender: Arc::downgrade(self.sender()),
+                    waiters: self.waiters.clone(),
+                    command,
+                });
+                reply
+            }
+            Err(TrySendError::Disconnected(_)) => Reply::ready(Err(ThreadedError::Shutdown)),
+        }
+    }
+
+    /// Evaluate Javascript code and wait for the result.
+    ///
+    /// See [Context::eval].
+    pub fn eval(&self, code: &str) -> Result<JsValue, ThreadedError> {
+        let (sender, reply) = Reply::channel();
+        self.send(Command::Eval(code.to_string(), sender), reply)
+    }
+
+    /// Evaluate Javascript code without blocking the current thread.
+    ///
+    /// See [Context::eval].
+    pub fn eval_async(&self, code: &str) -> Reply<JsValue> {
+        let (sender, reply) = Reply::channel();
+        self.send_async(Command::Eval(code.to_string(), sender), reply)
+    }
+
+    /// Call a global function and wait for the result.
+    ///
+    /// See [Context::call_function].
+    pub fn call_function(
+        &self,
+        function_name: &str,
+        args: impl IntoIterator<Item = impl Into<JsValue>>,
+    ) -> Result<JsValue, ThreadedError> {
+        let (sender, reply) = Reply::channel();
+        let args = args.into_iter().map(Into::into).collect();
+        self.send(
+            Command::CallFunction(function_name.to_string(), args, sender),
+            reply,
+        )
+    }
+
+    /// Call a global function without blocking the current thread.
+    ///
+    /// See [Context::call_function].
+    pub fn call_function_async(
+        &self,
+        function_name: &str,
+        args: impl IntoIterator<Item = impl Into<JsValue>>,
+    ) -> Reply<JsValue> {
+        let (sender, reply) = Reply::channel();
+        let args = args.into_iter().map(Into::into).collect();
+        self.send_async(
+            Command::CallFunction(function_name.to_string(), args, sender),
+            reply,
+        )
+    }
+
+    /// Set a global variable and wait until it is set.
+    ///
+    /// See [Context::set_global].
+    pub fn set_global<V>(&self, name: &str, value: V) -> Result<(), ThreadedError>
+    where
+        V: Into<JsValue>,
+    {
+        let (sender, reply) = Reply::channel();
+        self.send(
+            Command::SetGlobal(name.to_string(), value.into(), sender),
+            reply,
+        )
+    }
+
+    /// Set a global variable without blocking the current thread.
+    ///
+    /// See [Context::set_global].
+    pub fn set_global_async<V>(&self, name: &str, value: V) -> Reply<()>
+    where
+        V: Into<JsValue>,
+    {
+        let (sender, reply) = Reply::channel();
+        self.send_async(
+            Command::SetGlobal(name.to_string(), value.into(), sender),
+            reply,
+        )
+    }
+
+    /// Shut down the context thread.
+    ///
+    /// Waits until already queued commands have finished, unlike dropping
+    /// the context, which interrupts them.
+    /// Returns [ThreadedError::Shutdown] if the thread panicked.
+    pub fn shutdown(mut self) -> Result<(), ThreadedError> {
+        self.join()
+    }
+
+    fn join(&mut self) -> Result<(), ThreadedError> {
+        // Closing the channel ends the command loop.
+        self.sender.take();
+        match self.thread.take() {
+            Some(thread) => thread.join().map_err(|_| ThreadedError::Shutdown),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for ThreadedContext {
+    fn drop(&mut self) {
+        if self.thread.is_some() {
+            self.interrupt.store(true, Ordering::Relaxed);
+        }
+        let _ = self.join();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        sync::mpsc::Receiver,
+        task::{Wake, Waker},
+        thread::Thread,
+    };
+
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = Box::pin(future);
+        let waker = Waker::from(Arc::new(ThreadWaker(std::thread::current())));
+        let mut cx = TaskContext::from_waker(&waker);
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(value) => return value,
+                Poll::Pending => std::thread::park(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_threaded_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<ThreadedContext>();
+        assert_send_sync::<Reply<JsValue>>();
+    }
+
+    #[test]
+    fn test_threaded_blocking() {
+        let ctx = ThreadedContext::new(Context::new).unwrap();
+        ctx.set_global("x", 20).unwrap();
+        ctx.eval(" function add(a, b) { return a + b; } ").unwrap();
+        assert_eq!(ctx.eval(" x + 1 "), Ok(JsValue::Int(21)));
+        assert_eq!(ctx.call_function("add", vec![1, 2]), Ok(JsValue::Int(3)));
+        assert!(matches!(
+            ctx.eval(" throw new Error('fail') "),
+            Err(ThreadedError::Execution(ExecutionError::Exception(_)))
+        ));
+    }
+
+    #[test]
+    fn test_threaded_async() {
+        let ctx = ThreadedContext::new(Context::new).unwrap();
+        block_on(ctx.set_global_async("x", 20)).unwrap();
+        assert_eq!(block_on(ctx.eval_async(" x * 2 ")), Ok(JsValue::Int(40)));
+        assert_eq!(
+            block_on(ctx.call_function_async("parseInt", vec!["7"])),
+            Ok(JsValue::Int(7))
+        );
+    }
+
+    #[test]
+    fn test_threaded_shared_between_threads() {
+        let ctx = Arc::new(ThreadedContext::new(Context::new).unwrap());
+        ctx.eval(" var counter = 0; ").unwrap();
+
+        let handles = (0..4)
+            .map(|_| {
+                let ctx = ctx.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..10 {
+                        ctx.eval(" counter += 1 ").unwrap();
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(ctx.eval(" counter "), Ok(JsValue::Int(40)));
+    }
+
+    /// Build a context with a `block` callback that signals `started` and
+    /// waits for `release`.
+    fn blocking_context(capacity: usize) -> (ThreadedContext, Receiver<()>, mpsc::Sender<()>) {
+        let (started_sender, started) = mpsc::channel::<()>();
+        let (release, wait) = mpsc::channel::<()>();
+        let started_sender = Mutex::new(started_sender);
+        let wait = Mutex::new(wait);
+        let ctx = ThreadedContext::with_queue_capacity(capacity, move || {
+            let context = Context::new()?;
+            context
+                .add_callback("block", move || {
+                    started_sender.lock().unwrap().send(()).unwrap();
+                    let wait: &Receiver<()> = &wait.lock().unwrap();
+                    wait.recv().unwrap();
+                    true
+                })
+                .unwrap();
+            Ok(context)
+        })
+        .unwrap();
+        (ctx, started, release)
+    }
+
+    #[test]
+    fn test_threaded_async_waits_for_capacity() {
+        let (ctx, started, release) = blocking_context(1);
+
+        // The first command blocks the thread, the second fills the queue.
+        let first = ctx.eval_async(" block() ");
+        started.recv().unwrap();
+        let second = ctx.eval_async(" 1 ");
+        let third = ctx.eval_async(" 2 ");
+        let fourth = ctx.eval_async(" 3 ");
+
+        let waiting = std::thread::spawn(move || block_on(third));
+        release.send(()).unwrap();
+        assert_eq!(first.wait(), Ok(JsValue::Bool(true)));
+        assert_eq!(second.wait(), Ok(JsValue::Int(1)));
+        assert_eq!(waiting.join().unwrap(), Ok(JsValue::Int(2)));
+        assert_eq!(fourth.wait(), Ok(JsValue::Int(3)));
+    }
+
+    #[test]
+    fn test_threaded_drop_interrupts_script() {
+        let (ctx, started, release) = blocking_context(4);
+
+        let running = ctx.eval_async(" block(); while (true) {} ");
+        started.recv().unwrap();
+        let queued = ctx.eval_async(" 1 ");
+        release.send(()).unwrap();
+        drop(ctx);
+
+        assert!(matches!(
+            running.wait(),
+            Err(ThreadedError::Execution(ExecutionError::Exception(_)))
+        ));
+        assert_eq!(queued.wait(), Err(ThreadedError::Shutdown));
+    }
+
+    #[test]
+    fn test_threaded_drop_interrupts_pending_promise() {
+        let (ctx, started, release) = blocking_context(4);
+
+        // Keeps the job queue busy without ever settling.
+        let running = ctx.eval_async(
+            r#"
+                block();
+                new Promise(() => {
+                    const spin = () => Promise.resolve().then(spin);
+                    spin();
+                })
+            "#,
+        );
+        started.recv().unwrap();
+        release.send(()).unwrap();
+        drop(ctx);
+
+        assert!(matches!(running.wait(), Err(ThreadedError::Execution(_))));
+    }
+
+    #[test]
+    fn test_threaded_factory_error() {
+        let res = ThreadedContext::new(|| Err(ContextError::ContextCreationFailed));
+        assert!(matches!(res, Err(ContextError::ContextCreationFailed)));
+    }
+
+    #[test]
+    fn test_threaded_shutdown() {
+        let ctx = ThreadedContext::new(Context::new).unwrap();
+        let pending = ctx.eval_async(" 1 + 1 ");
+        ctx.shutdown().unwrap();
+        // Queued commands finish before the thread exits.
+        assert_eq!(pending.wait(), Ok(JsValue::Int(2)));
+    }
+}
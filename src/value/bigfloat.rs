@@ -0,0 +1,236 @@
+use std::{fmt, str::FromStr};
+
+use bigdecimal::BigDecimal;
+use num_traits::ToPrimitive;
+
+#[derive(Clone, Debug)]
+enum BigFloatInner {
+    Finite(BigDecimal),
+    Infinity { negative: bool },
+    NaN,
+}
+
+/// A value holding a QuickJS `BigFloat`, an arbitrary precision binary
+/// floating point number written with the `l` suffix, like `0.5l`.
+///
+/// Finite values are stored exactly as a `BigDecimal`. Converting a value to
+/// Javascript rounds it to the precision of the runtime, 113 bits by default.
+/// Like `f64`, a `BigFloat` can also be infinite or `NaN`, and `NaN` is not
+/// equal to itself. The sign of zero is not preserved.
+#[derive(Clone, Debug)]
+pub struct BigFloat {
+    inner: BigFloatInner,
+}
+
+impl BigFloat {
+    /// Create a `NaN` value.
+    pub fn nan() -> Self {
+        BigFloat {
+            inner: BigFloatInner::NaN,
+        }
+    }
+
+    /// Create a positive or negative infinite value.
+    pub fn infinity(negative: bool) -> Self {
+        BigFloat {
+            inner: BigFloatInner::Infinity { negative },
+        }
+    }
+
+    /// Returns `true` if the value is `NaN`.
+    pub fn is_nan(&self) -> bool {
+        matches!(self.inner, BigFloatInner::NaN)
+    }
+
+    /// Returns `true` if the value is positive or negative infinity.
+    pub fn is_infinite(&self) -> bool {
+        matches!(self.inner, BigFloatInner::Infinity { .. })
+    }
+
+    /// Return the value if it is finite and `None` otherwise.
+    pub fn as_decimal(&self) -> Option<&BigDecimal> {
+        match &self.inner {
+            BigFloatInner::Finite(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Convert to the nearest `f64`.
+    pub fn to_f64(&self) -> f64 {
+        match &self.inner {
+            BigFloatInner::Finite(value) => value.to_f64().unwrap_or(f64::NAN),
+            BigFloatInner::Infinity { negative: false } => f64::INFINITY,
+            BigFloatInner::Infinity { negative: true } => f64::NEG_INFINITY,
+            BigFloatInner::NaN => f64::NAN,
+        }
+    }
+
+    /// Convert a finite value into a `BigDecimal`.
+    pub fn into_decimal(self) -> Option<BigDecimal> {
+        match self.inner {
+            BigFloatInner::Finite(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl PartialEq for BigFloat {
+    fn eq(&self, other: &Self) -> bool {
+        use BigFloatInner::*;
+        match (&self.inner, &other.inner) {
+            (Finite(a), Finite(b)) => a == b,
+            (Infinity { negative: a }, Infinity { negative: b }) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl From<BigDecimal> for BigFloat {
+    fn from(value: BigDecimal) -> Self {
+        BigFloat {
+            inner: BigFloatInner::Finite(value),
+        }
+    }
+}
+
+impl From<f64> for BigFloat {
+    fn from(value: f64) -> Self {
+        if value.is_nan() {
+            BigFloat::nan()
+        } else if value.is_infinite() {
+            BigFloat::infinity(value < 0.0)
+        } else {
+            // Every finite f64 has an exact decimal representation:
+            // m * 2^-e == m * 5^e / 10^e.
+            let bits = value.to_bits();
+            let negative = bits >> 63 != 0;
+            let exponent = ((bits >> 52) & 0x7ff) as i64;
+            let fraction = bits & ((1 << 52) - 1);
+            let (mantissa, exponent) = if exponent == 0 {
+                (fraction, -1074)
+            } else {
+                (fraction | (1 << 52), exponent - 1075)
+            };
+            let mut mantissa = num_bigint::BigInt::from(mantissa);
+            if negative {
+                mantissa = -mantissa;
+            }
+            binary_to_decimal(mantissa, exponent).into()
+        }
+    }
+}
+
+/// Compute `mantissa * 2^exponent` exactly, using `m * 2^-e == m * 5^e / 10^e`.
+fn binary_to_decimal(mut mantissa: num_bigint::BigInt, mut exponent: i64) -> BigDecimal {
+    // An odd mantissa avoids trailing zeros in the decimal.
+    let zero = num_bigint::BigInt::from(0);
+    while exponent < 0 && mantissa != zero && (&mantissa % 2) == zero {
+        mantissa >>= 1;
+        exponent += 1;
+    }
+    if exponent >= 0 {
+        BigDecimal::new(mantissa << exponent as usize, 0)
+    } else {
+        let scale = -exponent;
+        let five = num_bigint::BigInt::from(5);
+        BigDecimal::new(mantissa * num_traits::pow(five, scale as usize), scale)
+    }
+}
+
+/// Parse the output of `BigFloat.prototype.toString(16)`, like
+/// `-1.8p-3`, which represents the value exactly.
+pub(crate) fn parse_hex(s: &str) -> Option<BigFloat> {
+    match s {
+        "NaN" => return Some(BigFloat::nan()),
+        "Infinity" => return Some(BigFloat::infinity(false)),
+        "-Infinity" => return Some(BigFloat::infinity(true)),
+        _ => {}
+    }
+    let (negative, s) = match s.strip_prefix('-') {
+        Some(s) => (true, s),
+        None => (false, s),
+    };
+    let (digits, exponent) = match s.find('p') {
+        Some(index) => (&s[..index], s[index + 1..].parse::<i64>().ok()?),
+        None => (s, 0),
+    };
+    let (integer, fraction) = match digits.find('.') {
+        Some(index) => (&digits[..index], &digits[index + 1..]),
+        None => (digits, ""),
+    };
+    let hex = format!("{}{}", integer, fraction);
+    let mut mantissa = num_bigint::BigInt::parse_bytes(hex.as_bytes(), 16)?;
+    if negative {
+        mantissa = -mantissa;
+    }
+    let exponent = exponent.checked_sub(4 * fraction.len() as i64)?;
+    Some(binary_to_decimal(mantissa, exponent).into())
+}
+
+/// Formats the value like Javascript, with `Infinity` and `NaN` for non
+/// finite values.
+impl fmt::Display for BigFloat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.inner {
+            BigFloatInner::Finite(value) => write!(f, "{}", value),
+            BigFloatInner::Infinity { negative: false } => write!(f, "Infinity"),
+            BigFloatInner::Infinity { negative: true } => write!(f, "-Infinity"),
+            BigFloatInner::NaN => write!(f, "NaN"),
+        }
+    }
+}
+
+/// Parses the Javascript string representation of a `BigFloat`.
+impl FromStr for BigFloat {
+    type Err = bigdecimal::ParseBigDecimalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "NaN" => Ok(BigFloat::nan()),
+            "Infinity" => Ok(BigFloat::infinity(false)),
+            "-Infinity" => Ok(BigFloat::infinity(true)),
+            s => BigDecimal::from_str(s).map(BigFloat::from),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bigfloat_from_f64() {
+        assert_eq!(
+            BigFloat::from(0.5),
+            BigFloat::from(BigDecimal::from_str("0.5").unwrap())
+        );
+        assert_eq!(
+            BigFloat::from(0.1).into_decimal().unwrap(),
+            BigDecimal::from_str("0.1000000000000000055511151231257827021181583404541015625")
+                .unwrap()
+        );
+        assert_eq!(BigFloat::from(-0.1).to_f64(), -0.1);
+        assert!(BigFloat::from(f64::NAN).is_nan());
+        assert_ne!(BigFloat::nan(), BigFloat::nan());
+        assert_eq!(BigFloat::from(f64::NEG_INFINITY), BigFloat::infinity(true));
+    }
+
+    #[test]
+    fn test_bigfloat_parse_hex() {
+        let parse = |s| parse_hex(s).unwrap().to_string();
+        assert_eq!(parse("3"), "3");
+        assert_eq!(parse("-1.8p-3"), "-0.1875");
+        assert_eq!(parse("0.a"), "0.625");
+        assert_eq!(parse("1p+4"), "16");
+        assert!(parse_hex("-Infinity").unwrap().is_infinite());
+        assert!(parse_hex("0.x").is_none());
+    }
+
+    #[test]
+    fn test_bigfloat_string() {
+        for s in ["1.25", "-3", "NaN", "Infinity", "-Infinity"] {
+            assert_eq!(s.parse::<BigFloat>().unwrap().to_string(), s);
+        }
+        assert!("1.2.3".parse::<BigFloat>().is_err());
+    }
+}
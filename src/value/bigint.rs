@@ -34,6 +34,27 @@ impl BigInt {
             BigIntOrI64::BigInt(bigint) => bigint.to_i64(),
         }
     }
+    /// Return `Some` if value fits into `i128` and `None` otherwise
+    pub fn as_i128(&self) -> Option<i128> {
+        match &self.inner {
+            BigIntOrI64::Int(int) => Some(*int as i128),
+            BigIntOrI64::BigInt(bigint) => bigint.to_i128(),
+        }
+    }
+    /// Return `Some` if value fits into `u64` and `None` otherwise
+    pub fn as_u64(&self) -> Option<u64> {
+        match &self.inner {
+            BigIntOrI64::Int(int) => int.to_u64(),
+            BigIntOrI64::BigInt(bigint) => bigint.to_u64(),
+        }
+    }
+    /// Return `Some` if value fits into `u128` and `None` otherwise
+    pub fn as_u128(&self) -> Option<u128> {
+        match &self.inner {
+            BigIntOrI64::Int(int) => int.to_u128(),
+            BigIntOrI64::BigInt(bigint) => bigint.to_u128(),
+        }
+    }
     /// Convert value into `num_bigint::BigInt`
     pub fn into_bigint(self) -> num_bigint::BigInt {
         match self.inner {
@@ -62,8 +83,115 @@ impl From<i64> for BigInt {
 
 impl From<num_bigint::BigInt> for BigInt {
     fn from(bigint: num_bigint::BigInt) -> Self {
-        BigInt {
-            inner: BigIntOrI64::BigInt(bigint),
+        let inner = match bigint.to_i64() {
+            Some(int) => BigIntOrI64::Int(int),
+            None => BigIntOrI64::BigInt(bigint),
+        };
+        BigInt { inner }
+    }
+}
+
+macro_rules! bigint_from_integer {
+    ($($ty:ty),*) => {
+        $(
+            impl From<$ty> for BigInt {
+                fn from(int: $ty) -> Self {
+                    num_bigint::BigInt::from(int).into()
+                }
+            }
+        )*
+    };
+}
+
+bigint_from_integer!(u64, i128, u128, isize, usize);
+
+macro_rules! bigint_from_small_integer {
+    ($($ty:ty),*) => {
+        $(
+            impl From<$ty> for BigInt {
+                fn from(int: $ty) -> Self {
+                    BigInt::from(int as i64)
+                }
+            }
+        )*
+    };
+}
+
+bigint_from_small_integer!(i8, i16, i32, u8, u16, u32);
+
+/// Implements a binary operator, using checked `i64` arithmetic while both
+/// operands are small and falling back to `num_bigint` otherwise.
+///
+/// Like the primitive integer types, division and remainder panic if the
+/// divisor is zero.
+macro_rules! bigint_binary_op {
+    ($($trait:ident $method:ident $checked:ident),*) => {
+        $(
+            impl std::ops::$trait for BigInt {
+                type Output = BigInt;
+
+                fn $method(self, other: BigInt) -> BigInt {
+                    if let (BigIntOrI64::Int(a), BigIntOrI64::Int(b)) = (&self.inner, &other.inner) {
+                        if let Some(int) = a.$checked(*b) {
+                            return int.into();
+                        }
+                    }
+                    std::ops::$trait::$method(self.into_bigint(), other.into_bigint()).into()
+                }
+            }
+
+            impl<'a> std::ops::$trait<&'a BigInt> for &'a BigInt {
+                type Output = BigInt;
+
+                fn $method(self, other: &'a BigInt) -> BigInt {
+                    std::ops::$trait::$method(self.clone(), other.clone())
+                }
+            }
+
+            impl std::ops::$trait<i64> for BigInt {
+                type Output = BigInt;
+
+                fn $method(self, other: i64) -> BigInt {
+                    std::ops::$trait::$method(self, BigInt::from(other))
+                }
+            }
+        )*
+    };
+}
+
+bigint_binary_op!(
+    Add add checked_add,
+    Sub sub checked_sub,
+    Mul mul checked_mul,
+    Div div checked_div,
+    Rem rem checked_rem
+);
+
+impl std::ops::Neg for BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        match self.inner {
+            BigIntOrI64::Int(int) => match int.checked_neg() {
+                Some(int) => int.into(),
+                None => (-num_bigint::BigInt::from(int)).into(),
+            },
+            BigIntOrI64::BigInt(bigint) => (-bigint).into(),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        match (&self.inner, &other.inner) {
+            (BigIntOrI64::Int(a), BigIntOrI64::Int(b)) => a.cmp(b),
+            _ => self.clone().into_bigint().cmp(&other.clone().into_bigint()),
         }
     }
 }
@@ -84,19 +212,19 @@ mod tests {
     #[test]
     fn test_bigint_as_i64_overflow() {
         let value = BigInt {
-            inner: BigIntOrI64::BigInt(num_bigint::BigInt::from(std::i128::MAX)),
+            inner: BigIntOrI64::BigInt(num_bigint::BigInt::from(i128::MAX)),
         };
         assert_eq!(value.as_i64(), None);
     }
 
     #[test]
     fn test_bigint_into_bigint() {
-        for i in vec![
-            0 as i128,
-            std::i64::MAX as i128,
-            std::i64::MIN as i128,
-            std::i128::MAX,
-            std::i128::MIN,
+        for i in [
+            0i128,
+            i64::MAX as i128,
+            i64::MIN as i128,
+            i128::MAX,
+            i128::MIN,
         ] {
             let value = BigInt {
                 inner: BigIntOrI64::BigInt(num_bigint::BigInt::from(i)),
@@ -104,4 +232,35 @@ mod tests {
             assert_eq!(value.into_bigint(), num_bigint::BigInt::from(i));
         }
     }
+
+    #[test]
+    fn test_bigint_from_128() {
+        assert_eq!(BigInt::from(12i128).inner, BigIntOrI64::Int(12));
+        assert_eq!(BigInt::from(i128::MIN).as_i128(), Some(i128::MIN));
+        assert_eq!(BigInt::from(u128::MAX).as_u128(), Some(u128::MAX));
+        assert_eq!(BigInt::from(u128::MAX).as_i128(), None);
+        assert_eq!(BigInt::from(u64::MAX).as_u64(), Some(u64::MAX));
+        assert_eq!(BigInt::from(-1).as_u64(), None);
+    }
+
+    #[test]
+    fn test_bigint_arithmetic() {
+        let max = BigInt::from(i64::MAX);
+        let sum = max.clone() + 1;
+        assert_eq!(sum.as_i128(), Some(i64::MAX as i128 + 1));
+        assert_eq!((sum - 1).inner, BigIntOrI64::Int(i64::MAX));
+        assert_eq!(
+            (&max * &max).as_i128(),
+            Some(i64::MAX as i128 * i64::MAX as i128)
+        );
+        assert_eq!(BigInt::from(7) / BigInt::from(-2), BigInt::from(-3));
+        assert_eq!(BigInt::from(-7) % BigInt::from(2), BigInt::from(-1));
+        assert_eq!(
+            (-BigInt::from(i64::MIN)).as_i128(),
+            Some(-(i64::MIN as i128))
+        );
+        assert_eq!(BigInt::from(i64::MIN) / -1, -BigInt::from(i64::MIN));
+        assert!(BigInt::from(u128::MAX) > max);
+        assert!(BigInt::from(-2) < BigInt::from(1));
+    }
 }
@@ -0,0 +1,108 @@
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use crate::{Context, ExecutionError, JsValue};
+
+/// Ids of dropped function handles, freed by the context they belong to.
+pub(crate) type ReleaseQueue = Arc<Mutex<Vec<u64>>>;
+
+struct Handle {
+    id: u64,
+    released: ReleaseQueue,
+}
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        // The function is freed by its context, which may live on another
+        // thread.
+        if let Ok(mut released) = self.released.lock() {
+            released.push(self.id);
+        }
+    }
+}
+
+/// A handle to a Javascript function.
+///
+/// Functions passed to callbacks or returned from `eval` are converted to a
+/// `JsFunction`. The handle keeps the function alive until the last clone of
+/// the handle is dropped, so it can be stored and called later.
+///
+/// A function can only be called with the context it was created in.
+/// Handles are invalidated when the context is reset.
+///
+/// ```rust
+/// use std::sync::{Arc, Mutex};
+/// use quick_js::{Context, JsFunction, JsValue};
+///
+/// let context = Context::new().unwrap();
+/// let listeners = Arc::new(Mutex::new(Vec::<JsFunction>::new()));
+///
+/// let subscribe_listeners = listeners.clone();
+/// context
+///     .add_callback("subscribe", move |listener: JsFunction| {
+///         subscribe_listeners.lock().unwrap().push(listener);
+///         JsValue::Undefined
+///     })
+///     .unwrap();
+/// context.eval(" subscribe(event => 'got ' + event) ").unwrap();
+///
+/// for listener in listeners.lock().unwrap().iter() {
+///     let res = listener.call(&context, vec!["click"]).unwrap();
+///     assert_eq!(res, JsValue::String("got click".into()));
+/// }
+/// ```
+#[derive(Clone)]
+pub struct JsFunction {
+    handle: Arc<Handle>,
+}
+
+impl JsFunction {
+    pub(crate) fn new(id: u64, released: ReleaseQueue) -> Self {
+        Self {
+            handle: Arc::new(Handle { id, released }),
+        }
+    }
+
+    pub(crate) fn id(&self) -> u64 {
+        self.handle.id
+    }
+
+    /// Check if this function belongs to the registry with the given queue.
+    pub(crate) fn belongs_to(&self, released: &ReleaseQueue) -> bool {
+        Arc::ptr_eq(&self.handle.released, released)
+    }
+
+    /// Call the function with the given arguments.
+    ///
+    /// Fails if the function belongs to a different context or the context
+    /// was reset.
+    /// Like [Context::call_function], a returned Promise is resolved.
+    pub fn call(
+        &self,
+        context: &Context,
+        args: impl IntoIterator<Item = impl Into<JsValue>>,
+    ) -> Result<JsValue, ExecutionError> {
+        let qargs = args
+            .into_iter()
+            .map(|arg| context.wrapper.serialize_value(arg.into()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let func = context.wrapper.function_value(self)?.try_into_function()?;
+        let v = context.wrapper.call_function(func, qargs)?.to_value()?;
+        Ok(v)
+    }
+}
+
+impl PartialEq for JsFunction {
+    fn eq(&self, other: &Self) -> bool {
+        self.id() == other.id() && self.belongs_to(&other.handle.released)
+    }
+}
+
+impl fmt::Debug for JsFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("JsFunction").field(&self.id()).finish()
+    }
+}
@@ -0,0 +1,691 @@
+use std::fmt::{self, Write};
+
+use super::{JsValue, Symbol, TypedArray};
+
+/// Marker for a reference to an object from within itself, left by lossy
+/// conversions of values that are only displayed.
+///
+/// Starts with a Unicode noncharacter, which is reserved for internal use, so
+/// it is not mistaken for a string of a script.
+pub(crate) const CIRCULAR: &str = "\u{fdd0}[Circular]";
+
+/// Options for [JsValue::inspect], modeled on the options of Node's
+/// `util.inspect`.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct InspectOptions {
+    /// The number of nesting levels to show. Deeper arrays, objects and
+    /// collections are shown as `[Object]`, `[Array]` and so on.
+    /// `None` shows all levels.
+    pub depth: Option<usize>,
+    /// Style the output with ANSI color codes.
+    pub colors: bool,
+    /// Put the entries of an array, object or collection on a single line
+    /// if they fit into `break_length`. Otherwise every entry gets its own
+    /// line.
+    pub compact: bool,
+    /// The line length at which entries are split into multiple lines.
+    pub break_length: usize,
+    /// The number of entries of arrays, collections and buffers to show.
+    /// `None` shows all entries.
+    pub max_array_length: Option<usize>,
+    /// The number of characters of strings to show.
+    /// `None` shows the whole string.
+    pub max_string_length: Option<usize>,
+}
+
+impl Default for InspectOptions {
+    fn default() -> Self {
+        Self {
+            depth: Some(2),
+            colors: false,
+            compact: true,
+            break_length: 80,
+            max_array_length: Some(100),
+            max_string_length: Some(10_000),
+        }
+    }
+}
+
+impl JsValue {
+    /// Format the value for humans, like Node's `util.inspect`.
+    ///
+    /// Strings are quoted, and nested values are limited and laid out as
+    /// configured by the options.
+    ///
+    /// ```rust
+    /// use quick_js::{Context, InspectOptions};
+    ///
+    /// let context = Context::new().unwrap();
+    /// let value = context.eval(" ({ a: [1, 'two'], 'b-c': { d: { e: {} } } }) ").unwrap();
+    ///
+    /// assert_eq!(
+    ///     value.inspect(&InspectOptions::default()),
+    ///     "{ a: [ 1, 'two' ], 'b-c': { d: { e: {} } } }"
+    /// );
+    /// let options = InspectOptions {
+    ///     depth: Some(0),
+    ///     compact: false,
+    ///     ..InspectOptions::default()
+    /// };
+    /// assert_eq!(value.inspect(&options), "{\n  a: [Array],\n  'b-c': [Object]\n}");
+    /// ```
+    pub fn inspect(&self, options: &InspectOptions) -> String {
+        Inspector { options }.value(self, 0)
+    }
+}
+
+/// Styles of the colored output, with the colors used by Node.
+#[derive(Clone, Copy)]
+enum Style {
+    Number,
+    String,
+    Undefined,
+    Null,
+    Special,
+    #[cfg(feature = "chrono")]
+    Date,
+}
+
+impl Style {
+    fn codes(self) -> (u8, u8) {
+        match self {
+            Style::Number => (33, 39),
+            Style::String => (32, 39),
+            Style::Undefined => (90, 39),
+            Style::Null => (1, 22),
+            Style::Special => (36, 39),
+            #[cfg(feature = "chrono")]
+            Style::Date => (35, 39),
+        }
+    }
+}
+
+struct Inspector<'a> {
+    options: &'a InspectOptions,
+}
+
+impl<'a> Inspector<'a> {
+    fn style(&self, text: String, style: Style) -> String {
+        if self.options.colors {
+            let (start, end) = style.codes();
+            format!("\x1b[{}m{}\x1b[{}m", start, text, end)
+        } else {
+            text
+        }
+    }
+
+    fn value(&self, value: &JsValue, level: usize) -> String {
+        if matches!(self.options.depth, Some(depth) if level > depth) {
+            if let Some(name) = container_name(value) {
+                return self.style(format!("[{}]", name), Style::Special);
+            }
+        }
+
+        match value {
+            JsValue::Undefined => self.style("undefined".into(), Style::Undefined),
+            JsValue::Null => self.style("null".into(), Style::Null),
+            JsValue::Bool(v) => self.style(v.to_string(), Style::Number),
+            JsValue::Int(v) => self.style(v.to_string(), Style::Number),
+            JsValue::Float(v) => self.style(number_to_string(*v), Style::Number),
+            JsValue::String(v) if v == CIRCULAR => self.style("[Circular]".into(), Style::Special),
+            JsValue::String(v) => self.string(v),
+            JsValue::Array(values) => self.group(
+                "",
+                ("[", "]"),
+                self.entries(values.iter().map(|value| self.value(value, level + 1))),
+                level,
+            ),
+            JsValue::Object(map) => {
+                let entries = map
+                    .iter()
+                    .map(|(key, value)| {
+                        format!("{}: {}", self.key(key), self.value(value, level + 1))
+                    })
+                    .collect();
+                self.group("", ("{", "}"), entries, level)
+            }
+            JsValue::Map(entries) => {
+                let prefix = format!("Map({}) ", entries.len());
+                let entries = self.entries(entries.iter().map(|(key, value)| {
+                    format!(
+                        "{} => {}",
+                        self.value(key, level + 1),
+                        self.value(value, level + 1)
+                    )
+                }));
+                self.group(&prefix, ("{", "}"), entries, level)
+            }
+            JsValue::Set(values) => {
+                let prefix = format!("Set({}) ", values.len());
+                let entries = self.entries(values.iter().map(|value| self.value(value, level + 1)));
+                self.group(&prefix, ("{", "}"), entries, level)
+            }
+            JsValue::Symbol(v) => self.style(
+                format!("Symbol({})", v.description().unwrap_or_default()),
+                Style::String,
+            ),
+            JsValue::Function(_) => self.style("[Function]".into(), Style::Special),
+            JsValue::Bytes(bytes) => {
+                let max = self.options.max_array_length.unwrap_or(bytes.len());
+                let mut contents = bytes
+                    .iter()
+                    .take(max)
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                if bytes.len() > max {
+                    let remaining = bytes.len() - max;
+                    let plural = if remaining > 1 { "s" } else { "" };
+                    write!(contents, " ... {} more byte{}", remaining, plural).unwrap();
+                }
+                let entries = vec![
+                    format!("[Uint8Contents]: <{}>", contents),
+                    format!(
+                        "byteLength: {}",
+                        self.style(bytes.len().to_string(), Style::Number)
+                    ),
+                ];
+                self.group("ArrayBuffer ", ("{", "}"), entries, level)
+            }
+            JsValue::TypedArray(array) => {
+                let prefix = format!("{}({}) ", array.constructor_name(), array.len());
+                let entries = self.entries(
+                    typed_array_elements(array)
+                        .into_iter()
+                        .map(|element| self.style(element, Style::Number)),
+                );
+                self.group(&prefix, ("[", "]"), entries, level)
+            }
+            #[cfg(feature = "chrono")]
+            JsValue::Date(v) => self.style(
+                v.to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+                Style::Date,
+            ),
+            #[cfg(feature = "bigint")]
+            JsValue::BigInt(v) => self.style(format!("{}n", v), Style::Number),
+            #[cfg(feature = "bignum")]
+            JsValue::BigDecimal(_) | JsValue::BigFloat(_) => {
+                self.style(value.to_string(), Style::Number)
+            }
+            JsValue::__NonExhaustive => unreachable!(),
+        }
+    }
+
+    fn string(&self, value: &str) -> String {
+        let (value, remaining) = match self.options.max_string_length {
+            Some(max) => match value.char_indices().nth(max) {
+                Some((index, _)) => (&value[..index], value[index..].chars().count()),
+                None => (value, 0),
+            },
+            None => (value, 0),
+        };
+        let mut quoted = self.style(quote_string(value), Style::String);
+        if remaining > 0 {
+            write!(quoted, "... {} more character", remaining).unwrap();
+            if remaining > 1 {
+                quoted.push('s');
+            }
+        }
+        quoted
+    }
+
+    fn key(&self, key: &str) -> String {
+        if is_identifier(key, false) {
+            key.to_string()
+        } else {
+            self.style(quote_string(key), Style::String)
+        }
+    }
+
+    /// Collect the entries of a list, limited to `max_array_length`.
+    fn entries(&self, entries: impl ExactSizeIterator<Item = String>) -> Vec<String> {
+        let len = entries.len();
+        let max = self.options.max_array_length.unwrap_or(len);
+        let mut entries = entries.take(max).collect::<Vec<_>>();
+        if len > max {
+            let remaining = len - max;
+            let plural = if remaining > 1 { "s" } else { "" };
+            entries.push(format!("... {} more item{}", remaining, plural));
+        }
+        entries
+    }
+
+    /// Lay out the entries of a container on one or multiple lines.
+    fn group(
+        &self,
+        prefix: &str,
+        (open, close): (&str, &str),
+        entries: Vec<String>,
+        level: usize,
+    ) -> String {
+        if entries.is_empty() {
+            return format!("{}{}{}", prefix, open, close);
+        }
+        if self.options.compact && entries.iter().all(|entry| !entry.contains('\n')) {
+            let line = format!("{}{} {} {}", prefix, open, entries.join(", "), close);
+            if 2 * level + visible_len(&line) <= self.options.break_length {
+                return line;
+            }
+        }
+        let entries = entries
+            .iter()
+            .map(|entry| entry.replace('\n', "\n  "))
+            .collect::<Vec<_>>();
+        format!("{}{}\n  {}\n{}", prefix, open, entries.join(",\n  "), close)
+    }
+}
+
+/// The name shown for a container that is nested too deep, or `None` for
+/// values that are shown in full.
+fn container_name(value: &JsValue) -> Option<&'static str> {
+    match value {
+        JsValue::Array(values) if !values.is_empty() => Some("Array"),
+        JsValue::Object(map) if !map.is_empty() => Some("Object"),
+        JsValue::Map(entries) if !entries.is_empty() => Some("Map"),
+        JsValue::Set(values) if !values.is_empty() => Some("Set"),
+        JsValue::Bytes(_) => Some("ArrayBuffer"),
+        JsValue::TypedArray(array) if !array.is_empty() => Some(array.constructor_name()),
+        _ => None,
+    }
+}
+
+/// Whether an object key can be written without quotes.
+///
+/// Like Node, `util.inspect` quotes keys containing `$`.
+fn is_identifier(key: &str, allow_dollar: bool) -> bool {
+    let valid = |c: char| c.is_ascii_alphabetic() || c == '_' || (allow_dollar && c == '$');
+    let mut chars = key.chars();
+    match chars.next() {
+        Some(first) if valid(first) => chars.all(|c| valid(c) || c.is_ascii_digit()),
+        _ => false,
+    }
+}
+
+/// The length of a string without ANSI color codes.
+fn visible_len(text: &str) -> usize {
+    let mut len = 0;
+    let mut in_escape = false;
+    for c in text.chars() {
+        match c {
+            '\x1b' => in_escape = true,
+            'm' if in_escape => in_escape = false,
+            _ if in_escape => {}
+            _ => len += 1,
+        }
+    }
+    len
+}
+
+/// Quote a string with single quotes like Node, or with double quotes or
+/// backticks if that avoids escaping.
+fn quote_string(value: &str) -> String {
+    let quote = if !value.contains('\'') {
+        '\''
+    } else if !value.contains('"') {
+        '"'
+    } else if !value.contains('`') && !value.contains("${") {
+        '`'
+    } else {
+        '\''
+    };
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push(quote);
+    escape_string(&mut quoted, value, quote);
+    quoted.push(quote);
+    quoted
+}
+
+/// Escape a string for a Javascript string literal with the given quote.
+fn escape_string(out: &mut String, value: &str, quote: char) {
+    for c in value.chars() {
+        match c {
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{c}' => out.push_str("\\f"),
+            '\u{b}' => out.push_str("\\v"),
+            '\\' => out.push_str("\\\\"),
+            c if c == quote => {
+                out.push('\\');
+                out.push(c);
+            }
+            c if c.is_ascii_control() => write!(out, "\\x{:02X}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+}
+
+/// Format a number like Javascript's `Number.prototype.toString`, except
+/// that negative zero is formatted as `-0`.
+fn number_to_string(value: f64) -> String {
+    if value.is_nan() {
+        return "NaN".into();
+    }
+    if value.is_infinite() {
+        return if value > 0.0 { "Infinity" } else { "-Infinity" }.into();
+    }
+    if value == 0.0 {
+        return if value.is_sign_negative() { "-0" } else { "0" }.into();
+    }
+
+    // The shortest representation that round-trips, like `1.5e-7`.
+    let exponential = format!("{:e}", value.abs());
+    let index = exponential.find('e').unwrap();
+    let digits = exponential[..index].replace('.', "");
+    let exponent: i32 = exponential[index + 1..].parse().unwrap();
+
+    // The value is 0.digits * 10^n.
+    let k = digits.len() as i32;
+    let n = exponent + 1;
+    let sign = if value < 0.0 { "-" } else { "" };
+    let formatted = if k <= n && n <= 21 {
+        format!("{}{}", digits, "0".repeat((n - k) as usize))
+    } else if 0 < n && n <= 21 {
+        format!("{}.{}", &digits[..n as usize], &digits[n as usize..])
+    } else if -6 < n && n <= 0 {
+        format!("0.{}{}", "0".repeat(-n as usize), digits)
+    } else {
+        let exponent_sign = if n > 0 { "+" } else { "-" };
+        let mantissa = if k == 1 {
+            digits
+        } else {
+            format!("{}.{}", &digits[..1], &digits[1..])
+        };
+        format!("{}e{}{}", mantissa, exponent_sign, (n - 1).abs())
+    };
+    format!("{}{}", sign, formatted)
+}
+
+/// Format the elements of a typed array as Javascript numbers.
+fn typed_array_elements(array: &TypedArray) -> Vec<String> {
+    fn format_all<T: ToString>(values: &[T]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+    fn format_bigints<T: ToString>(values: &[T]) -> Vec<String> {
+        values
+            .iter()
+            .map(|value| format!("{}n", value.to_string()))
+            .collect()
+    }
+
+    use TypedArray::*;
+    match array {
+        Int8(v) => format_all(v),
+        Uint8(v) | Uint8Clamped(v) => format_all(v),
+        Int16(v) => format_all(v),
+        Uint16(v) => format_all(v),
+        Int32(v) => format_all(v),
+        Uint32(v) => format_all(v),
+        Float32(v) => v.iter().map(|v| number_to_string(f64::from(*v))).collect(),
+        Float64(v) => v.iter().map(|v| number_to_string(*v)).collect(),
+        BigInt64(v) => format_bigints(v),
+        BigUint64(v) => format_bigints(v),
+    }
+}
+
+/// Formats the value in Javascript literal syntax, like
+/// `{a: [1, "two"], "b-c": new Map([[1, 2]])}`.
+///
+/// Functions can not be written as literals and are formatted as
+/// `[Function]`.
+impl fmt::Display for JsValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn list<'a>(
+            f: &mut fmt::Formatter<'_>,
+            values: impl IntoIterator<Item = &'a JsValue>,
+        ) -> fmt::Result {
+            f.write_str("[")?;
+            for (index, value) in values.into_iter().enumerate() {
+                if index > 0 {
+                    f.write_str(", ")?;
+                }
+                write!(f, "{}", value)?;
+            }
+            f.write_str("]")
+        }
+        fn string(f: &mut fmt::Formatter<'_>, value: &str) -> fmt::Result {
+            let mut quoted = String::with_capacity(value.len() + 2);
+            quoted.push('"');
+            escape_string(&mut quoted, value, '"');
+            quoted.push('"');
+            f.write_str(&quoted)
+        }
+
+        match self {
+            JsValue::Undefined => f.write_str("undefined"),
+            JsValue::Null => f.write_str("null"),
+            JsValue::Bool(v) => write!(f, "{}", v),
+            JsValue::Int(v) => write!(f, "{}", v),
+            JsValue::Float(v) => f.write_str(&number_to_string(*v)),
+            JsValue::String(v) => string(f, v),
+            JsValue::Array(values) => list(f, values),
+            JsValue::Object(map) => {
+                f.write_str("{")?;
+                for (index, (key, value)) in map.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(", ")?;
+                    }
+                    if is_identifier(key, true) {
+                        f.write_str(key)?;
+                    } else {
+                        string(f, key)?;
+                    }
+                    write!(f, ": {}", value)?;
+                }
+                f.write_str("}")
+            }
+            JsValue::Map(entries) => {
+                f.write_str("new Map([")?;
+                for (index, (key, value)) in entries.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "[{}, {}]", key, value)?;
+                }
+                f.write_str("])")
+            }
+            JsValue::Set(values) => {
+                f.write_str("new Set(")?;
+                list(f, values)?;
+                f.write_str(")")
+            }
+            JsValue::Symbol(Symbol::Local(None)) => f.write_str("Symbol()"),
+            JsValue::Symbol(Symbol::Local(Some(description))) => {
+                f.write_str("Symbol(")?;
+                string(f, description)?;
+                f.write_str(")")
+            }
+            JsValue::Symbol(Symbol::Registered(key)) => {
+                f.write_str("Symbol.for(")?;
+                string(f, key)?;
+                f.write_str(")")
+            }
+            JsValue::Symbol(Symbol::WellKnown(name)) => write!(f, "Symbol.{}", name),
+            JsValue::Function(_) => f.write_str("[Function]"),
+            JsValue::Bytes(bytes) => {
+                write!(f, "new Uint8Array([")?;
+                for (index, byte) in bytes.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", byte)?;
+                }
+                f.write_str("]).buffer")
+            }
+            JsValue::TypedArray(array) => write!(
+                f,
+                "new {}([{}])",
+                array.constructor_name(),
+                typed_array_elements(array).join(", ")
+            ),
+            #[cfg(feature = "chrono")]
+            JsValue::Date(v) => write!(
+                f,
+                "new Date(\"{}\")",
+                v.to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+            ),
+            #[cfg(feature = "bigint")]
+            JsValue::BigInt(v) => write!(f, "{}n", v),
+            #[cfg(feature = "bignum")]
+            JsValue::BigDecimal(v) => write!(f, "{}m", v),
+            #[cfg(feature = "bignum")]
+            JsValue::BigFloat(v) => match v.as_decimal() {
+                Some(v) => write!(f, "{}l", v),
+                None => write!(f, "BigFloat(\"{}\")", v),
+            },
+            JsValue::__NonExhaustive => unreachable!(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inspect(value: &JsValue) -> String {
+        value.inspect(&InspectOptions::default())
+    }
+
+    #[test]
+    fn test_number_to_string() {
+        let cases = [
+            (1.0, "1"),
+            (-1.5, "-1.5"),
+            (0.1, "0.1"),
+            (-0.0, "-0"),
+            (123456789.125, "123456789.125"),
+            (1e21, "1e+21"),
+            (1.5e300, "1.5e+300"),
+            (1e20, "100000000000000000000"),
+            (0.000001, "0.000001"),
+            (1.5e-7, "1.5e-7"),
+            (f64::NAN, "NaN"),
+            (f64::NEG_INFINITY, "-Infinity"),
+        ];
+        for (value, expected) in cases.iter() {
+            assert_eq!(number_to_string(*value), *expected);
+        }
+    }
+
+    #[test]
+    fn test_inspect_primitives() {
+        assert_eq!(inspect(&JsValue::Undefined), "undefined");
+        assert_eq!(inspect(&JsValue::Float(2.5)), "2.5");
+        assert_eq!(inspect(&"it's".into()), "\"it's\"");
+        assert_eq!(inspect(&"a\n'\"".into()), "`a\\n'\"`");
+        assert_eq!(
+            inspect(&JsValue::Symbol(Symbol::WellKnown("iterator".into()))),
+            "Symbol(Symbol.iterator)"
+        );
+
+        let options = InspectOptions {
+            max_string_length: Some(2),
+            ..InspectOptions::default()
+        };
+        assert_eq!(
+            JsValue::from("abcd").inspect(&options),
+            "'ab'... 2 more characters"
+        );
+
+        let options = InspectOptions {
+            colors: true,
+            ..InspectOptions::default()
+        };
+        assert_eq!(
+            JsValue::Array(vec![1.into(), JsValue::Null]).inspect(&options),
+            "[ \x1b[33m1\x1b[39m, \x1b[1mnull\x1b[22m ]"
+        );
+    }
+
+    #[test]
+    fn test_inspect_collections() {
+        let map = JsValue::Map(vec![("a".into(), JsValue::Set(vec![1.into()]))]);
+        assert_eq!(inspect(&map), "Map(1) { 'a' => Set(1) { 1 } }");
+        assert_eq!(inspect(&JsValue::Array(vec![])), "[]");
+
+        let array = JsValue::Array((0..4).map(JsValue::from).collect());
+        let options = InspectOptions {
+            max_array_length: Some(2),
+            ..InspectOptions::default()
+        };
+        assert_eq!(array.inspect(&options), "[ 0, 1, ... 2 more items ]");
+
+        assert_eq!(
+            inspect(&JsValue::Bytes(vec![1, 255])),
+            "ArrayBuffer { [Uint8Contents]: <01 ff>, byteLength: 2 }"
+        );
+        assert_eq!(
+            JsValue::Bytes(vec![1, 2, 3]).inspect(&options),
+            "ArrayBuffer { [Uint8Contents]: <01 02 ... 1 more byte>, byteLength: 3 }"
+        );
+        assert_eq!(
+            inspect(&TypedArray::Float32(vec![0.5, 1.0]).into()),
+            "Float32Array(2) [ 0.5, 1 ]"
+        );
+    }
+
+    #[test]
+    fn test_inspect_layout() {
+        let long = JsValue::Array((0..30).map(|i| JsValue::from(i * 1000)).collect());
+        let text = inspect(&JsValue::Object(
+            vec![("list".to_string(), long)].into_iter().collect(),
+        ));
+        assert!(text.starts_with("{\n  list: [\n    0,\n    1000,\n"));
+        assert!(text.ends_with("    29000\n  ]\n}"));
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_date() {
+        use chrono::TimeZone;
+
+        let date = JsValue::Date(chrono::Utc.timestamp_millis_opt(1_600_000_000_123).unwrap());
+        assert_eq!(inspect(&date), "2020-09-13T12:26:40.123Z");
+        assert_eq!(date.to_string(), r#"new Date("2020-09-13T12:26:40.123Z")"#);
+    }
+
+    #[cfg(feature = "bigint")]
+    #[test]
+    fn test_bigint() {
+        let value = JsValue::BigInt(num_bigint::BigInt::from(i128::MAX).into());
+        let text = format!("{}n", i128::MAX);
+        assert_eq!(inspect(&value), text);
+        assert_eq!(value.to_string(), text);
+    }
+
+    #[test]
+    fn test_display() {
+        let object = JsValue::Object(
+            vec![
+                (
+                    "a".to_string(),
+                    JsValue::Array(vec![1.into(), "t\"wo".into()]),
+                ),
+                ("b-c".to_string(), JsValue::Float(f64::NAN)),
+                (
+                    "$d".to_string(),
+                    JsValue::Map(vec![(1.into(), JsValue::Set(vec![]))]),
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        assert_eq!(
+            object.to_string(),
+            r#"{a: [1, "t\"wo"], "b-c": NaN, $d: new Map([[1, new Set([])]])}"#
+        );
+        assert_eq!(
+            JsValue::Symbol(Symbol::Registered("k".into())).to_string(),
+            r#"Symbol.for("k")"#
+        );
+        assert_eq!(
+            JsValue::Bytes(vec![1, 2]).to_string(),
+            "new Uint8Array([1, 2]).buffer"
+        );
+        assert_eq!(
+            JsValue::from(TypedArray::BigInt64(vec![-1])).to_string(),
+            "new BigInt64Array([-1n])"
+        );
+    }
+}
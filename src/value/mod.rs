@@ -1,15 +1,34 @@
+#[cfg(feature = "bignum")]
+pub(crate) mod bigfloat;
 #[cfg(feature = "bigint")]
 pub(crate) mod bigint;
+pub(crate) mod function;
+mod inspect;
+mod symbol;
+mod typed_array;
 
 use std::convert::{TryFrom, TryInto};
-use std::{collections::HashMap, error, fmt};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    error, fmt,
+    hash::Hash,
+};
 
+pub use indexmap::IndexMap;
+
+#[cfg(feature = "bignum")]
+pub use bigfloat::BigFloat;
 #[cfg(feature = "bigint")]
 pub use bigint::BigInt;
+pub use function::JsFunction;
+pub use inspect::InspectOptions;
+pub(crate) use inspect::CIRCULAR;
+pub use symbol::Symbol;
+pub use typed_array::TypedArray;
 
 /// A value that can be (de)serialized to/from the quickjs runtime.
 #[derive(PartialEq, Clone, Debug)]
-#[allow(missing_docs)]
+#[allow(missing_docs, clippy::manual_non_exhaustive)]
 pub enum JsValue {
     Undefined,
     Null,
@@ -18,7 +37,26 @@ pub enum JsValue {
     Float(f64),
     String(String),
     Array(Vec<JsValue>),
-    Object(HashMap<String, JsValue>),
+    /// JS Object, with properties in Javascript enumeration order.
+    ///
+    /// Only enumerable properties with string keys are converted. Properties
+    /// with symbol keys are skipped, since they can not be represented.
+    Object(IndexMap<String, JsValue>),
+    /// JS Map, as a list of key value pairs in insertion order.
+    Map(Vec<(JsValue, JsValue)>),
+    /// JS Set, as a list of values in insertion order.
+    Set(Vec<JsValue>),
+    /// JS Symbol.
+    Symbol(Symbol),
+    /// JS function, as a handle that keeps the function alive.
+    /// See [JsFunction].
+    Function(JsFunction),
+    /// Raw bytes / JS ArrayBuffer integration.
+    /// The buffer is handed to the runtime without copying.
+    Bytes(Vec<u8>),
+    /// JS typed arrays like `Uint8Array` or `Float64Array`.
+    /// The data is handed to the runtime without copying.
+    TypedArray(TypedArray),
     /// chrono::Datetime<Utc> / JS Date integration.
     /// Only available with the optional `chrono` feature.
     #[cfg(feature = "chrono")]
@@ -27,6 +65,15 @@ pub enum JsValue {
     /// Only available with the optional `bigint` feature
     #[cfg(feature = "bigint")]
     BigInt(crate::BigInt),
+    /// bigdecimal::BigDecimal / JS BigDecimal integration, for literals
+    /// like `0.1m`.
+    /// Only available with the optional `bignum` feature
+    #[cfg(feature = "bignum")]
+    BigDecimal(bigdecimal::BigDecimal),
+    /// JS BigFloat integration, for literals like `0.1l`.
+    /// Only available with the optional `bignum` feature
+    #[cfg(feature = "bignum")]
+    BigFloat(crate::BigFloat),
     #[doc(hidden)]
     __NonExhaustive,
 }
@@ -49,6 +96,64 @@ impl JsValue {
             _ => None,
         }
     }
+
+    /// Cast value to a byte slice.
+    ///
+    /// Returns `Some(&[u8])` if value is a `JsValue::Bytes`, None otherwise.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            JsValue::Bytes(ref b) => Some(b.as_slice()),
+            _ => None,
+        }
+    }
+
+    /// Convert to the bytes of a `JsValue::Bytes`.
+    pub fn into_bytes(self) -> Option<Vec<u8>> {
+        match self {
+            JsValue::Bytes(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    /// Compare values like Javascript numbers, so that `Int(1)` equals
+    /// `Float(1.0)`, also in nested arrays, objects and collections.
+    ///
+    /// Other values are compared with `==`, and like in Javascript, `NaN`
+    /// is not equal to itself.
+    ///
+    /// ```rust
+    /// use quick_js::JsValue;
+    ///
+    /// let ints = JsValue::Array(vec![JsValue::Int(1)]);
+    /// let floats = JsValue::Array(vec![JsValue::Float(1.0)]);
+    /// assert_ne!(ints, floats);
+    /// assert!(ints.number_eq(&floats));
+    /// ```
+    pub fn number_eq(&self, other: &JsValue) -> bool {
+        fn all_eq(a: &[JsValue], b: &[JsValue]) -> bool {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.number_eq(b))
+        }
+
+        match (self, other) {
+            (JsValue::Int(int), JsValue::Float(float))
+            | (JsValue::Float(float), JsValue::Int(int)) => f64::from(*int) == *float,
+            (JsValue::Array(a), JsValue::Array(b)) | (JsValue::Set(a), JsValue::Set(b)) => {
+                all_eq(a, b)
+            }
+            (JsValue::Object(a), JsValue::Object(b)) => {
+                a.len() == b.len()
+                    && a.iter()
+                        .all(|(key, a)| matches!(b.get(key), Some(b) if a.number_eq(b)))
+            }
+            (JsValue::Map(a), JsValue::Map(b)) => {
+                a.len() == b.len()
+                    && a.iter()
+                        .zip(b)
+                        .all(|((ka, va), (kb, vb))| ka.number_eq(kb) && va.number_eq(vb))
+            }
+            _ => self == other,
+        }
+    }
 }
 
 macro_rules! value_impl_from {
@@ -94,11 +199,11 @@ macro_rules! value_impl_from {
 value_impl_from! {
     (
         bool => Bool,
-        i32 => Int,
-        f64 => Float,
         String => String,
     )
     (
+        i32 => |x| x => Int,
+        f64 => |x| x => Float,
         i8 => |x| i32::from(x) => Int,
         i16 => |x| i32::from(x) => Int,
         u8 => |x| i32::from(x) => Int,
@@ -107,49 +212,107 @@ value_impl_from! {
     )
 }
 
-#[cfg(feature = "bigint")]
-value_impl_from! {
-    ()
-    (
-        i64 => |x| x.into() => BigInt,
-        u64 => |x| num_bigint::BigInt::from(x).into() => BigInt,
-        i128 => |x| num_bigint::BigInt::from(x).into() => BigInt,
-        u128 => |x| num_bigint::BigInt::from(x).into() => BigInt,
-        num_bigint::BigInt => |x| x.into() => BigInt,
-    )
+/// `Number.MAX_SAFE_INTEGER`, the largest integer that a Javascript number
+/// represents exactly.
+const MAX_SAFE_INTEGER: i64 = (1 << 53) - 1;
+
+/// Convert an integer to a number, if the number represents it exactly.
+fn integer_to_number(value: i128) -> Option<JsValue> {
+    if let Ok(int) = i32::try_from(value) {
+        Some(JsValue::Int(int))
+    } else if (-i128::from(MAX_SAFE_INTEGER)..=i128::from(MAX_SAFE_INTEGER)).contains(&value) {
+        Some(JsValue::Float(value as f64))
+    } else {
+        None
+    }
 }
 
-#[cfg(feature = "bigint")]
-impl std::convert::TryFrom<JsValue> for i64 {
+/// Convert a number to an integer, if it is an integer in the safe range.
+fn number_to_integer(value: f64) -> Option<i64> {
+    if value.fract() == 0.0 && value.abs() <= MAX_SAFE_INTEGER as f64 {
+        Some(value as i64)
+    } else {
+        None
+    }
+}
+
+/// Integral floats are converted if they are in range.
+impl TryFrom<JsValue> for i32 {
     type Error = ValueError;
 
     fn try_from(value: JsValue) -> Result<Self, Self::Error> {
         match value {
-            JsValue::Int(int) => Ok(int as i64),
-            JsValue::BigInt(bigint) => bigint.as_i64().ok_or(ValueError::UnexpectedType),
+            JsValue::Int(int) => Ok(int),
+            JsValue::Float(float) => number_to_integer(float)
+                .and_then(|int| i32::try_from(int).ok())
+                .ok_or(ValueError::OutOfRange),
             _ => Err(ValueError::UnexpectedType),
         }
     }
 }
 
-#[cfg(feature = "bigint")]
-macro_rules! value_bigint_impl_tryfrom {
-    (
-        ($($t:ty => $to_type:ident, )*)
-    ) => {
+impl TryFrom<JsValue> for f64 {
+    type Error = ValueError;
+
+    fn try_from(value: JsValue) -> Result<Self, Self::Error> {
+        match value {
+            JsValue::Float(float) => Ok(float),
+            JsValue::Int(int) => Ok(f64::from(int)),
+            _ => Err(ValueError::UnexpectedType),
+        }
+    }
+}
+
+macro_rules! value_impl_integer {
+    ( $( $t:ty => $to_type:ident, )* ) => {
         $(
-            impl std::convert::TryFrom<JsValue> for $t {
+            /// Integers outside of the safe integer range of Javascript
+            /// numbers are converted to a `BigInt`.
+            #[cfg(feature = "bigint")]
+            impl From<$t> for JsValue {
+                fn from(value: $t) -> Self {
+                    i128::try_from(value)
+                        .ok()
+                        .and_then(integer_to_number)
+                        .unwrap_or_else(|| JsValue::BigInt(num_bigint::BigInt::from(value).into()))
+                }
+            }
+
+            /// Integers outside of the safe integer range of Javascript
+            /// numbers fail with [ValueError::OutOfRange], because they can
+            /// not be represented exactly.
+            /// With the `bigint` feature, they are converted to a `BigInt`.
+            #[cfg(not(feature = "bigint"))]
+            impl TryFrom<$t> for JsValue {
                 type Error = ValueError;
 
-                fn try_from(value: JsValue) -> Result<Self, Self::Error> {
-                    use num_traits::ToPrimitive;
+                fn try_from(value: $t) -> Result<Self, Self::Error> {
+                    i128::try_from(value)
+                        .ok()
+                        .and_then(integer_to_number)
+                        .ok_or(ValueError::OutOfRange)
+                }
+            }
 
+            /// Numbers are converted if they are integers in the safe
+            /// integer range and in the range of the type.
+            impl TryFrom<JsValue> for $t {
+                type Error = ValueError;
+
+                fn try_from(value: JsValue) -> Result<Self, Self::Error> {
                     match value {
-                        JsValue::Int(int) => Ok(int as $t),
-                        JsValue::BigInt(bigint) => bigint
-                            .into_bigint()
-                            .$to_type()
-                            .ok_or(ValueError::UnexpectedType),
+                        JsValue::Int(int) => <$t>::try_from(int).map_err(|_| ValueError::OutOfRange),
+                        JsValue::Float(float) => number_to_integer(float)
+                            .and_then(|int| <$t>::try_from(int).ok())
+                            .ok_or(ValueError::OutOfRange),
+                        #[cfg(feature = "bigint")]
+                        JsValue::BigInt(bigint) => {
+                            use num_traits::ToPrimitive;
+                            bigint
+                                .into_bigint()
+                                .$to_type()
+                                .ok_or(ValueError::OutOfRange)
+                        }
                         _ => Err(ValueError::UnexpectedType),
                     }
                 }
@@ -158,13 +321,39 @@ macro_rules! value_bigint_impl_tryfrom {
     }
 }
 
+value_impl_integer! {
+    i64 => to_i64,
+    u64 => to_u64,
+    i128 => to_i128,
+    u128 => to_u128,
+    isize => to_isize,
+    usize => to_usize,
+}
+
 #[cfg(feature = "bigint")]
-value_bigint_impl_tryfrom! {
-    (
-        u64 => to_u64,
-        i128 => to_i128,
-        u128 => to_u128,
-    )
+impl From<num_bigint::BigInt> for JsValue {
+    fn from(value: num_bigint::BigInt) -> Self {
+        JsValue::BigInt(value.into())
+    }
+}
+
+#[cfg(feature = "bigint")]
+impl From<BigInt> for JsValue {
+    fn from(value: BigInt) -> Self {
+        JsValue::BigInt(value)
+    }
+}
+
+#[cfg(feature = "bigint")]
+impl std::convert::TryFrom<JsValue> for BigInt {
+    type Error = ValueError;
+
+    fn try_from(value: JsValue) -> Result<Self, Self::Error> {
+        match value {
+            JsValue::BigInt(bigint) => Ok(bigint),
+            other => num_bigint::BigInt::try_from(other).map(BigInt::from),
+        }
+    }
 }
 
 #[cfg(feature = "bigint")]
@@ -174,12 +363,58 @@ impl std::convert::TryFrom<JsValue> for num_bigint::BigInt {
     fn try_from(value: JsValue) -> Result<Self, Self::Error> {
         match value {
             JsValue::Int(int) => Ok(num_bigint::BigInt::from(int)),
+            JsValue::Float(float) => number_to_integer(float)
+                .map(num_bigint::BigInt::from)
+                .ok_or(ValueError::OutOfRange),
             JsValue::BigInt(bigint) => Ok(bigint.into_bigint()),
             _ => Err(ValueError::UnexpectedType),
         }
     }
 }
 
+#[cfg(feature = "bignum")]
+impl From<bigdecimal::BigDecimal> for JsValue {
+    fn from(value: bigdecimal::BigDecimal) -> Self {
+        JsValue::BigDecimal(value)
+    }
+}
+
+/// Accepts `BigDecimal` and `Int` values. Floats are rejected, because
+/// most decimal fractions have no exact float representation.
+#[cfg(feature = "bignum")]
+impl std::convert::TryFrom<JsValue> for bigdecimal::BigDecimal {
+    type Error = ValueError;
+
+    fn try_from(value: JsValue) -> Result<Self, Self::Error> {
+        match value {
+            JsValue::BigDecimal(decimal) => Ok(decimal),
+            JsValue::Int(int) => Ok(int.into()),
+            _ => Err(ValueError::UnexpectedType),
+        }
+    }
+}
+
+#[cfg(feature = "bignum")]
+impl From<BigFloat> for JsValue {
+    fn from(value: BigFloat) -> Self {
+        JsValue::BigFloat(value)
+    }
+}
+
+#[cfg(feature = "bignum")]
+impl std::convert::TryFrom<JsValue> for BigFloat {
+    type Error = ValueError;
+
+    fn try_from(value: JsValue) -> Result<Self, Self::Error> {
+        match value {
+            JsValue::BigFloat(float) => Ok(float),
+            JsValue::Int(int) => Ok(f64::from(int).into()),
+            JsValue::Float(float) => Ok(float.into()),
+            _ => Err(ValueError::UnexpectedType),
+        }
+    }
+}
+
 impl<T> From<Vec<T>> for JsValue
 where
     T: Into<JsValue>,
@@ -226,6 +461,64 @@ where
     }
 }
 
+// A blanket implementation for `Option<T>` would conflict with the
+// `From<T> for Option<T>` implementation when `T` is `JsValue`.
+macro_rules! value_impl_try_from_option {
+    ( $( [ $( $param:ident ),* ] $t:ty, )* ) => {
+        $(
+            /// `undefined` and `null` are converted to `None`.
+            impl< $( $param ),* > TryFrom<JsValue> for Option<$t>
+            where
+                $t: TryFrom<JsValue>,
+            {
+                type Error = ValueError;
+
+                fn try_from(value: JsValue) -> Result<Self, Self::Error> {
+                    match value {
+                        JsValue::Undefined | JsValue::Null => Ok(None),
+                        value => <$t>::try_from(value)
+                            .map(Some)
+                            .map_err(|_| ValueError::UnexpectedType),
+                    }
+                }
+            }
+        )*
+    }
+}
+
+value_impl_try_from_option! {
+    [] bool,
+    [] i32,
+    [] f64,
+    [] String,
+    [] JsFunction,
+    [] Symbol,
+    [] TypedArray,
+    [T] Vec<T>,
+    [V] HashMap<String, V>,
+    [K, V] BTreeMap<K, V>,
+    [K, V] IndexMap<K, V>,
+    [T] HashSet<T>,
+    [T] BTreeSet<T>,
+    [] i64,
+    [] u64,
+    [] i128,
+    [] u128,
+    [] isize,
+    [] usize,
+}
+
+#[cfg(feature = "bigint")]
+value_impl_try_from_option! {
+    [] num_bigint::BigInt,
+}
+
+#[cfg(feature = "bignum")]
+value_impl_try_from_option! {
+    [] bigdecimal::BigDecimal,
+    [] BigFloat,
+}
+
 impl<K, V> From<HashMap<K, V>> for JsValue
 where
     K: Into<String>,
@@ -257,8 +550,168 @@ where
     }
 }
 
+impl<K, V> From<BTreeMap<K, V>> for JsValue
+where
+    K: Into<JsValue>,
+    V: Into<JsValue>,
+{
+    fn from(map: BTreeMap<K, V>) -> Self {
+        let entries = map.into_iter().map(|(k, v)| (k.into(), v.into())).collect();
+        JsValue::Map(entries)
+    }
+}
+
+impl<K, V> TryFrom<JsValue> for BTreeMap<K, V>
+where
+    K: TryFrom<JsValue> + Ord,
+    V: TryFrom<JsValue>,
+{
+    type Error = ValueError;
+
+    fn try_from(value: JsValue) -> Result<Self, Self::Error> {
+        match value {
+            JsValue::Map(entries) => entries.into_iter().map(map_entry_try_from).collect(),
+            JsValue::Object(object) => object
+                .into_iter()
+                .map(|(k, v)| map_entry_try_from((JsValue::String(k), v)))
+                .collect(),
+            _ => Err(ValueError::UnexpectedType),
+        }
+    }
+}
+
+/// Converts to a `JsValue::Map`.
+/// Construct a `JsValue::Object` directly for an ordered object.
+impl<K, V> From<IndexMap<K, V>> for JsValue
+where
+    K: Into<JsValue>,
+    V: Into<JsValue>,
+{
+    fn from(map: IndexMap<K, V>) -> Self {
+        let entries = map.into_iter().map(|(k, v)| (k.into(), v.into())).collect();
+        JsValue::Map(entries)
+    }
+}
+
+impl<K, V> TryFrom<JsValue> for IndexMap<K, V>
+where
+    K: TryFrom<JsValue> + Eq + Hash,
+    V: TryFrom<JsValue>,
+{
+    type Error = ValueError;
+
+    fn try_from(value: JsValue) -> Result<Self, Self::Error> {
+        match value {
+            JsValue::Map(entries) => entries.into_iter().map(map_entry_try_from).collect(),
+            JsValue::Object(object) => object
+                .into_iter()
+                .map(|(k, v)| map_entry_try_from((JsValue::String(k), v)))
+                .collect(),
+            _ => Err(ValueError::UnexpectedType),
+        }
+    }
+}
+
+fn map_entry_try_from<K, V>((k, v): (JsValue, JsValue)) -> Result<(K, V), ValueError>
+where
+    K: TryFrom<JsValue>,
+    V: TryFrom<JsValue>,
+{
+    match (k.try_into(), v.try_into()) {
+        (Ok(k), Ok(v)) => Ok((k, v)),
+        _ => Err(ValueError::UnexpectedType),
+    }
+}
+
+impl<T> From<HashSet<T>> for JsValue
+where
+    T: Into<JsValue>,
+{
+    fn from(set: HashSet<T>) -> Self {
+        JsValue::Set(set.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T> TryFrom<JsValue> for HashSet<T>
+where
+    T: TryFrom<JsValue> + Eq + Hash,
+{
+    type Error = ValueError;
+
+    fn try_from(value: JsValue) -> Result<Self, Self::Error> {
+        match value {
+            JsValue::Set(values) => values
+                .into_iter()
+                .map(|v| v.try_into().map_err(|_| ValueError::UnexpectedType))
+                .collect(),
+            _ => Err(ValueError::UnexpectedType),
+        }
+    }
+}
+
+impl<T> From<BTreeSet<T>> for JsValue
+where
+    T: Into<JsValue>,
+{
+    fn from(set: BTreeSet<T>) -> Self {
+        JsValue::Set(set.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T> TryFrom<JsValue> for BTreeSet<T>
+where
+    T: TryFrom<JsValue> + Ord,
+{
+    type Error = ValueError;
+
+    fn try_from(value: JsValue) -> Result<Self, Self::Error> {
+        match value {
+            JsValue::Set(values) => values
+                .into_iter()
+                .map(|v| v.try_into().map_err(|_| ValueError::UnexpectedType))
+                .collect(),
+            _ => Err(ValueError::UnexpectedType),
+        }
+    }
+}
+
+impl From<Symbol> for JsValue {
+    fn from(symbol: Symbol) -> Self {
+        JsValue::Symbol(symbol)
+    }
+}
+
+impl From<JsFunction> for JsValue {
+    fn from(function: JsFunction) -> Self {
+        JsValue::Function(function)
+    }
+}
+
+impl TryFrom<JsValue> for JsFunction {
+    type Error = ValueError;
+
+    fn try_from(value: JsValue) -> Result<Self, Self::Error> {
+        match value {
+            JsValue::Function(function) => Ok(function),
+            _ => Err(ValueError::UnexpectedType),
+        }
+    }
+}
+
+impl TryFrom<JsValue> for Symbol {
+    type Error = ValueError;
+
+    fn try_from(value: JsValue) -> Result<Self, Self::Error> {
+        match value {
+            JsValue::Symbol(symbol) => Ok(symbol),
+            _ => Err(ValueError::UnexpectedType),
+        }
+    }
+}
+
 /// Error during value conversion.
 #[derive(PartialEq, Eq, Debug)]
+#[allow(clippy::manual_non_exhaustive)]
 pub enum ValueError {
     /// Invalid non-utf8 string.
     InvalidString(std::str::Utf8Error),
@@ -268,6 +721,15 @@ pub enum ValueError {
     Internal(String),
     /// Received an unexpected type that could not be converted.
     UnexpectedType,
+    /// The value references itself, directly or through nested values.
+    CyclicValue,
+    /// The value is nested deeper than the configured maximum depth.
+    DepthLimitExceeded,
+    /// The value contains more values than the configured maximum size.
+    SizeLimitExceeded,
+    /// A number is not an integer, or out of range of the target type or
+    /// of the numbers that Javascript represents exactly.
+    OutOfRange,
     #[doc(hidden)]
     __NonExhaustive,
 }
@@ -292,6 +754,10 @@ impl fmt::Display for ValueError {
             StringWithZeroBytes(_) => write!(f, "String contains \\0 bytes",),
             Internal(e) => write!(f, "Value conversion failed - internal error: {}", e),
             UnexpectedType => write!(f, "Could not convert - received unexpected type"),
+            CyclicValue => write!(f, "Could not convert - value contains a cycle"),
+            DepthLimitExceeded => write!(f, "Could not convert - maximum depth exceeded"),
+            SizeLimitExceeded => write!(f, "Could not convert - maximum size exceeded"),
+            OutOfRange => write!(f, "Could not convert - number would lose precision"),
             __NonExhaustive => unreachable!(),
         }
     }
@@ -304,10 +770,53 @@ mod tests {
     #[allow(unused_imports)]
     use super::*;
 
+    #[test]
+    fn test_btreemap_roundtrip() {
+        let mut map = BTreeMap::new();
+        map.insert(1, "a".to_string());
+        map.insert(2, "b".to_string());
+        let value = JsValue::from(map.clone());
+        assert_eq!(
+            value,
+            JsValue::Map(vec![(1.into(), "a".into()), (2.into(), "b".into())])
+        );
+        assert_eq!(BTreeMap::<i32, String>::try_from(value), Ok(map));
+    }
+
+    #[test]
+    fn test_indexmap_preserves_order() {
+        let value = JsValue::Map(vec![("b".into(), 1.into()), ("a".into(), 2.into())]);
+        let map = IndexMap::<String, i32>::try_from(value.clone()).unwrap();
+        assert_eq!(map.keys().collect::<Vec<_>>(), vec!["b", "a"]);
+        assert_eq!(JsValue::from(map), value);
+    }
+
+    #[test]
+    fn test_indexmap_from_object() {
+        let mut object = IndexMap::new();
+        object.insert("z".to_string(), JsValue::Int(1));
+        object.insert("a".to_string(), JsValue::Int(2));
+        let map = IndexMap::<String, i32>::try_from(JsValue::Object(object)).unwrap();
+        assert_eq!(
+            map.into_iter().collect::<Vec<_>>(),
+            vec![("z".to_string(), 1), ("a".to_string(), 2)]
+        );
+    }
+
+    #[test]
+    fn test_hashset_roundtrip() {
+        let set = vec![true].into_iter().collect::<HashSet<_>>();
+        let value = JsValue::from(set.clone());
+        assert_eq!(value, JsValue::Set(vec![JsValue::Bool(true)]));
+        assert_eq!(HashSet::<bool>::try_from(value), Ok(set));
+    }
+
     #[cfg(feature = "bigint")]
     #[test]
     fn test_bigint_from_i64() {
-        let int = 1234i64;
+        assert_eq!(JsValue::from(1234i64), JsValue::Int(1234));
+
+        let int = i64::MAX;
         let value = JsValue::from(int);
         if let JsValue::BigInt(value) = value {
             assert_eq!(value.as_i64(), Some(int));
@@ -319,7 +828,7 @@ mod tests {
     #[cfg(feature = "bigint")]
     #[test]
     fn test_bigint_from_bigint() {
-        let bigint = num_bigint::BigInt::from(std::i128::MAX);
+        let bigint = num_bigint::BigInt::from(i128::MAX);
         let value = JsValue::from(bigint.clone());
         if let JsValue::BigInt(value) = value {
             assert_eq!(value.into_bigint(), bigint);
@@ -335,4 +844,70 @@ mod tests {
         let value_bigint = JsValue::BigInt(num_bigint::BigInt::from(1234i64).into());
         assert_eq!(value_i64, value_bigint);
     }
+
+    #[test]
+    // `try_from` works with and without the bigint feature.
+    #[allow(clippy::unnecessary_fallible_conversions)]
+    fn test_integer_conversions() {
+        let safe = (1i64 << 53) - 1;
+        let cases = [
+            (1i64 << 40, JsValue::Float((1i64 << 40) as f64)),
+            (-safe, JsValue::Float(-safe as f64)),
+            (i64::from(i32::MIN), JsValue::Int(i32::MIN)),
+        ];
+        for (int, value) in cases.iter() {
+            assert_eq!(JsValue::try_from(*int), Ok(value.clone()));
+            assert_eq!(i64::try_from(value.clone()), Ok(*int));
+        }
+        assert_eq!(
+            JsValue::try_from(usize::MAX >> 16),
+            Ok(JsValue::Float((usize::MAX >> 16) as f64))
+        );
+
+        #[cfg(not(feature = "bigint"))]
+        {
+            assert_eq!(JsValue::try_from(safe + 1), Err(ValueError::OutOfRange));
+            assert_eq!(JsValue::try_from(u64::MAX), Err(ValueError::OutOfRange));
+            assert_eq!(JsValue::try_from(i128::MIN), Err(ValueError::OutOfRange));
+        }
+        #[cfg(feature = "bigint")]
+        assert_eq!(
+            JsValue::from(u128::MAX),
+            JsValue::BigInt(num_bigint::BigInt::from(u128::MAX).into())
+        );
+
+        assert_eq!(i32::try_from(JsValue::Float(-2.0)), Ok(-2));
+        assert_eq!(u64::try_from(JsValue::Int(-1)), Err(ValueError::OutOfRange));
+        assert_eq!(
+            i64::try_from(JsValue::Float(1.5)),
+            Err(ValueError::OutOfRange)
+        );
+        assert_eq!(
+            i64::try_from(JsValue::Float(2f64.powi(53))),
+            Err(ValueError::OutOfRange)
+        );
+        assert_eq!(
+            i32::try_from(JsValue::Float(1e10)),
+            Err(ValueError::OutOfRange)
+        );
+        assert_eq!(
+            usize::try_from(JsValue::from("1")),
+            Err(ValueError::UnexpectedType)
+        );
+        assert_eq!(f64::try_from(JsValue::Int(3)), Ok(3.0));
+    }
+
+    #[test]
+    fn test_number_eq() {
+        let object =
+            |value: JsValue| JsValue::Object(vec![("a".to_string(), value)].into_iter().collect());
+        assert!(JsValue::Int(1).number_eq(&JsValue::Float(1.0)));
+        assert!(!JsValue::Int(1).number_eq(&JsValue::Float(1.5)));
+        assert!(!JsValue::Float(f64::NAN).number_eq(&JsValue::Float(f64::NAN)));
+        assert!(object(JsValue::Float(2.0)).number_eq(&object(JsValue::Int(2))));
+        assert!(!object(JsValue::Int(2)).number_eq(&object(JsValue::from("2"))));
+        assert!(JsValue::Map(vec![(JsValue::Int(1), JsValue::Float(0.0))])
+            .number_eq(&JsValue::Map(vec![(JsValue::Float(1.0), JsValue::Int(0))])));
+        assert!(!JsValue::Set(vec![JsValue::Int(1)]).number_eq(&JsValue::Set(vec![])));
+    }
 }
@@ -0,0 +1,48 @@
+/// A value holding a JavaScript
+/// [Symbol](https://developer.mozilla.org/en-US/docs/Web/JavaScript/Reference/Global_Objects/Symbol).
+///
+/// Symbols are unique, so only registered and well-known symbols refer to
+/// the same symbol when they are passed back to Javascript.
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
+pub enum Symbol {
+    /// A symbol created with `Symbol(description)`.
+    ///
+    /// A new, distinct symbol is created every time this value is passed to
+    /// Javascript.
+    Local(Option<String>),
+    /// A symbol from the global registry, created with `Symbol.for(key)`.
+    Registered(String),
+    /// A well-known symbol like `Symbol.iterator`, by name (`"iterator"`).
+    WellKnown(String),
+}
+
+impl Symbol {
+    /// The names of the well-known symbols.
+    pub(crate) const WELL_KNOWN: &'static [&'static str] = &[
+        "asyncIterator",
+        "hasInstance",
+        "isConcatSpreadable",
+        "iterator",
+        "match",
+        "matchAll",
+        "replace",
+        "search",
+        "species",
+        "split",
+        "toPrimitive",
+        "toStringTag",
+        "unscopables",
+    ];
+
+    /// Returns the description of the symbol.
+    ///
+    /// This is the key for registered symbols and `Symbol.<name>` for
+    /// well-known symbols.
+    pub fn description(&self) -> Option<String> {
+        match self {
+            Symbol::Local(description) => description.clone(),
+            Symbol::Registered(key) => Some(key.clone()),
+            Symbol::WellKnown(name) => Some(format!("Symbol.{}", name)),
+        }
+    }
+}
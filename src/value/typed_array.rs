@@ -0,0 +1,155 @@
+use std::convert::TryFrom;
+
+use super::{JsValue, ValueError};
+
+/// A value holding a JavaScript
+/// [TypedArray](https://developer.mozilla.org/en-US/docs/Web/JavaScript/Reference/Global_Objects/TypedArray).
+///
+/// Each variant corresponds to one typed array constructor, like
+/// `Uint8Array` or `Float64Array`.
+#[derive(PartialEq, Clone, Debug)]
+#[allow(missing_docs)]
+pub enum TypedArray {
+    Int8(Vec<i8>),
+    Uint8(Vec<u8>),
+    Uint8Clamped(Vec<u8>),
+    Int16(Vec<i16>),
+    Uint16(Vec<u16>),
+    Int32(Vec<i32>),
+    Uint32(Vec<u32>),
+    Float32(Vec<f32>),
+    Float64(Vec<f64>),
+    BigInt64(Vec<i64>),
+    BigUint64(Vec<u64>),
+}
+
+impl TypedArray {
+    /// Returns the number of elements.
+    pub fn len(&self) -> usize {
+        use TypedArray::*;
+        match self {
+            Int8(v) => v.len(),
+            Uint8(v) | Uint8Clamped(v) => v.len(),
+            Int16(v) => v.len(),
+            Uint16(v) => v.len(),
+            Int32(v) => v.len(),
+            Uint32(v) => v.len(),
+            Float32(v) => v.len(),
+            Float64(v) => v.len(),
+            BigInt64(v) => v.len(),
+            BigUint64(v) => v.len(),
+        }
+    }
+
+    /// Returns `true` if the array has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The name of the Javascript constructor, like `Uint8Array`.
+    pub fn constructor_name(&self) -> &'static str {
+        use TypedArray::*;
+        match self {
+            Int8(_) => "Int8Array",
+            Uint8(_) => "Uint8Array",
+            Uint8Clamped(_) => "Uint8ClampedArray",
+            Int16(_) => "Int16Array",
+            Uint16(_) => "Uint16Array",
+            Int32(_) => "Int32Array",
+            Uint32(_) => "Uint32Array",
+            Float32(_) => "Float32Array",
+            Float64(_) => "Float64Array",
+            BigInt64(_) => "BigInt64Array",
+            BigUint64(_) => "BigUint64Array",
+        }
+    }
+}
+
+macro_rules! typed_array_impl_from {
+    ($( $t:ty => $var:ident, )*) => {
+        $(
+            impl From<Vec<$t>> for TypedArray {
+                fn from(values: Vec<$t>) -> Self {
+                    TypedArray::$var(values)
+                }
+            }
+
+            impl TryFrom<TypedArray> for Vec<$t> {
+                type Error = ValueError;
+
+                fn try_from(array: TypedArray) -> Result<Self, Self::Error> {
+                    match array {
+                        TypedArray::$var(values) => Ok(values),
+                        _ => Err(ValueError::UnexpectedType),
+                    }
+                }
+            }
+        )*
+    }
+}
+
+typed_array_impl_from! {
+    i8 => Int8,
+    i16 => Int16,
+    u16 => Uint16,
+    i32 => Int32,
+    u32 => Uint32,
+    f32 => Float32,
+    f64 => Float64,
+    i64 => BigInt64,
+    u64 => BigUint64,
+}
+
+impl From<Vec<u8>> for TypedArray {
+    fn from(values: Vec<u8>) -> Self {
+        TypedArray::Uint8(values)
+    }
+}
+
+impl TryFrom<TypedArray> for Vec<u8> {
+    type Error = ValueError;
+
+    fn try_from(array: TypedArray) -> Result<Self, Self::Error> {
+        match array {
+            TypedArray::Uint8(values) | TypedArray::Uint8Clamped(values) => Ok(values),
+            _ => Err(ValueError::UnexpectedType),
+        }
+    }
+}
+
+impl From<TypedArray> for JsValue {
+    fn from(array: TypedArray) -> Self {
+        JsValue::TypedArray(array)
+    }
+}
+
+impl TryFrom<JsValue> for TypedArray {
+    type Error = ValueError;
+
+    fn try_from(value: JsValue) -> Result<Self, Self::Error> {
+        match value {
+            JsValue::TypedArray(array) => Ok(array),
+            _ => Err(ValueError::UnexpectedType),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_typed_array_from_vec() {
+        let array = TypedArray::from(vec![1.5f32, 2.5]);
+        assert_eq!(array.len(), 2);
+        assert_eq!(array.constructor_name(), "Float32Array");
+        assert_eq!(Vec::<f32>::try_from(array.clone()), Ok(vec![1.5, 2.5]));
+        assert_eq!(Vec::<f64>::try_from(array), Err(ValueError::UnexpectedType));
+    }
+
+    #[test]
+    fn test_typed_array_uint8_clamped_into_bytes() {
+        let array = TypedArray::Uint8Clamped(vec![0, 255]);
+        assert_eq!(Vec::<u8>::try_from(array), Ok(vec![0, 255]));
+    }
+}
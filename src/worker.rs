@@ -0,0 +1,566 @@
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{self, Receiver, SyncSender, TrySendError},
+        Arc, Mutex,
+    },
+    thread::JoinHandle,
+};
+
+use crate::{
+    threaded::{Command, Reply},
+    Context, ContextBuilder, ContextError, ExecutionError, JsValue, ThreadedError,
+};
+
+type FactoryFn = dyn Fn() -> ContextBuilder + Send + Sync;
+type InitFn = dyn Fn(&Context) -> Result<(), ExecutionError> + Send + Sync;
+
+/// State shared by all worker threads.
+struct Shared {
+    factory: Box<FactoryFn>,
+    init: Option<Box<InitFn>>,
+    preludes: Vec<Arc<[u8]>>,
+    restarts: AtomicUsize,
+    /// Set on drop to interrupt running tasks and skip queued ones.
+    interrupt: Arc<AtomicBool>,
+}
+
+impl Shared {
+    fn build_context(&self) -> Result<Context, ContextError> {
+        let mut builder = (self.factory)();
+        for bytecode in &self.preludes {
+            builder = builder.prelude_bytecode(bytecode.clone());
+        }
+        let mut context = builder.build()?;
+        context.wrapper.set_interrupt_flag(self.interrupt.clone());
+        if let Some(init) = &self.init {
+            init(&context).map_err(ContextError::Execution)?;
+        }
+        Ok(context)
+    }
+}
+
+/// A pool of threads that each own a [Context].
+///
+/// Tasks are calls of global Javascript functions. They are queued in a
+/// bounded queue shared by all workers, so a busy worker does not hold up
+/// the others.
+///
+/// If a task runs out of memory or panics, the worker drops its context and
+/// builds a fresh one for the next task. If that fails, the task fails with
+/// [ExecutionError::Internal] and the next task tries again.
+///
+/// When the pool is dropped, running tasks are interrupted, queued tasks fail
+/// with [ThreadedError::Shutdown] and the worker threads are shut down.
+/// A callback that blocks is not interrupted.
+/// Use [WorkerPool::shutdown] to let queued tasks finish instead.
+///
+/// ```rust
+/// use quick_js::{Context, JsValue, WorkerPool};
+///
+/// let pool = WorkerPool::builder(|| Context::builder().memory_limit(10_000_000))
+///     .threads(2)
+///     .prelude("function transform(x) { return x * 2; }", "transform.js")
+///     .build()
+///     .unwrap();
+///
+/// let results = pool.map("transform", (1..=3).map(JsValue::from));
+/// assert_eq!(
+///     results,
+///     vec![Ok(JsValue::Int(2)), Ok(JsValue::Int(4)), Ok(JsValue::Int(6))],
+/// );
+/// ```
+pub struct WorkerPool {
+    shared: Arc<Shared>,
+    sender: Option<SyncSender<Command>>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl WorkerPool {
+    /// Create a builder for a pool whose contexts are built from the
+    /// [ContextBuilder] returned by `factory`.
+    ///
+    /// The factory is called on the worker threads, once for every context.
+    pub fn builder<F>(factory: F) -> WorkerPoolBuilder
+    where
+        F: Fn() -> ContextBuilder + Send + Sync + 'static,
+    {
+        WorkerPoolBuilder {
+            factory: Box::new(factory),
+            init: None,
+            preludes: Vec::new(),
+            threads: 4,
+            queue_capacity: None,
+        }
+    }
+
+    fn sender(&self) -> &SyncSender<Command> {
+        // Only taken in drop.
+        self.sender.as_ref().unwrap()
+    }
+
+    /// Returns the number of worker threads.
+    pub fn threads(&self) -> usize {
+        self.threads.len()
+    }
+
+    /// Returns how often a worker had to replace its context because a task
+    /// ran out of memory or panicked.
+    pub fn restart_count(&self) -> usize {
+        self.shared.restarts.load(Ordering::Relaxed)
+    }
+
+    /// Queue a call of a global function.
+    ///
+    /// Blocks while the queue is full.
+    /// The returned [Reply] can be awaited or waited on.
+    pub fn submit(
+        &self,
+        function_name: &str,
+        args: impl IntoIterator<Item = impl Into<JsValue>>,
+    ) -> Reply<JsValue> {
+        let (sender, reply) = Reply::channel();
+        let args = args.into_iter().map(Into::into).collect();
+        match self.sender().send(Command::CallFunction(
+            function_name.to_string(),
+            args,
+            sender,
+        )) {
+            Ok(()) => reply,
+            Err(_) => Reply::ready(Err(ThreadedError::Shutdown)),
+        }
+    }
+
+    /// Queue a call of a global function without blocking.
+    ///
+    /// Fails with [ThreadedError::QueueFull] if the queue is full.
+    pub fn try_submit(
+        &self,
+        function_name: &str,
+        args: impl IntoIterator<Item = impl Into<JsValue>>,
+    ) -> Reply<JsValue> {
+        let (sender, reply) = Reply::channel();
+        let args = args.into_iter().map(Into::into).collect();
+        match self.sender().try_send(Command::CallFunction(
+            function_name.to_string(),
+            args,
+            sender,
+        )) {
+            Ok(()) => reply,
+            Err(TrySendError::Full(_)) => Reply::ready(Err(ThreadedError::QueueFull)),
+            Err(TrySendError::Disconnected(_)) => Reply::ready(Err(ThreadedError::Shutdown)),
+        }
+    }
+
+    /// Call a global function once for every input, with the input as the
+    /// only argument, and wait for all results.
+    ///
+    /// The calls are distributed over all workers.
+    /// Results are returned in the order of the inputs.
+    pub fn map<I>(&self, function_name: &str, inputs: I) -> Vec<Result<JsValue, ThreadedError>>
+    where
+        I: IntoIterator,
+        I::Item: Into<JsValue>,
+    {
+        let replies = inputs
+            .into_iter()
+            .map(|input| self.submit(function_name, Some(input)))
+            .collect::<Vec<_>>();
+        replies.into_iter().map(Reply::wait).collect()
+    }
+
+    /// Shut down all workers.
+    ///
+    /// Waits until already queued tasks have finished, unlike dropping the
+    /// pool, which interrupts them.
+    /// Returns [ThreadedError::Shutdown] if a worker thread panicked.
+    pub fn shutdown(mut self) -> Result<(), ThreadedError> {
+        self.join()
+    }
+
+    fn join(&mut self) -> Result<(), ThreadedError> {
+        // Closing the channel ends the worker loops.
+        self.sender.take();
+        let mut res = Ok(());
+        for thread in self.threads.drain(..) {
+            if thread.join().is_err() {
+                res = Err(ThreadedError::Shutdown);
+            }
+        }
+        res
+    }
+}
+
+impl Drop for WorkerPool {
+    fn drop(&mut self) {
+        if !self.threads.is_empty() {
+            self.shared.interrupt.store(true, Ordering::Relaxed);
+        }
+        let _ = self.join();
+    }
+}
+
+fn run_worker(shared: &Shared, receiver: &Mutex<Receiver<Command>>, context: Context) {
+    // `None` after the context was dropped, until a new one could be built.
+    let mut context = Some(context);
+    loop {
+        let command = match receiver.lock().unwrap().recv() {
+            Ok(command) => command,
+            Err(_) => return,
+        };
+        if shared.interrupt.load(Ordering::Relaxed) {
+            command.fail(ThreadedError::Shutdown);
+            continue;
+        }
+        let ctx = match context.take() {
+            Some(ctx) => ctx,
+            None => match rebuild_context(shared) {
+                Ok(ctx) => ctx,
+                Err(e) => {
+                    // Report the failure to the task and try again for the
+                    // next one.
+                    command.fail(ThreadedError::Execution(ExecutionError::Internal(format!(
+                        "Could not rebuild the context: {}",
+                        e
+                    ))));
+                    continue;
+                }
+            },
+        };
+        let healthy = panic::catch_unwind(AssertUnwindSafe(|| command.run(&ctx))).unwrap_or(false);
+        if healthy {
+            context = Some(ctx);
+        } else {
+            shared.restarts.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Build a replacement context, treating a panic like a failed build.
+fn rebuild_context(shared: &Shared) -> Result<Context, ContextError> {
+    panic::catch_unwind(AssertUnwindSafe(|| shared.build_context()))
+        .unwrap_or(Err(ContextError::ContextCreationFailed))
+}
+
+/// A builder for [WorkerPool].
+///
+/// Create with [WorkerPool::builder].
+pub struct WorkerPoolBuilder {
+    factory: Box<FactoryFn>,
+    init: Option<Box<InitFn>>,
+    preludes: Vec<(String, String)>,
+    threads: usize,
+    queue_capacity: Option<usize>,
+}
+
+impl WorkerPoolBuilder {
+    /// Set the number of worker threads. Defaults to 4.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Set the capacity of the task queue.
+    /// Defaults to twice the number of threads.
+    pub fn queue_capacity(mut self, capacity: usize) -> Self {
+        self.queue_capacity = Some(capacity);
+        self
+    }
+
+    /// Add a script that is evaluated in every context.
+    ///
+    /// The script is compiled to bytecode once when the pool is built, and
+    /// the bytecode is shared by all workers.
+    /// See [ContextBuilder::prelude].
+    pub fn prelude(mut self, code: impl Into<String>, filename: impl Into<String>) -> Self {
+        self.preludes.push((code.into(), filename.into()));
+        self
+    }
+
+    /// Set a function that initializes every newly built context, for
+    /// example by adding callbacks.
+    ///
+    /// The function runs on the worker threads, after the preludes.
+    pub fn init<F>(mut self, init: F) -> Self
+    where
+        F: Fn(&Context) -> Result<(), ExecutionError> + Send + Sync + 'static,
+    {
+        self.init = Some(Box::new(init));
+        self
+    }
+
+    /// Finalize the builder and start the workers.
+    ///
+    /// Fails if a prelude does not compile or a worker can not build its
+    /// context.
+    pub fn build(self) -> Result<WorkerPool, ContextError> {
+        let preludes = if self.preludes.is_empty() {
+            Vec::new()
+        } else {
+            let context = Context::new()?;
+            self.preludes
+                .iter()
+                .map(|(code, filename)| {
+                    context
+                        .wrapper
+                        .compile_bytecode(code, filename)
+                        .map(Into::into)
+                        .map_err(ContextError::Execution)
+                })
+                .collect::<Result<Vec<_>, _>>()?
+        };
+
+        let shared = Arc::new(Shared {
+            factory: self.factory,
+            init: self.init,
+            preludes,
+            restarts: AtomicUsize::new(0),
+            interrupt: Arc::new(AtomicBool::new(false)),
+        });
+        let capacity = self.queue_capacity.unwrap_or(self.threads * 2);
+        let (sender, receiver) = mpsc::sync_channel::<Command>(capacity);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let (init_sender, init_receiver) = mpsc::channel();
+
+        let mut pool = WorkerPool {
+            shared: shared.clone(),
+            sender: Some(sender),
+            threads: Vec::with_capacity(self.threads),
+        };
+        for index in 0..self.threads {
+            let shared = shared.clone();
+            let receiver = receiver.clone();
+            let init_sender = init_sender.clone();
+            let thread = std::thread::Builder::new()
+                .name(format!("quick-js-worker-{}", index))
+                .spawn(move || {
+                    let context = match shared.build_context() {
+                        Ok(context) => {
+                            let _ = init_sender.send(Ok(()));
+                            context
+                        }
+                        Err(e) => {
+                            let _ = init_sender.send(Err(e));
+                            return;
+                        }
+                    };
+                    drop(init_sender);
+                    run_worker(&shared, &receiver, context);
+                })
+                .map_err(|_| ContextError::RuntimeCreationFailed)?;
+            pool.threads.push(thread);
+        }
+        drop(init_sender);
+
+        for _ in 0..self.threads {
+            match init_receiver.recv() {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => return Err(e),
+                Err(_) => return Err(ContextError::ContextCreationFailed),
+            }
+        }
+        Ok(pool)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_worker_pool_map() {
+        let pool = WorkerPool::builder(Context::builder)
+            .threads(3)
+            .prelude("function square(x) { return x * x; }", "square.js")
+            .build()
+            .unwrap();
+        assert_eq!(pool.threads(), 3);
+
+        let results = pool.map("square", 0..100);
+        let expected = (0..100)
+            .map(|x| Ok(JsValue::Int(x * x)))
+            .collect::<Vec<_>>();
+        assert_eq!(results, expected);
+    }
+
+    #[test]
+    fn test_worker_pool_submit() {
+        let pool = WorkerPool::builder(Context::builder)
+            .threads(2)
+            .init(|ctx| ctx.add_callback("double", |x: i32| x * 2))
+            .build()
+            .unwrap();
+
+        let reply = pool.submit("double", vec![21]);
+        assert_eq!(reply.wait(), Ok(JsValue::Int(42)));
+
+        let reply = pool.submit("missing", vec![1]);
+        assert!(matches!(reply.wait(), Err(ThreadedError::Execution(_))));
+    }
+
+    #[test]
+    fn test_worker_pool_prelude_error() {
+        let res = WorkerPool::builder(Context::builder)
+            .prelude("function (", "broken.js")
+            .build();
+        assert!(matches!(res, Err(ContextError::Execution(_))));
+    }
+
+    #[test]
+    fn test_worker_pool_init_error() {
+        let res = WorkerPool::builder(Context::builder)
+            .init(|ctx| ctx.eval(" throw 1 ").map(|_| ()))
+            .build();
+        assert!(matches!(res, Err(ContextError::Execution(_))));
+    }
+
+    #[test]
+    fn test_worker_pool_out_of_memory_restarts_worker() {
+        let pool = WorkerPool::builder(|| Context::builder().memory_limit(1_000_000))
+            .threads(1)
+            .prelude(
+                r#"
+                    var state = 0;
+                    function bump() { return ++state; }
+                    function exhaust() {
+                        var s = "x";
+                        while (true) { s += s; }
+                    }
+                "#,
+                "oom.js",
+            )
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            pool.submit("bump", None::<JsValue>).wait(),
+            Ok(JsValue::Int(1))
+        );
+        assert_eq!(
+            pool.submit("exhaust", None::<JsValue>).wait(),
+            Err(ThreadedError::Execution(ExecutionError::OutOfMemory))
+        );
+        // The worker continues with a fresh context.
+        assert_eq!(
+            pool.submit("bump", None::<JsValue>).wait(),
+            Ok(JsValue::Int(1))
+        );
+        assert_eq!(pool.restart_count(), 1);
+    }
+
+    #[test]
+    fn test_worker_pool_thrown_out_of_memory_message_keeps_worker() {
+        let pool = WorkerPool::builder(Context::builder)
+            .threads(1)
+            .prelude(
+                r#"
+                    var state = 0;
+                    function bump() { return ++state; }
+                    function fake() { throw new InternalError("out of memory"); }
+                    function fakeString() { throw "out of memory"; }
+                "#,
+                "fake.js",
+            )
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            pool.submit("bump", None::<JsValue>).wait(),
+            Ok(JsValue::Int(1))
+        );
+        for name in &["fake", "fakeString"] {
+            let res = pool.submit(name, None::<JsValue>).wait();
+            assert!(
+                matches!(
+                    res,
+                    Err(ThreadedError::Execution(ExecutionError::Exception(_)))
+                ),
+                "{:?}",
+                res
+            );
+        }
+        assert_eq!(
+            pool.submit("bump", None::<JsValue>).wait(),
+            Ok(JsValue::Int(2))
+        );
+        assert_eq!(pool.restart_count(), 0);
+    }
+
+    #[test]
+    fn test_worker_pool_rebuild_error() {
+        let builds = AtomicUsize::new(0);
+        let pool = WorkerPool::builder(|| Context::builder().memory_limit(1_000_000))
+            .threads(1)
+            .prelude(
+                r#"
+                    function exhaust() {
+                        var s = "x";
+                        while (true) { s += s; }
+                    }
+                "#,
+                "oom.js",
+            )
+            .init(move |_| match builds.fetch_add(1, Ordering::Relaxed) {
+                1 => Err(ExecutionError::Internal("init failed".into())),
+                _ => Ok(()),
+            })
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            pool.submit("exhaust", None::<JsValue>).wait(),
+            Err(ThreadedError::Execution(ExecutionError::OutOfMemory))
+        );
+        // The failed rebuild is reported to the next task ...
+        assert!(matches!(
+            pool.submit("parseInt", vec!["5"]).wait(),
+            Err(ThreadedError::Execution(ExecutionError::Internal(_)))
+        ));
+        // ... and the worker tries again for the one after.
+        assert_eq!(
+            pool.submit("parseInt", vec!["5"]).wait(),
+            Ok(JsValue::Int(5))
+        );
+    }
+
+    #[test]
+    fn test_worker_pool_shutdown() {
+        let pool = WorkerPool::builder(Context::builder)
+            .threads(1)
+            .build()
+            .unwrap();
+        let pending = pool.submit("parseInt", vec!["5"]);
+        pool.shutdown().unwrap();
+        assert_eq!(pending.wait(), Ok(JsValue::Int(5)));
+    }
+
+    #[test]
+    fn test_worker_pool_drop_interrupts_tasks() {
+        let (started_sender, started) = mpsc::channel::<()>();
+        let started_sender = Mutex::new(started_sender);
+        let pool = WorkerPool::builder(Context::builder)
+            .threads(1)
+            .prelude("function spin() { started(); while (true) {} }", "spin.js")
+            .init(move |ctx| {
+                let sender = Mutex::new(started_sender.lock().unwrap().clone());
+                ctx.add_callback("started", move || {
+                    sender.lock().unwrap().send(()).unwrap();
+                    true
+                })
+            })
+            .build()
+            .unwrap();
+
+        let running = pool.submit("spin", Vec::<JsValue>::new());
+        started.recv().unwrap();
+        let queued = pool.submit("spin", Vec::<JsValue>::new());
+        drop(pool);
+
+        assert!(matches!(
+            running.wait(),
+            Err(ThreadedError::Execution(ExecutionError::Exception(_)))
+        ));
+        assert_eq!(queued.wait(), Err(ThreadedError::Shutdown));
+    }
+}